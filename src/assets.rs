@@ -0,0 +1,345 @@
+use crate::audio::Wav;
+use crate::draw::SpriteAtlas;
+use crate::game::Sprite;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::ops::Not;
+use glam::uvec2;
+use image::RgbaImage;
+
+/// Where assets are unless OBFUSCATION_ASSETS says otherwise
+pub const ASSET_ROOT: &str = "tf/custom";
+/// Manifest file inside the asset root
+pub const MANIFEST_NAME: &str = "assets.txt";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AssetKind { Texture, Sound, Font, Atlas }
+
+impl AssetKind {
+    pub const ALL: [AssetKind; 4] = [AssetKind::Texture, AssetKind::Sound, AssetKind::Font, AssetKind::Atlas];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AssetKind::Texture  => "texture",
+            AssetKind::Sound    => "sound",
+            AssetKind::Font     => "font",
+            AssetKind::Atlas    => "atlas"
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<AssetKind> {
+        AssetKind::ALL.into_iter().find(|k| k.name() == name)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AssetEntry {
+    pub kind:   AssetKind,
+    pub key:    String,
+    /// relative to the manifest's root
+    pub path:   String
+}
+
+/// Every file the game loads, by kind and key. See tf/custom/assets.txt for the format.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Manifest {
+    pub root:       String,
+    pub entries:    Vec<AssetEntry>
+}
+
+#[derive(Debug)]
+pub enum ManifestError {
+    Io(String, std::io::Error),
+    Parse{ line: usize, msg: String }
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ManifestError::Io(path, err)        => write!(f, "can't read {}: {}", path, err),
+            ManifestError::Parse{ line, msg }   => write!(f, "line {}: {}", line, msg)
+        }
+    }
+}
+
+impl std::error::Error for ManifestError {}
+
+impl Manifest {
+
+    pub fn parse(root: &str, text: &str) -> Result<Manifest, ManifestError> {
+        let mut manifest = Manifest{ root: root.to_owned(), entries: Vec::new() };
+
+        for (i, line) in text.lines().enumerate() {
+            let line_no = i + 1;
+            let err = |msg: String| ManifestError::Parse{ line: line_no, msg };
+
+            let line = line.split('#').next().unwrap().trim();
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let [kind, key, path] = words[..] else {
+                if words.is_empty() { continue } else { return Err(err("expected: <kind> <key> <path>".to_owned())) }
+            };
+
+            let kind = AssetKind::from_name(kind).ok_or_else(|| err(format!("'{}' isn't a kind, expected texture, sound, font, or atlas", kind)))?;
+            if manifest.get(kind, key).is_some() {
+                return Err(err(format!("{} '{}' is already listed", kind.name(), key)));
+            }
+            manifest.entries.push(AssetEntry{ kind, key: key.to_owned(), path: path.to_owned() });
+        }
+        Ok(manifest)
+    }
+
+    /// Reads MANIFEST_NAME inside root
+    pub fn load(root: &str) -> Result<Manifest, ManifestError> {
+        let path = Path::new(root).join(MANIFEST_NAME).to_string_lossy().into_owned();
+        let text = std::fs::read_to_string(&path).map_err(|e| ManifestError::Io(path, e))?;
+        Manifest::parse(root, &text)
+    }
+
+    pub fn get(&self, kind: AssetKind, key: &str) -> Option<&AssetEntry> {
+        self.entries.iter().find(|e| e.kind == kind && e.key == key)
+    }
+
+    /// Path of an entry, including the root
+    pub fn path(&self, entry: &AssetEntry) -> String {
+        Path::new(&self.root).join(&entry.path).to_string_lossy().into_owned()
+    }
+}
+
+/// An asset that couldn't be loaded, and got replaced by a placeholder
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AssetProblem {
+    pub kind:   AssetKind,
+    pub key:    String,
+    /// empty if the key isn't in the manifest at all
+    pub path:   String,
+    pub msg:    String
+}
+
+impl fmt::Display for AssetProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{} '{}': {}", self.kind.name(), self.key, self.msg)
+        } else {
+            write!(f, "{} '{}' ({}): {}", self.kind.name(), self.key, self.path, self.msg)
+        }
+    }
+}
+
+/// Magenta and black checkers, for textures that didn't load
+pub fn placeholder_image() -> RgbaImage {
+    RgbaImage::from_fn(2, 2, |x, y| if (x + y) % 2 == 0 { image::Rgba([255, 0, 255, 255]) } else { image::Rgba([0, 0, 0, 255]) })
+}
+
+/// A hundredth of a second of nothing, for sounds that didn't load
+pub fn silent_wav() -> Wav {
+    Wav{ rate: 44100, channels: 1, samples: vec![0; 441] }
+}
+
+/// Everything in a manifest read from disk and decoded, but not on the GPU yet. Nothing here
+/// fails: anything that can't be loaded gets a placeholder and goes into problems, so they can
+/// all be reported at once instead of stopping at the first.
+pub struct AssetData {
+    pub manifest:   Manifest,
+    pub problems:   Vec<AssetProblem>,
+    textures:       HashMap<String, RgbaImage>,
+    sounds:         HashMap<String, Wav>,
+    fonts:          HashMap<String, Vec<u8>>,
+    /// with a texture for each page
+    atlases:        HashMap<String, (SpriteAtlas, Vec<RgbaImage>)>,
+    silent:         Wav
+}
+
+impl AssetData {
+
+    pub fn read(manifest: Manifest) -> AssetData {
+        let mut data = AssetData{
+            manifest,
+            problems:   Vec::new(),
+            textures:   HashMap::new(),
+            sounds:     HashMap::new(),
+            fonts:      HashMap::new(),
+            atlases:    HashMap::new(),
+            silent:     silent_wav()
+        };
+
+        let mut problems = Vec::new();
+        for entry in data.manifest.entries.clone() {
+            let path = data.manifest.path(&entry);
+            let mut problem = |msg: String| problems.push(AssetProblem{ kind: entry.kind, key: entry.key.clone(), path: path.clone(), msg });
+
+            match entry.kind {
+                AssetKind::Texture => match image::open(&path) {
+                    Ok(img) => { data.textures.insert(entry.key.clone(), img.to_rgba8()); },
+                    Err(e)  => problem(e.to_string())
+                },
+                AssetKind::Sound => match std::fs::read(&path).map_err(|e| e.to_string()).and_then(|b| Wav::parse(&b).map_err(|e| e.to_string())) {
+                    Ok(wav) => { data.sounds.insert(entry.key.clone(), wav); },
+                    Err(e)  => problem(e)
+                },
+                AssetKind::Font => match std::fs::read(&path) {
+                    Ok(bytes)   => { data.fonts.insert(entry.key.clone(), bytes); },
+                    Err(e)      => problem(e.to_string())
+                },
+                AssetKind::Atlas => match SpriteAtlas::load(&path) {
+                    Ok(mut atlas) => {
+                        let mut pages = Vec::with_capacity(atlas.pages.len());
+                        for page in &atlas.pages {
+                            pages.push(image::open(&page.path).map(|img| img.to_rgba8()).unwrap_or_else(|e| {
+                                problem(format!("page '{}' ({}): {}", page.name, page.path, e));
+                                placeholder_image()
+                            }));
+                        }
+                        let img = placeholder_image();
+                        atlas.add_placeholder(uvec2(img.width(), img.height()));
+                        pages.push(img);
+                        data.atlases.insert(entry.key.clone(), (atlas, pages));
+                    },
+                    Err(e) => problem(e.to_string())
+                }
+            }
+        }
+        data.problems = problems;
+        data
+    }
+
+    /// For things that go wrong after reading, like a font that doesn't parse. Only the first
+    /// problem with each asset is kept.
+    pub fn problem(&mut self, kind: AssetKind, key: &str, msg: String) {
+        if self.problems.iter().any(|p| p.kind == kind && p.key == key) {
+            return;
+        }
+        let path = self.manifest.get(kind, key).map(|entry| self.manifest.path(entry)).unwrap_or_default();
+        self.problems.push(AssetProblem{ kind, key: key.to_owned(), path, msg });
+    }
+
+    fn missing(&mut self, kind: AssetKind, key: &str) {
+        let msg = if self.manifest.get(kind, key).is_some() { "didn't load, using a placeholder" } else { "not in the manifest, using a placeholder" };
+        self.problem(kind, key, msg.to_owned());
+    }
+
+    pub fn texture(&mut self, key: &str) -> RgbaImage {
+        match self.textures.get(key) {
+            Some(img) => img.clone(),
+            None => { self.missing(AssetKind::Texture, key); placeholder_image() }
+        }
+    }
+
+    pub fn sound(&mut self, key: &str) -> &Wav {
+        if self.sounds.contains_key(key).not() {
+            self.missing(AssetKind::Sound, key);
+            return &self.silent;
+        }
+        &self.sounds[key]
+    }
+
+    /// None means use the built in font
+    pub fn font(&mut self, key: &str) -> Option<&[u8]> {
+        if self.fonts.contains_key(key).not() {
+            self.missing(AssetKind::Font, key);
+            return None;
+        }
+        Some(&self.fonts[key])
+    }
+
+    /// An atlas and a texture for each of its pages
+    pub fn atlas(&mut self, key: &str) -> (SpriteAtlas, Vec<RgbaImage>) {
+        match self.atlases.get(key) {
+            Some(atlas) => atlas.clone(),
+            None => {
+                self.missing(AssetKind::Atlas, key);
+                let img = placeholder_image();
+                (SpriteAtlas::placeholder(uvec2(img.width(), img.height())), vec![img])
+            }
+        }
+    }
+
+    /// A sprite from the atlas that atlas(key) gave. Each name that isn't there is a problem, and
+    /// gets the atlas's placeholder.
+    pub fn sprite(&mut self, key: &str, atlas: &SpriteAtlas, name: &str) -> Sprite {
+        // an atlas that didn't load at all is already a problem, don't list everything in it too
+        if atlas.contains(name).not() && self.atlases.contains_key(key) {
+            let msg = format!("no sprite named '{}', using a placeholder", name);
+            if self.problems.iter().any(|p| p.kind == AssetKind::Atlas && p.key == key && p.msg == msg).not() {
+                let path = self.manifest.get(AssetKind::Atlas, key).map(|entry| self.manifest.path(entry)).unwrap_or_default();
+                self.problems.push(AssetProblem{ kind: AssetKind::Atlas, key: key.to_owned(), path, msg });
+            }
+        }
+        atlas.get(name).unwrap_or_default()
+    }
+
+    /// Prints every problem, returns true if there were any
+    pub fn report(&self) -> bool {
+        for problem in &self.problems {
+            println!("asset problem: {}", problem);
+        }
+        self.problems.is_empty().not()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_parse() {
+        let manifest = Manifest::parse("root", "
+            # comment
+            sound   step    step.wav
+            font    ui      ui.ttf      # trailing comment
+            sound   ui      ui.wav
+        ").unwrap();
+        assert_eq!(manifest.entries.len(), 3);
+        assert_eq!(manifest.get(AssetKind::Sound, "ui").unwrap().path, "ui.wav");
+        assert_eq!(Path::new(&manifest.path(&manifest.entries[0])), Path::new("root/step.wav"));
+
+        let line_of = |text| match Manifest::parse("", text) {
+            Err(ManifestError::Parse{ line, .. }) => line,
+            _ => 0
+        };
+        assert_eq!(line_of("sound step"), 1);
+        assert_eq!(line_of("\nmusic step step.ogg"), 2);
+        assert_eq!(line_of("sound a a.wav\nsound a b.wav"), 2);
+    }
+
+    #[test]
+    fn test_assets_custom() {
+        let mut data = AssetData::read(Manifest::load(ASSET_ROOT).unwrap());
+        assert_eq!(data.problems, vec![]);
+        assert!(data.font("ui").is_some());
+        assert_eq!(data.sound("step").frames(), 4538 / 2);
+        let (atlas, pages) = data.atlas("sprites");
+        assert_eq!(atlas.pages.len(), pages.len());
+        crate::draw::WorldSprites::from_atlas(&atlas).unwrap();
+        assert_eq!(data.problems, vec![]);
+
+        // everything wrong gets reported, not just the first, and placeholders fill in
+        let mut data = AssetData::read(Manifest::parse(ASSET_ROOT, "
+            sound   gone    gone.wav
+            sound   notwav  sprites.png
+            atlas   bad     bindings.txt
+            font    ui      atkinson.ttf
+        ").unwrap());
+        assert_eq!(data.problems.len(), 3);
+        assert!(data.problems[1].to_string().contains("not a RIFF WAVE"));
+        assert_eq!(data.sound("gone"), &silent_wav());
+        assert_eq!(data.sound("never_listed"), &silent_wav());
+        assert!(data.atlas("bad").0.get("player").is_some());
+        assert_eq!(data.problems.len(), 4);
+        assert_eq!(data.problems[3].path, "");
+
+        // sprites missing from an atlas that did load are each a problem, but only once
+        let mut data = AssetData::read(Manifest::load(ASSET_ROOT).unwrap());
+        let (atlas, pages) = data.atlas("sprites");
+        assert_eq!(data.sprite("sprites", &atlas, "player"), atlas.get("player").unwrap());
+        let missing = data.sprite("sprites", &atlas, "nope");
+        assert_eq!(missing, atlas.fallback.unwrap());
+        assert_eq!(missing.page, pages.len() - 1);
+        data.sprite("sprites", &atlas, "nope");
+        data.sprite("sprites", &atlas, "also_nope");
+        assert_eq!(data.problems.len(), 2);
+        assert!(data.problems[1].to_string().contains("also_nope"));
+    }
+}
@@ -0,0 +1,273 @@
+pub mod wav;
+pub use wav::*;
+
+pub mod music;
+pub use music::*;
+
+use crate::game::*;
+use crate::draw::mq;
+
+use glam::Vec2;
+use std::ops::Not;
+
+/// Sounds that get their own volume setting
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SoundCategory { Interface, Player, World, Music }
+
+impl SoundCategory {
+    pub const COUNT: usize = 4;
+
+    pub fn of(kind: SoundKind) -> SoundCategory {
+        match kind {
+            SoundKind::Pickup | SoundKind::Place | SoundKind::Craft | SoundKind::Reload => SoundCategory::Interface,
+            SoundKind::Step | SoundKind::PlayerShoot                                    => SoundCategory::Player,
+            _                                                                           => SoundCategory::World
+        }
+    }
+}
+
+/// Sounds that cut themselves off instead of piling up when played again
+pub fn restarts(kind: SoundKind) -> bool {
+    matches!(kind, SoundKind::PlayerShoot)
+}
+
+/// Quieter than this isn't worth a voice
+const MIN_VOLUME: f32 = 0.01;
+
+/// One sound to start playing
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Play {
+    pub kind:       SoundKind,
+    pub volume:     f32,
+    /// -1 is all the way left, 1 is all the way right
+    pub pan:        f32,
+    /// stop other plays of the same sound first
+    pub restart:    bool
+}
+
+/// Turns AudioEvents into sounds to play. Doesn't touch any actual audio, so it can be tested.
+#[derive(Clone, Debug)]
+pub struct Mixer {
+    pub master:     f32,
+    /// by SoundCategory
+    pub volumes:    [f32; SoundCategory::COUNT],
+    /// sounds playing at once, at most
+    pub max_voices: usize,
+    /// plays of the same sound at once, at most
+    pub max_same:   usize,
+    /// tiles away from the listener where sounds fade out completely
+    pub range:      f32,
+    /// tiles to the side where sounds are all the way in one ear
+    pub pan_range:  f32,
+    /// sounds playing and their time left in seconds
+    voices:         Vec<(SoundKind, f32)>
+}
+
+impl Default for Mixer {
+    fn default() -> Self {
+        Mixer{
+            master:     1.0,
+            volumes:    [1.0; SoundCategory::COUNT],
+            max_voices: 16,
+            max_same:   3,
+            range:      12.0,
+            pan_range:  8.0,
+            voices:     Vec::new()
+        }
+    }
+}
+
+impl Mixer {
+
+    pub fn volume(&self, category: SoundCategory) -> f32 {
+        self.master * self.volumes[category as usize]
+    }
+
+    /// Volume and pan of an event heard from listener (world pixels)
+    pub fn volume_pan(&self, event: &AudioEvent, listener: Vec2) -> (f32, f32) {
+        let volume = event.intensity * self.volume(SoundCategory::of(event.kind));
+        match event.pos {
+            None => (volume, 0.0),
+            Some(pos) => {
+                let offset = (pos - listener) / TILE_SIZE;
+                let falloff = (1.0 - offset.length() / self.range).clamp(0.0, 1.0);
+                (volume * falloff, (offset.x / self.pan_range).clamp(-1.0, 1.0))
+            }
+        }
+    }
+
+    pub fn voices(&self) -> usize {
+        self.voices.len()
+    }
+
+    /// Sounds to start this frame. length is how many seconds each sound lasts, so voices can be
+    /// freed up when they're done.
+    pub fn mix(&mut self, events: &[AudioEvent], listener: Vec2, delta: f32, length: impl Fn(SoundKind) -> f32) -> Vec<Play> {
+        for voice in &mut self.voices {
+            voice.1 -= delta;
+        }
+        self.voices.retain(|v| v.1 > 0.0);
+
+        let mut plays: Vec<Play> = events.iter()
+            .map(|e| {
+                let (volume, pan) = self.volume_pan(e, listener);
+                Play{ kind: e.kind, volume: volume.min(1.0), pan, restart: restarts(e.kind) }
+            })
+            .filter(|p| p.volume > MIN_VOLUME)
+            .collect();
+
+        // loudest first, so the quiet ones are what gets dropped
+        plays.sort_by(|a, b| b.volume.partial_cmp(&a.volume).unwrap());
+
+        let mut out: Vec<Play> = Vec::new();
+        for play in plays {
+            // the same sound twice in one frame just sounds like one louder sound
+            if out.iter().any(|p| p.kind == play.kind) {
+                continue;
+            }
+            if play.restart {
+                self.voices.retain(|v| v.0 != play.kind);
+            } else if self.voices.iter().filter(|v| v.0 == play.kind).count() >= self.max_same {
+                continue;
+            }
+            if self.voices.len() >= self.max_voices {
+                continue;
+            }
+            self.voices.push((play.kind, length(play.kind)));
+            out.push(play);
+        }
+        out
+    }
+}
+
+/// Steps between all the way left and all the way right. macroquad can't pan, so each sound is
+/// loaded once per step, already panned.
+pub const PAN_STEPS: usize = 5;
+
+/// Plays AudioEvents and music through macroquad
+pub struct Audio {
+    pub mixer:  Mixer,
+    pub music:  Music,
+    /// by SoundKind, then variant, then pan step
+    bank:       Vec<Vec<Vec<mq::Sound>>>,
+    /// seconds, by SoundKind
+    lengths:    [f32; SoundKind::COUNT],
+    /// by STEMS, all looping the whole time at different volumes
+    stems:      Vec<mq::Sound>,
+    stems_playing: bool
+}
+
+impl Default for Audio {
+    fn default() -> Self {
+        Audio{
+            mixer:      Default::default(),
+            music:      Default::default(),
+            bank:       vec![Vec::new(); SoundKind::COUNT],
+            lengths:    [1.0; SoundKind::COUNT],
+            stems:      Vec::new(),
+            stems_playing: false
+        }
+    }
+}
+
+impl Audio {
+
+    /// Adds a variant of a sound. Each play picks one of them randomly. Only mono sounds get
+    /// panned, anything else plays in the middle.
+    pub async fn add(&mut self, kind: SoundKind, wav: &Wav) {
+        let mut pans = Vec::with_capacity(PAN_STEPS);
+        if wav.channels == 1 {
+            for step in 0..PAN_STEPS {
+                let pan = step as f32 / (PAN_STEPS - 1) as f32 * 2.0 - 1.0;
+                let (left, right) = ((1.0 - pan).min(1.0), (1.0 + pan).min(1.0));
+                let stereo = Wav{
+                    rate:       wav.rate,
+                    channels:   2,
+                    samples:    wav.samples.iter().flat_map(|&s| [(s as f32 * left) as i16, (s as f32 * right) as i16]).collect()
+                };
+                pans.push(mq::load_sound_from_bytes(&stereo.to_bytes()).await.unwrap());
+            }
+        } else {
+            pans.push(mq::load_sound_from_bytes(&wav.to_bytes()).await.unwrap());
+        }
+        self.lengths[kind as usize] = wav.duration();
+        self.bank[kind as usize].push(pans);
+    }
+
+    /// Adds the next of STEMS
+    pub async fn add_stem(&mut self, wav: &Wav) {
+        self.stems.push(mq::load_sound_from_bytes(&wav.to_bytes()).await.unwrap());
+    }
+
+    /// Crossfades the stems towards intensity, see game::Intensity
+    pub fn update_music(&mut self, intensity: f32, delta: f32) {
+        if self.stems_playing.not() {
+            for stem in &self.stems {
+                mq::play_sound(stem, mq::PlaySoundParams{ looped: true, volume: 0.0 });
+            }
+            self.stems_playing = true;
+        }
+        self.music.update(intensity, delta);
+        for (stem, volume) in self.stems.iter().zip(self.music.volumes(self.mixer.volume(SoundCategory::Music))) {
+            mq::set_sound_volume(stem, volume);
+        }
+    }
+
+    /// listener is where the camera is, in world pixels
+    pub fn play(&mut self, events: &[AudioEvent], listener: Vec2, delta: f32) {
+        let lengths = self.lengths;
+        for play in self.mixer.mix(events, listener, delta, |kind| lengths[kind as usize]) {
+            let variants = &self.bank[play.kind as usize];
+            if variants.is_empty() {
+                continue;
+            }
+            if play.restart {
+                variants.iter().flatten().for_each(mq::stop_sound);
+            }
+            let pans = &variants[mq::gen_range(0, variants.len())];
+            let step = ((play.pan + 1.0) * 0.5 * (pans.len() - 1) as f32).round() as usize;
+            mq::play_sound(&pans[step], mq::PlaySoundParams{ looped: false, volume: play.volume });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::vec2;
+
+    #[test]
+    fn test_mixer() {
+        let mut mixer = Mixer::default();
+        let listener = vec2(10.0, 10.0) * TILE_SIZE;
+
+        // fades with distance, pans to the side it's on
+        let (volume, pan) = mixer.volume_pan(&AudioEvent::at(SoundKind::Press, listener + vec2(-6.0, 0.0) * TILE_SIZE, 1.0), listener);
+        assert_eq!((volume, pan), (0.5, -0.75));
+        assert_eq!(mixer.volume_pan(&AudioEvent::at(SoundKind::Press, listener + vec2(0.0, 20.0) * TILE_SIZE, 1.0), listener).0, 0.0);
+        assert_eq!(mixer.volume_pan(&AudioEvent::flat(SoundKind::Craft, 0.5), listener), (0.5, 0.0));
+
+        // 50 turrets going off at once are one sound
+        let shots: Vec<AudioEvent> = (0..50).map(|i| AudioEvent::at(SoundKind::TurretShoot, listener + vec2(i as f32, 0.0), 1.0)).collect();
+        assert_eq!(mixer.mix(&shots, listener, 0.0, |_| 1.0).len(), 1);
+        // and they stop piling up across frames
+        for _ in 0..10 {
+            mixer.mix(&shots, listener, 0.1, |_| 1.0);
+        }
+        assert_eq!(mixer.voices(), mixer.max_same);
+        // until they finish
+        mixer.mix(&[], listener, 1.0, |_| 1.0);
+        assert_eq!(mixer.voices(), 0);
+
+        // category volume
+        mixer.volumes[SoundCategory::Interface as usize] = 0.0;
+        assert!(mixer.mix(&[AudioEvent::flat(SoundKind::Pickup, 1.0)], listener, 0.0, |_| 1.0).is_empty());
+
+        // restarting sounds replace themselves instead of adding voices
+        for _ in 0..5 {
+            let plays = mixer.mix(&[AudioEvent::flat(SoundKind::PlayerShoot, 0.5)], listener, 0.01, |_| 1.0);
+            assert!(plays[0].restart);
+        }
+        assert_eq!(mixer.voices(), 1);
+    }
+}
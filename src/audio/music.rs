@@ -0,0 +1,140 @@
+use crate::audio::Wav;
+
+use std::f32::consts::TAU;
+
+/// Seconds in one loop of the built in stems, 4 bars at 120 bpm
+pub const MUSIC_LOOP: f32 = 8.0;
+const MUSIC_RATE: u32 = 22050;
+const BEAT: f32 = 0.5;
+
+/// One looping layer of the soundtrack
+#[derive(Clone, Copy, Debug)]
+pub struct Stem {
+    /// sound in the asset manifest that replaces the built in one
+    pub key:        &'static str,
+    /// intensity where this is all the way in. It fades in over STEM_FADE below that.
+    pub full_at:    f32
+}
+
+pub const STEMS: [Stem; 3] = [
+    Stem{ key: "music_ambient", full_at: 0.0 },
+    Stem{ key: "music_pulse",   full_at: 0.4 },
+    Stem{ key: "music_drums",   full_at: 0.75 }
+];
+
+/// Intensity range that each stem fades in over
+pub const STEM_FADE: f32 = 0.25;
+
+/// Volume of each stem, crossfading to follow game intensity
+#[derive(Clone, Debug)]
+pub struct Music {
+    pub muted:      bool,
+    /// how much a stem's gain can change per second
+    pub fade_rate:  f32,
+    gains:          [f32; STEMS.len()]
+}
+
+impl Default for Music {
+    fn default() -> Self {
+        Music{ muted: false, fade_rate: 0.25, gains: [0.0; STEMS.len()] }
+    }
+}
+
+impl Music {
+
+    /// Gain a stem is heading towards at some intensity
+    pub fn target(stem: &Stem, intensity: f32) -> f32 {
+        ((intensity - stem.full_at) / STEM_FADE + 1.0).clamp(0.0, 1.0)
+    }
+
+    pub fn update(&mut self, intensity: f32, delta: f32) {
+        for (gain, stem) in self.gains.iter_mut().zip(&STEMS) {
+            let step = self.fade_rate * delta;
+            *gain += (Music::target(stem, intensity) - *gain).clamp(-step, step);
+        }
+    }
+
+    /// What each stem should play at, volume being the music volume setting
+    pub fn volumes(&self, volume: f32) -> [f32; STEMS.len()] {
+        if self.muted {
+            return [0.0; STEMS.len()];
+        }
+        self.gains.map(|g| g * volume)
+    }
+}
+
+/// Stem made up on the spot, so there's music without any music files
+pub fn synth_stem(index: usize) -> Wav {
+    match index {
+        // low drone that swells every 4 seconds
+        0 => synth(|t| {
+            let swell = 0.6 + 0.4 * (TAU * t / 4.0).sin();
+            swell * (0.5 * (TAU * 55.0 * t).sin() + 0.3 * (TAU * 82.5 * t).sin() + 0.2 * (TAU * 110.0 * t).sin())
+        }),
+        // bass eighth notes, one note a bar
+        1 => synth(|t| {
+            let note = [55.0, 55.0, 65.41, 49.0][(t / (BEAT * 4.0)) as usize % 4];
+            let te = t % (BEAT / 2.0);
+            let env = (1.0 - te / (BEAT / 2.0)).powi(2);
+            env * ((TAU * note * te).sin() + 0.3 * (TAU * note * 2.0 * te).sin())
+        }),
+        // kick on the beat, hat between
+        _ => synth(|t| {
+            let te = t % BEAT;
+            let kick = (TAU * (45.0 * te + 2.5 * (1.0 - (-30.0 * te).exp()))).sin() * (-8.0 * te).exp();
+            let hat = if te < BEAT / 2.0 { 0.0 } else { noise(t) * (-40.0 * (te - BEAT / 2.0)).exp() * 0.4 };
+            kick + hat
+        })
+    }
+}
+
+fn synth(f: impl Fn(f32) -> f32) -> Wav {
+    let frames = (MUSIC_LOOP * MUSIC_RATE as f32) as usize;
+    let samples = (0..frames)
+        .map(|i| (f(i as f32 / MUSIC_RATE as f32).clamp(-1.0, 1.0) * 0.3 * i16::MAX as f32) as i16)
+        .collect();
+    Wav{ rate: MUSIC_RATE, channels: 1, samples }
+}
+
+/// -1 to 1, the same every time for the same t
+fn noise(t: f32) -> f32 {
+    let n = ((t * MUSIC_RATE as f32) as u32).wrapping_mul(1103515245).wrapping_add(12345);
+    ((n >> 16) & 0x7fff) as f32 / 16384.0 - 1.0
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_music() {
+        let mut music = Music::default();
+
+        // calm is just the ambient stem, after it fades in
+        music.update(0.0, 1.0);
+        assert_eq!(music.volumes(1.0), [0.25, 0.0, 0.0]);
+        for _ in 0..10 {
+            music.update(0.0, 1.0);
+        }
+        assert_eq!(music.volumes(0.5), [0.5, 0.0, 0.0]);
+
+        // crossfades in the rest as intensity rises
+        assert!((Music::target(&STEMS[1], 0.275) - 0.5).abs() < 0.001);
+        music.update(1.0, 1.0);
+        assert_eq!(music.volumes(1.0), [1.0, 0.25, 0.25]);
+        for _ in 0..10 {
+            music.update(1.0, 1.0);
+        }
+        assert_eq!(music.volumes(1.0), [1.0, 1.0, 1.0]);
+
+        music.muted = true;
+        assert_eq!(music.volumes(1.0), [0.0; 3]);
+
+        for i in 0..STEMS.len() {
+            let wav = synth_stem(i);
+            assert!((wav.duration() - MUSIC_LOOP).abs() < 0.001);
+            assert!(wav.samples.iter().any(|&s| s.abs() > 1000));
+        }
+    }
+}
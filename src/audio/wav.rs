@@ -0,0 +1,257 @@
+use std::fmt;
+
+const FORMAT_PCM:           u16 = 1;
+const FORMAT_FLOAT:         u16 = 3;
+const FORMAT_EXTENSIBLE:    u16 = 0xFFFE;
+
+/// Decoded PCM audio, converted to 16 bits. Samples are interleaved by channel.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Wav {
+    pub rate:       u32,
+    pub channels:   u16,
+    pub samples:    Vec<i16>
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WavError {
+    /// Doesn't start with RIFF....WAVE
+    NotWav,
+    /// A chunk claims to be longer than the file
+    Truncated{ chunk: [u8; 4], at: usize },
+    /// No fmt chunk before the data chunk
+    MissingFormat,
+    MissingData,
+    /// Only integer PCM (8, 16, 24, 32 bit) and 32-bit float are supported
+    Unsupported{ format: u16, bits: u16 },
+    /// The fmt chunk makes no sense, like 0 channels
+    BadFormat(&'static str)
+}
+
+impl fmt::Display for WavError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WavError::NotWav                    => write!(f, "not a RIFF WAVE file"),
+            WavError::Truncated{ chunk, at }    => write!(f, "'{}' chunk at byte {} runs past the end of the file", String::from_utf8_lossy(chunk), at),
+            WavError::MissingFormat             => write!(f, "no fmt chunk before the data"),
+            WavError::MissingData               => write!(f, "no data chunk"),
+            WavError::Unsupported{ format, bits } => write!(f, "unsupported format {:#06x} with {} bits per sample, expected PCM or 32-bit float", format, bits),
+            WavError::BadFormat(msg)            => write!(f, "bad fmt chunk: {}", msg)
+        }
+    }
+}
+
+impl std::error::Error for WavError {}
+
+struct Format {
+    tag:        u16,
+    channels:   u16,
+    rate:       u32,
+    align:      u16,
+    bits:       u16
+}
+
+impl Format {
+    fn parse(body: &[u8]) -> Result<Format, WavError> {
+        if body.len() < 16 {
+            return Err(WavError::BadFormat("shorter than 16 bytes"));
+        }
+        let u16_at = |i: usize| u16::from_le_bytes([body[i], body[i + 1]]);
+        let mut format = Format{
+            tag:        u16_at(0),
+            channels:   u16_at(2),
+            rate:       u32::from_le_bytes([body[4], body[5], body[6], body[7]]),
+            align:      u16_at(12),
+            bits:       u16_at(14)
+        };
+        // the real format is the first two bytes of the sub format GUID
+        if format.tag == FORMAT_EXTENSIBLE {
+            if body.len() < 26 {
+                return Err(WavError::BadFormat("extensible format without a sub format"));
+            }
+            format.tag = u16_at(24);
+        }
+        if format.channels == 0 {
+            return Err(WavError::BadFormat("no channels"));
+        }
+        if format.rate == 0 {
+            return Err(WavError::BadFormat("sample rate of 0"));
+        }
+        match (format.tag, format.bits) {
+            (FORMAT_PCM, 8 | 16 | 24 | 32) | (FORMAT_FLOAT, 32) => {},
+            (format, bits) => return Err(WavError::Unsupported{ format, bits })
+        }
+        if format.align as u32 != format.channels as u32 * format.bits as u32 / 8 {
+            return Err(WavError::BadFormat("block align doesn't match channels and bits"));
+        }
+        Ok(format)
+    }
+
+    fn sample(&self, b: &[u8]) -> i16 {
+        match (self.tag, self.bits) {
+            (FORMAT_PCM, 8)     => ((b[0] as i16) - 128) << 8,
+            (FORMAT_PCM, 16)    => i16::from_le_bytes([b[0], b[1]]),
+            (FORMAT_PCM, 24)    => i16::from_le_bytes([b[1], b[2]]),
+            (FORMAT_PCM, 32)    => i16::from_le_bytes([b[2], b[3]]),
+            _                   => (f32::from_le_bytes([b[0], b[1], b[2], b[3]]).clamp(-1.0, 1.0) * i16::MAX as f32) as i16
+        }
+    }
+}
+
+impl Wav {
+
+    /// Walks the RIFF chunks, skipping any it doesn't know (LIST, id3, padding...)
+    pub fn parse(bytes: &[u8]) -> Result<Wav, WavError> {
+        if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+            return Err(WavError::NotWav);
+        }
+        let mut format = None;
+        let mut at = 12;
+        while at + 8 <= bytes.len() {
+            let chunk = [bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]];
+            let size = u32::from_le_bytes([bytes[at + 4], bytes[at + 5], bytes[at + 6], bytes[at + 7]]) as usize;
+            let body = bytes.get(at + 8..at + 8 + size).ok_or(WavError::Truncated{ chunk, at })?;
+            match &chunk {
+                b"fmt " => format = Some(Format::parse(body)?),
+                b"data" => {
+                    let format = format.ok_or(WavError::MissingFormat)?;
+                    let width = format.bits as usize / 8;
+                    return Ok(Wav{
+                        rate:       format.rate,
+                        channels:   format.channels,
+                        // a partial frame at the end gets dropped
+                        samples:    body[..body.len() - body.len() % format.align as usize]
+                            .chunks_exact(width)
+                            .map(|b| format.sample(b))
+                            .collect()
+                    });
+                },
+                _ => {}
+            }
+            // chunks are padded to even sizes
+            at += 8 + size + (size & 1);
+        }
+        Err(WavError::MissingData)
+    }
+
+    /// Canonical 16-bit PCM WAV file
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let data_size = self.samples.len() as u32 * 2;
+        let mut out = Vec::with_capacity(44 + data_size as usize);
+        out.extend_from_slice(b"RIFF");
+        out.extend_from_slice(&(36 + data_size).to_le_bytes());
+        out.extend_from_slice(b"WAVEfmt ");
+        out.extend_from_slice(&16_u32.to_le_bytes());
+        out.extend_from_slice(&FORMAT_PCM.to_le_bytes());
+        out.extend_from_slice(&self.channels.to_le_bytes());
+        out.extend_from_slice(&self.rate.to_le_bytes());
+        out.extend_from_slice(&(self.rate * self.channels as u32 * 2).to_le_bytes());
+        out.extend_from_slice(&(self.channels * 2).to_le_bytes());
+        out.extend_from_slice(&16_u16.to_le_bytes());
+        out.extend_from_slice(b"data");
+        out.extend_from_slice(&data_size.to_le_bytes());
+        out.extend(self.samples.iter().flat_map(|s| s.to_le_bytes()));
+        out
+    }
+
+    /// Samples per channel
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels as usize
+    }
+
+    /// Seconds
+    pub fn duration(&self) -> f32 {
+        self.frames() as f32 / self.rate as f32
+    }
+
+    /// Same sound at a different sample rate, linearly interpolated
+    pub fn resample(&self, rate: u32) -> Wav {
+        self.stretch(rate as f64 / self.rate as f64, rate)
+    }
+
+    /// Same sample rate, but played factor times faster and higher. 2.0 is an octave up.
+    pub fn pitch(&self, factor: f32) -> Wav {
+        self.stretch(1.0 / factor as f64, self.rate)
+    }
+
+    /// Makes length*scale frames out of this one
+    fn stretch(&self, scale: f64, rate: u32) -> Wav {
+        let channels = self.channels as usize;
+        let frames = self.frames();
+        let out_frames = (frames as f64 * scale).round() as usize;
+        let mut samples = Vec::with_capacity(out_frames * channels);
+        for i in 0..out_frames {
+            let src = i as f64 / scale;
+            let a = (src as usize).min(frames - 1);
+            let b = (a + 1).min(frames - 1);
+            let t = (src - a as f64) as f32;
+            for c in 0..channels {
+                let (sa, sb) = (self.samples[a * channels + c] as f32, self.samples[b * channels + c] as f32);
+                samples.push((sa + (sb - sa) * t).round() as i16);
+            }
+        }
+        Wav{ rate, channels: self.channels, samples }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ops::Not;
+
+    #[test]
+    fn test_wav_files() {
+        for entry in std::fs::read_dir("tf/custom").unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|e| e == "wav").not() {
+                continue;
+            }
+            let bytes = std::fs::read(&path).unwrap();
+            let wav = Wav::parse(&bytes).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+            assert_eq!((wav.rate, wav.channels), (44100, 1), "{}", path.display());
+            assert!(wav.frames() > 0, "{}", path.display());
+
+            // round trips through to_bytes
+            assert_eq!(Wav::parse(&wav.to_bytes()).unwrap(), wav);
+
+            let octave = wav.pitch(2.0);
+            assert_eq!(octave.rate, 44100);
+            assert!(octave.frames().abs_diff(wav.frames() / 2) <= 1);
+            let half = wav.resample(22050);
+            assert!((half.duration() - wav.duration()).abs() < 0.001);
+        }
+
+        // step.wav has a pile of zeroed chunks after data
+        let step = Wav::parse(&std::fs::read("tf/custom/step.wav").unwrap()).unwrap();
+        assert_eq!(step.frames(), 4538 / 2);
+    }
+
+    #[test]
+    fn test_wav_errors() {
+        let wav = Wav{ rate: 8000, channels: 2, samples: vec![0, 100, -100, 200, 300, -300] };
+        let bytes = wav.to_bytes();
+        assert_eq!(Wav::parse(&bytes).unwrap(), wav);
+
+        assert_eq!(Wav::parse(b"RIFF\0\0\0\0AVI "), Err(WavError::NotWav));
+        assert_eq!(Wav::parse(&bytes[..bytes.len() - 2]), Err(WavError::Truncated{ chunk: *b"data", at: 36 }));
+        assert_eq!(Wav::parse(&bytes[..36]), Err(WavError::MissingData));
+
+        // 8-bit unsigned, and something that isn't PCM
+        let mut eight = bytes.clone();
+        eight[32..36].copy_from_slice(&[2, 0, 8, 0]);
+        assert_eq!(Wav::parse(&eight).unwrap().samples[..3], [-128 << 8, -128 << 8, (100 - 128) << 8]);
+        let mut adpcm = bytes.clone();
+        adpcm[20] = 2;
+        assert_eq!(Wav::parse(&adpcm), Err(WavError::Unsupported{ format: 2, bits: 16 }));
+        assert!(WavError::Unsupported{ format: 2, bits: 16 }.to_string().contains("0x0002"));
+
+        // channels * bits doesn't fit in a u16
+        let mut wide = bytes.clone();
+        wide[22..24].copy_from_slice(&[0xFF, 0xFF]);
+        assert!(matches!(Wav::parse(&wide), Err(WavError::BadFormat(_))));
+
+        // interpolates between frames, per channel
+        let up = Wav{ rate: 1, channels: 1, samples: vec![0, 100] }.resample(2);
+        assert_eq!(up.samples, vec![0, 50, 100, 100]);
+    }
+}
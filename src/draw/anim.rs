@@ -0,0 +1,266 @@
+use crate::game::*;
+use crate::draw::WorldSprites;
+
+use std::collections::HashMap;
+use std::ops::Not;
+use glam::{vec2, Vec2, Mat2};
+
+/// Something for the game to react to, like playing a sound when a foot hits the ground
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnimEvent {
+    Step
+}
+
+/// One frame of a clip. offset is in sprite-sized units along the sprite's own axes, and scale and
+/// rotation are applied on top of however the sprite is already transformed.
+#[derive(Clone, Copy, Debug)]
+pub struct AnimFrame {
+    pub sprite:     Sprite,
+    pub duration:   f32,
+    pub offset:     Vec2,
+    pub scale:      f32,
+    pub rotation:   f32,
+    pub alpha:      f32,
+    /// sent when this frame starts
+    pub event:      Option<AnimEvent>
+}
+
+impl AnimFrame {
+    pub fn new(sprite: Sprite, duration: f32) -> AnimFrame {
+        AnimFrame{ sprite, duration, offset: Vec2::ZERO, scale: 1.0, rotation: 0.0, alpha: 1.0, event: None }
+    }
+
+    /// Returns the new position and transform of a sprite drawn at pos with tf
+    pub fn apply(&self, pos: Vec2, tf: Mat2) -> (Vec2, Mat2) {
+        (pos + tf.mul_vec2(self.offset), tf * Mat2::from_scale_angle(Vec2::splat(self.scale), self.rotation))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct AnimClip {
+    pub frames:     Vec<AnimFrame>,
+    pub looping:    bool
+}
+
+impl AnimClip {
+    pub fn length(&self) -> f32 {
+        self.frames.iter().map(|f| f.duration).sum()
+    }
+
+    /// Frame showing at time. Clips that don't loop stay on their last frame once they're done.
+    pub fn frame_at(&self, time: f32) -> &AnimFrame {
+        let mut start = 0.0;
+        for frame in &self.frames {
+            start += frame.duration;
+            if time < start {
+                return frame;
+            }
+        }
+        self.frames.last().unwrap()
+    }
+
+    fn events_between(&self, from: f32, to: f32, events: &mut Vec<AnimEvent>) {
+        let mut start = 0.0;
+        for frame in &self.frames {
+            if from <= start && start < to {
+                events.extend(frame.event);
+            }
+            start += frame.duration;
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ClipId(pub usize);
+
+/// Clips, addressable by name
+#[derive(Clone, Debug, Default)]
+pub struct AnimLibrary {
+    clips:      Vec<AnimClip>,
+    by_name:    HashMap<&'static str, ClipId>
+}
+
+impl AnimLibrary {
+    pub fn add(&mut self, name: &'static str, clip: AnimClip) -> ClipId {
+        assert!(clip.frames.is_empty().not(), "clip '{}' has no frames", name);
+        let id = ClipId(self.clips.len());
+        self.clips.push(clip);
+        self.by_name.insert(name, id);
+        id
+    }
+
+    pub fn get(&self, name: &str) -> Option<ClipId> {
+        self.by_name.get(name).copied()
+    }
+
+    pub fn clip(&self, id: ClipId) -> &AnimClip {
+        &self.clips[id.0]
+    }
+}
+
+/// A playing clip, one for each thing being animated
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Anim {
+    pub clip:   ClipId,
+    pub time:   f32
+}
+
+impl Anim {
+    pub fn new(clip: ClipId) -> Anim {
+        Anim{ clip, time: 0.0 }
+    }
+
+    /// Already played through, so it just shows the last frame
+    pub fn ended(clip: ClipId) -> Anim {
+        Anim{ clip, time: f32::INFINITY }
+    }
+
+    pub fn play(&mut self, clip: ClipId) {
+        *self = Anim::new(clip);
+    }
+
+    pub fn frame<'a>(&self, lib: &'a AnimLibrary) -> &'a AnimFrame {
+        lib.clip(self.clip).frame_at(self.time)
+    }
+
+    pub fn finished(&self, lib: &AnimLibrary) -> bool {
+        let clip = lib.clip(self.clip);
+        clip.looping.not() && self.time >= clip.length()
+    }
+
+    /// Moves time forward, adding events of any frames that started along the way
+    pub fn advance(&mut self, lib: &AnimLibrary, delta: f32, events: &mut Vec<AnimEvent>) {
+        let clip = lib.clip(self.clip);
+        let length = clip.length();
+
+        if self.time >= length && clip.looping.not() {
+            return;
+        }
+
+        let to = self.time + delta;
+        if to < length {
+            clip.events_between(self.time, to, events);
+            self.time = to;
+        } else if clip.looping && length > 0.0 {
+            clip.events_between(self.time, length, events);
+            let to = (to - length) % length;
+            clip.events_between(0.0, to, events);
+            self.time = to;
+        } else {
+            clip.events_between(self.time, length, events);
+            self.time = length;
+        }
+    }
+}
+
+/// Clips used by draw_world
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WorldClips {
+    pub drone:          ClipId,
+    pub player_hop:     ClipId,
+    pub turret_idle:    ClipId,
+    pub turret_fire:    ClipId,
+    pub conveyor:       ClipId,
+    pub filterveyor:    ClipId,
+    pub obfuscate:      ClipId
+}
+
+impl WorldClips {
+    pub fn new(s: &WorldSprites, lib: &mut AnimLibrary) -> WorldClips {
+        let still = |sprite| AnimClip{ frames: vec![AnimFrame::new(sprite, 1.0)], looping: true };
+
+        // hop up and back down while leaning forward, ends on the ground
+        let player_hop = [(0.36, 0.04), (0.84, 0.12), (1.0, 0.2), (0.84, 0.28), (0.36, 0.36), (0.0, 0.0)].iter()
+            .enumerate()
+            .map(|(i, &(height, rotation))| AnimFrame{
+                offset:     vec2(0.0, -0.55 * height),
+                rotation,
+                event:      (i == 0).then_some(AnimEvent::Step),
+                duration:   if height == 0.0 { 0.0 } else { 0.05 },
+                ..AnimFrame::new(s.player, 0.0)
+            })
+            .collect();
+
+        let turret_fire = [-0.15, -0.1, -0.05, 0.0].iter()
+            .map(|&x| AnimFrame{ offset: vec2(x, 0.0), ..AnimFrame::new(s.turret, 0.04) })
+            .collect();
+
+        let obfuscate = (0..6)
+            .map(|i| AnimFrame{
+                scale:      1.0 + 0.6 * i as f32,
+                rotation:   0.3 * i as f32,
+                alpha:      1.0 - i as f32 / 6.0,
+                ..AnimFrame::new(s.obfuscator, 0.06)
+            })
+            .collect();
+
+        WorldClips{
+            drone:          lib.add("drone", AnimClip{ frames: vec![AnimFrame::new(s.drone[1], 0.125), AnimFrame::new(s.drone[0], 0.125)], looping: true }),
+            player_hop:     lib.add("player_hop", AnimClip{ frames: player_hop, looping: false }),
+            turret_idle:    lib.add("turret_idle", still(s.turret)),
+            turret_fire:    lib.add("turret_fire", AnimClip{ frames: turret_fire, looping: false }),
+            conveyor:       lib.add("conveyor", AnimClip{ frames: vec![AnimFrame::new(s.conveyor[0], 0.1), AnimFrame::new(s.conveyor[1], 0.1)], looping: true }),
+            filterveyor:    lib.add("filterveyor", still(s.filterveyor)),
+            obfuscate:      lib.add("obfuscate", AnimClip{ frames: obfuscate, looping: false })
+        }
+    }
+
+    /// What a machine does when nothing is happening to it
+    pub fn mach_idle(&self, spec: &MachineSpec) -> ClipId {
+        match spec {
+            MachineSpec::Conveyor{filter: true, ..}     => self.filterveyor,
+            MachineSpec::Conveyor{filter: false, ..}    => self.conveyor,
+            _                                           => self.turret_idle
+        }
+    }
+
+    /// If clip is something that a machine with spec can be playing
+    pub fn mach_can_play(&self, spec: &MachineSpec, clip: ClipId) -> bool {
+        clip == self.mach_idle(spec) || (matches!(spec, MachineSpec::Turret{..}) && clip == self.turret_fire)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_anim_advance() {
+        let sprite = |page| Sprite{ page, coord: Default::default() };
+        let mut lib = AnimLibrary::default();
+        let frames = vec![
+            AnimFrame{ event: Some(AnimEvent::Step), ..AnimFrame::new(sprite(0), 1.0) },
+            AnimFrame::new(sprite(1), 2.0)
+        ];
+        let looped = lib.add("looped", AnimClip{ frames: frames.clone(), looping: true });
+        let once = lib.add("once", AnimClip{ frames, looping: false });
+        assert_eq!(lib.get("once"), Some(once));
+
+        let mut events = Vec::new();
+
+        let mut anim = Anim::new(looped);
+        anim.advance(&lib, 0.5, &mut events);
+        assert_eq!(events, [AnimEvent::Step]);
+        assert_eq!(anim.frame(&lib).sprite.page, 0);
+        anim.advance(&lib, 1.0, &mut events);
+        assert_eq!(anim.frame(&lib).sprite.page, 1);
+        assert_eq!(events.len(), 1);
+
+        // wraps around and starts the first frame again
+        anim.advance(&lib, 2.0, &mut events);
+        assert_eq!(events.len(), 2);
+        assert_eq!(anim.frame(&lib).sprite.page, 0);
+        assert!(anim.finished(&lib).not());
+
+        let mut anim = Anim::new(once);
+        anim.advance(&lib, 10.0, &mut events);
+        assert_eq!(events.len(), 3);
+        assert!(anim.finished(&lib));
+        assert_eq!(anim.frame(&lib).sprite.page, 1);
+        anim.advance(&lib, 10.0, &mut events);
+        assert_eq!(events.len(), 3);
+
+        assert!(Anim::ended(once).finished(&lib));
+    }
+}
@@ -0,0 +1,156 @@
+use crate::game::*;
+
+use glam::{uvec2, UVec2, IVec2};
+
+/// Each tile is two characters wide. The first is whatever is on the floor, the second is
+/// whatever's on top of it or extra info:
+///
+/// `→↓←↑` rail, `┐┘┌└` rail bends, `T` turret, `C` conveyor, `F` filterveyor, `i` feral items.
+/// Machines are followed by their direction, feral items by the number of stacks.
+/// `@` player, `D` drone, `*` bullet.
+pub fn ascii_world(main: &GameMain, tl: UVec2, size: UVec2) -> String {
+    let mut out = String::with_capacity(((size.x * 2 + 1) * size.y) as usize);
+
+    let overlay_at = |pos: glam::Vec2| -> Option<UVec2> {
+        let tile = (pos / TILE_SIZE).floor().as_ivec2() - tl.as_ivec2();
+        (0 <= tile.x && tile.x < size.x as i32 && 0 <= tile.y && tile.y < size.y as i32).then(|| tile.as_uvec2())
+    };
+
+    // overlays, lowest priority first
+    let mut overlay: Vec<Option<char>> = vec![None; (size.x * size.y) as usize];
+    let mut put = |tile: Option<UVec2>, c: char| {
+        if let Some(tile) = tile {
+            overlay[(tile.y * size.x + tile.x) as usize] = Some(c);
+        }
+    };
+    for id in main.bullet_ids.iter_ids() {
+        put(overlay_at(main.bullet_pos[id.0]), '*');
+    }
+    for id in main.drone_ids.iter_ids() {
+        put(overlay_at(main.drone_pos[id.0]), 'D');
+    }
+    put(overlay_at(main.player_pos), '@');
+
+    for y in 0..size.y {
+        for x in 0..size.x {
+            let pos = tl + uvec2(x, y);
+            let (floor, extra) = ascii_tile(main, pos);
+            out.push(floor);
+            out.push(overlay[(y * size.x + x) as usize].unwrap_or(extra));
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
+/// Whole world, ready to be printed to a terminal in place of the previous frame
+pub fn ascii_tui_frame(main: &GameMain) -> String {
+    // move cursor to top left and clear screen
+    let mut out = String::from("\x1b[H\x1b[2J");
+    out.push_str(&ascii_world(main, uvec2(0, 0), main.world_size));
+    out
+}
+
+fn ascii_tile(main: &GameMain, pos: UVec2) -> (char, char) {
+    let tile: TilePos = pos.into();
+
+    if pos.x >= main.world_size.x || pos.y >= main.world_size.y {
+        return (' ', ' ');
+    }
+
+    if let Some(mach) = main.mach_by_tile.get(tile) {
+        let d = &main.mach_data[mach.0];
+        let c = match d.spec {
+            MachineSpec::None                               => '?',
+            MachineSpec::Turret{..}                         => 'T',
+            MachineSpec::Conveyor{filter: false, ..}        => 'C',
+            MachineSpec::Conveyor{filter: true, ..}         => 'F',
+        };
+        return (c, dir_arrow(d.dir));
+    }
+
+    let stacks = main.feral_by_tile.get(tile).map(|feral| {
+        let count = main.feral_data[feral.0].slots.iter().filter(|x| x.is_some()).count();
+        char::from_digit(count as u32, 10).unwrap_or('+')
+    });
+
+    if let Some(&rail_idx) = main.rail_by_tile.get(tile) {
+        return (rail_char(&main.rail[rail_idx as usize]), stacks.unwrap_or(' '));
+    }
+
+    match stacks {
+        Some(c) => ('i', c),
+        None    => ('.', ' ')
+    }
+}
+
+pub fn dir_arrow(dir: Dir) -> char {
+    match dir {
+        Dir::Right  => '→',
+        Dir::Down   => '↓',
+        Dir::Left   => '←',
+        Dir::Up     => '↑',
+    }
+}
+
+pub fn rail_char(rail: &Rail) -> char {
+    let enter = -dir_to_ivec2(&rail.dir); // side the rail comes in from
+    let exit = match rail.bend {
+        Bend::Forward => return dir_arrow(rail.dir),
+        Bend::Right   => rot_cw_90(dir_to_vec2(&rail.dir)).as_ivec2(),
+        Bend::Left    => rot_ccw_90(dir_to_vec2(&rail.dir)).as_ivec2()
+    };
+
+    // corners are named by which two sides they connect
+    let sides = enter + exit;
+    match sides {
+        IVec2{x: -1, y:  1} => '┐',
+        IVec2{x: -1, y: -1} => '┘',
+        IVec2{x:  1, y:  1} => '┌',
+        _                   => '└'
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::vec2;
+
+    #[test]
+    fn test_ascii_world() {
+        let mut main = GameMain{ world_size: uvec2(4, 3), player_pos: vec2(-1000.0, -1000.0), ..Default::default() };
+        main.rail = vec![
+            Rail{ pos: uvec2(0, 0), dir: Dir::Right, bend: Bend::Forward },
+            Rail{ pos: uvec2(1, 0), dir: Dir::Right, bend: Bend::Right },
+            Rail{ pos: uvec2(1, 1), dir: Dir::Down,  bend: Bend::Left },
+            Rail{ pos: uvec2(2, 1), dir: Dir::Right, bend: Bend::Left },
+            Rail{ pos: uvec2(2, 0), dir: Dir::Up,    bend: Bend::Right },
+            Rail{ pos: uvec2(3, 0), dir: Dir::Right, bend: Bend::Forward },
+        ];
+        regen_rail_by_tile(&main.rail, &mut main.rail_by_tile);
+
+        main.mach_ids.resize(64);
+        let mach = main.mach_ids.create().unwrap();
+        main.mach_data.resize(64, Default::default());
+        main.mach_data[mach.0] = Machine{ pos: Some(uvec2(0, 2)), dir: Dir::Up, enabled: true, spec: MachineSpec::Turret{ ammo: 0, can_fire_time_us: 0, target: Default::default() } };
+        main.mach_by_tile.insert(tile_pos(0, 2), mach);
+
+        main.itemtype_data.push(Default::default());
+        main.itemtype_data.push(Default::default());
+        main.feral_ids.resize(64);
+        place_item(&main.itemtype_data, &mut main.feral_ids, &mut main.feral_data, &mut main.feral_by_tile, uvec2(3, 2), ItemSlot{ itemtype: ItemTypeId(0), count: 1 }).ok();
+        place_item(&main.itemtype_data, &mut main.feral_ids, &mut main.feral_data, &mut main.feral_by_tile, uvec2(3, 2), ItemSlot{ itemtype: ItemTypeId(1), count: 1 }).ok();
+
+        main.player_pos = vec2(2.5, 2.5) * TILE_SIZE;
+
+        assert_eq!(ascii_world(&main, uvec2(0, 0), main.world_size),
+            "→ ┐ ┌ → \n\
+             . └ ┘ . \n\
+             T↑. .@i2\n");
+
+        // outside of the world is blank
+        assert_eq!(ascii_world(&main, uvec2(3, 2), uvec2(2, 1)), "i2  \n");
+    }
+}
@@ -0,0 +1,252 @@
+use crate::game::Sprite;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use glam::{UVec2, Vec2, uvec2};
+
+/// One texture that sprites are cut out of
+#[derive(Clone, Debug)]
+pub struct AtlasPage {
+    pub name:   String,
+    pub path:   String,
+    pub size:   UVec2,
+    /// cell size and padding in pixels, if sprites can be given as grid coordinates
+    pub grid:   Option<(u32, u32)>
+}
+
+/// Named sprites spread over one or more textures, described by an .atlas file. See
+/// tf/custom/sprites.atlas for the format.
+#[derive(Clone, Debug, Default)]
+pub struct SpriteAtlas {
+    pub pages:      Vec<AtlasPage>,
+    by_name:        HashMap<String, Sprite>,
+    /// given for any name that isn't in the atlas, so a broken atlas still draws something
+    pub fallback:   Option<Sprite>
+}
+
+#[derive(Debug)]
+pub enum AtlasError {
+    Io(String, std::io::Error),
+    Parse{ line: usize, msg: String },
+    Missing(String)
+}
+
+impl fmt::Display for AtlasError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AtlasError::Io(path, err)       => write!(f, "can't read {}: {}", path, err),
+            AtlasError::Parse{ line, msg }  => write!(f, "line {}: {}", line, msg),
+            AtlasError::Missing(name)       => write!(f, "no sprite named '{}'", name)
+        }
+    }
+}
+
+impl std::error::Error for AtlasError {}
+
+impl SpriteAtlas {
+
+    pub fn parse(text: &str) -> Result<SpriteAtlas, AtlasError> {
+        let mut atlas = SpriteAtlas::default();
+
+        for (i, line) in text.lines().enumerate() {
+            let line_no = i + 1;
+            let err = |msg: &str| AtlasError::Parse{ line: line_no, msg: msg.to_owned() };
+
+            let line = line.split('#').next().unwrap().trim();
+            let mut words = line.split_whitespace();
+            let Some(first) = words.next() else { continue };
+
+            let rest: Vec<&str> = words.collect();
+            let num = |s: &str| s.parse::<u32>().map_err(|_| err(&format!("'{}' isn't a number", s)));
+
+            if first == "page" {
+                let grid = match rest.len() {
+                    4 => None,
+                    6 => Some((num(rest[4])?, num(rest[5])?)),
+                    _ => return Err(err("expected: page <name> <path> <width> <height> [<cell> <padding>]"))
+                };
+                let size = uvec2(num(rest[2])?, num(rest[3])?);
+                if size.x == 0 || size.y == 0 {
+                    return Err(err("page size can't be zero"));
+                }
+                atlas.pages.push(AtlasPage{ name: rest[0].to_owned(), path: rest[1].to_owned(), size, grid });
+                continue;
+            }
+
+            let Some(page) = atlas.pages.last() else {
+                return Err(err("sprite before any page"));
+            };
+
+            // top left and size in pixels
+            let (tl, size) = match rest.len() {
+                2 => {
+                    let Some((cell, padding)) = page.grid else {
+                        return Err(err("grid coordinates used on a page without a grid"));
+                    };
+                    (uvec2(num(rest[0])?, num(rest[1])?) * (cell + padding), uvec2(cell, cell))
+                },
+                4 => (uvec2(num(rest[0])?, num(rest[1])?), uvec2(num(rest[2])?, num(rest[3])?)),
+                _ => return Err(err("expected: <sprite> <column> <row>, or <sprite> <x> <y> <width> <height>"))
+            };
+
+            if (tl + size).cmpgt(page.size).any() {
+                return Err(err(&format!("'{}' goes outside of page '{}'", first, page.name)));
+            }
+
+            let page_size = page.size.as_vec2();
+            let sprite = Sprite{
+                page:   atlas.pages.len() - 1,
+                coord:  (tl.as_vec2() / page_size, (tl + size).as_vec2() / page_size)
+            };
+
+            if atlas.by_name.insert(first.to_owned(), sprite).is_some() {
+                return Err(err(&format!("'{}' is already defined", first)));
+            }
+        }
+
+        Ok(atlas)
+    }
+
+    /// Page paths are relative to the .atlas file
+    pub fn load(path: &str) -> Result<SpriteAtlas, AtlasError> {
+        let text = std::fs::read_to_string(path).map_err(|e| AtlasError::Io(path.to_owned(), e))?;
+        let mut atlas = SpriteAtlas::parse(&text)?;
+        let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+        for page in &mut atlas.pages {
+            page.path = dir.join(&page.path).to_string_lossy().into_owned();
+        }
+        Ok(atlas)
+    }
+
+    /// One page with no path, where every sprite is the whole page
+    pub fn placeholder(size: UVec2) -> SpriteAtlas {
+        let mut atlas = SpriteAtlas::default();
+        atlas.add_placeholder(size);
+        atlas
+    }
+
+    /// Adds a page with no path that the fallback covers all of, for sprites that aren't in the
+    /// atlas. Whoever draws needs a texture for it too.
+    pub fn add_placeholder(&mut self, size: UVec2) {
+        self.pages.push(AtlasPage{ name: "placeholder".to_owned(), path: String::new(), size, grid: None });
+        self.fallback = Some(Sprite{ page: self.pages.len() - 1, coord: (Vec2::ZERO, Vec2::ONE) });
+    }
+
+    /// Without counting the fallback
+    pub fn contains(&self, name: &str) -> bool {
+        self.by_name.contains_key(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<Sprite> {
+        self.by_name.get(name).copied().or(self.fallback)
+    }
+
+    pub fn sprite(&self, name: &str) -> Result<Sprite, AtlasError> {
+        self.get(name).ok_or_else(|| AtlasError::Missing(name.to_owned()))
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.by_name.keys().map(|x| x.as_str())
+    }
+}
+
+/// Sprites used by draw_world, looked up once instead of by name every frame
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WorldSprites {
+    pub player:         Sprite,
+    pub drone:          [Sprite; 2],
+    pub bullet:         Sprite,
+    pub crosshair:      Sprite,
+    pub tile_cursor:    Sprite,
+    pub rail:           Sprite,
+    pub rail_bend:      Sprite,
+    pub conveyor:       [Sprite; 2],
+    pub filterveyor:    Sprite,
+    pub turret:         Sprite,
+    pub obfuscator:     Sprite
+}
+
+impl WorldSprites {
+    /// Looks up each sprite by name with sprite, see AssetData::sprite
+    pub fn new(mut sprite: impl FnMut(&str) -> Sprite) -> WorldSprites {
+        WorldSprites{
+            player:         sprite("player"),
+            drone:          [sprite("drone_0"), sprite("drone_1")],
+            bullet:         sprite("bullet"),
+            crosshair:      sprite("crosshair"),
+            tile_cursor:    sprite("tile_cursor"),
+            rail:           sprite("rail"),
+            rail_bend:      sprite("rail_bend"),
+            conveyor:       [sprite("conveyor_0"), sprite("conveyor_1")],
+            filterveyor:    sprite("filterveyor"),
+            turret:         sprite("turret"),
+            obfuscator:     sprite("obfuscate_effect")
+        }
+    }
+
+    /// Fails on the first sprite that isn't in the atlas and has no fallback
+    pub fn from_atlas(atlas: &SpriteAtlas) -> Result<WorldSprites, AtlasError> {
+        let mut missing = None;
+        let sprites = WorldSprites::new(|name| atlas.sprite(name).unwrap_or_else(|e| {
+            missing.get_or_insert(e);
+            Sprite::default()
+        }));
+        missing.map_or(Ok(sprites), Err)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ops::Not;
+    use glam::vec2;
+
+    #[test]
+    fn test_atlas_parse() {
+        let atlas = SpriteAtlas::parse("
+            # comment
+            page a a.png 1024 1024 128 8
+            player      0 0
+            drone       1 2     # trailing comment
+            page b b.png 64 32
+            big         0 0 64 32
+            small       16 8 16 16
+        ").unwrap();
+
+        assert_eq!(atlas.pages.len(), 2);
+        assert_eq!(atlas.get("player"), Some(Sprite{ page: 0, coord: (vec2(0.0, 0.0), vec2(0.125, 0.125)) }));
+        assert_eq!(atlas.get("drone").unwrap().coord.0, vec2(136.0, 272.0) / 1024.0);
+        assert_eq!(atlas.get("big"), Some(Sprite{ page: 1, coord: (vec2(0.0, 0.0), vec2(1.0, 1.0)) }));
+        assert_eq!(atlas.get("small").unwrap().coord, (vec2(0.25, 0.25), vec2(0.5, 0.75)));
+        assert!(matches!(atlas.sprite("nope"), Err(AtlasError::Missing(_))));
+
+        let line_of = |text| match SpriteAtlas::parse(text) {
+            Err(AtlasError::Parse{ line, .. }) => line,
+            _ => 0
+        };
+        assert_eq!(line_of("player 0 0"), 1);
+        assert_eq!(line_of("page a a.png 64 64\nplayer 0 0"), 2);
+        assert_eq!(line_of("page a a.png 64 64 32 0\nplayer 1 1\nplayer 0 0"), 3);
+        assert_eq!(line_of("page a a.png 64 64 32 0\n\nplayer 2 0"), 3);
+        assert_eq!(line_of("page a a.png 64 64\nplayer 0 x 1 1"), 2);
+    }
+
+    #[test]
+    fn test_atlas_custom() {
+        let atlas = SpriteAtlas::load("tf/custom/sprites.atlas").unwrap();
+        WorldSprites::from_atlas(&atlas).unwrap();
+        assert_eq!(Path::new(&atlas.pages[0].path), Path::new("tf/custom/sprites.png"));
+
+        let placeholder = SpriteAtlas::placeholder(uvec2(2, 2));
+        assert_eq!(placeholder.sprite("anything").unwrap().coord, (vec2(0.0, 0.0), vec2(1.0, 1.0)));
+        assert!(placeholder.contains("anything").not());
+
+        // the fallback goes on its own page after the rest
+        let mut atlas = atlas;
+        atlas.add_placeholder(uvec2(2, 2));
+        assert_eq!(atlas.sprite("nope").unwrap().page, atlas.pages.len() - 1);
+        assert!(atlas.contains("player"));
+    }
+}
@@ -0,0 +1,162 @@
+use crate::game::*;
+use crate::draw::View;
+
+use glam::{vec2, Vec2};
+use std::ops::Not;
+
+/// Tiles that fit across the shorter side of the screen, from closest to furthest
+pub const ZOOM_LEVELS: [f32; 6] = [6.0, 8.0, 10.0, 14.0, 18.0, 25.0];
+/// Screen pixels the view can shake by at full trauma
+pub const SHAKE_MAX: f32 = 24.0;
+
+/// Follows the player around, or gets moved around freely for building. Zoom and position ease
+/// towards their targets instead of snapping.
+#[derive(Clone, Debug)]
+pub struct Camera {
+    /// world pixels, without shake
+    pub center:         Vec2,
+    pub target:         Vec2,
+    /// tiles across, see ZOOM_LEVELS
+    pub size:           f32,
+    pub zoom_level:     usize,
+    /// if false, follow the player
+    pub free_pan:       bool,
+    /// world pixel that the mouse is dragging around
+    pub grab:           Option<Vec2>,
+
+    /// how quickly center and size catch up, per second
+    pub follow_rate:    f32,
+    pub zoom_rate:      f32,
+
+    /// 0 to 1, how shaky it is. Offset goes with trauma squared so small hits stay small
+    pub trauma:         f32,
+    pub trauma_decay:   f32,
+    pub shake_max:      f32,
+    pub shake_offset:   Vec2,
+    shake_time:         f32
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Camera{
+            center:         Vec2::ZERO,
+            target:         Vec2::ZERO,
+            size:           ZOOM_LEVELS[2],
+            zoom_level:     2,
+            free_pan:       false,
+            grab:           None,
+            follow_rate:    8.0,
+            zoom_rate:      10.0,
+            trauma:         0.0,
+            trauma_decay:   1.5,
+            shake_max:      SHAKE_MAX,
+            shake_offset:   Vec2::ZERO,
+            shake_time:     0.0
+        }
+    }
+}
+
+impl Camera {
+
+    /// Positive is further away
+    pub fn zoom_by(&mut self, steps: i32) {
+        self.zoom_level = (self.zoom_level as i32 + steps).clamp(0, ZOOM_LEVELS.len() as i32 - 1) as usize;
+    }
+
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).min(1.0);
+    }
+
+    /// Where to go when following pos. Keeps the view inside of the world unless the world is
+    /// smaller than the view, then centers it.
+    pub fn follow_target(&self, pos: Vec2, world_size: Vec2, size: f32) -> Vec2 {
+        let half = 0.5 * TILE_SIZE * ZOOM_LEVELS[self.zoom_level].max(size);
+        let lo = half;
+        let hi = world_size - half;
+        vec2(if lo.x < hi.x { pos.x.clamp(lo.x, hi.x) } else { 0.5 * world_size.x },
+             if lo.y < hi.y { pos.y.clamp(lo.y, hi.y) } else { 0.5 * world_size.y })
+    }
+
+    /// follow is where the player is, in world pixels
+    pub fn update(&mut self, follow: Vec2, world_size: Vec2, events: &[GameEvent], delta: f32) {
+        for event in events {
+            match event {
+                GameEvent::DroneKilled{..}  => self.add_trauma(0.25),
+                GameEvent::Obfuscated{..}   => self.add_trauma(0.6),
+                _ => {}
+            }
+        }
+
+        if self.free_pan.not() {
+            self.target = self.follow_target(follow, world_size, self.size);
+        }
+        self.target = self.target.clamp(Vec2::ZERO, world_size);
+
+        // exponential smoothing, so it doesn't depend on frame rate
+        self.center += (self.target - self.center) * (1.0 - (-self.follow_rate * delta).exp());
+        self.size += (ZOOM_LEVELS[self.zoom_level] - self.size) * (1.0 - (-self.zoom_rate * delta).exp());
+
+        self.shake_time += delta;
+        self.trauma = (self.trauma - self.trauma_decay * delta).max(0.0);
+        let t = self.shake_time;
+        let wobble = vec2((t * 37.0).sin() + 0.5 * (t * 71.3).sin(), (t * 43.0).cos() + 0.5 * (t * 63.7).cos()) / 1.5;
+        self.shake_offset = wobble * self.trauma.powi(2) * self.shake_max;
+    }
+
+    /// Jump straight to wherever it's going
+    pub fn snap(&mut self) {
+        self.center = self.target;
+        self.size = ZOOM_LEVELS[self.zoom_level];
+    }
+
+    /// Keeps the grabbed world position under the mouse
+    pub fn drag(&mut self, mouse_pos: Vec2) {
+        if let Some(grab) = self.grab {
+            self.free_pan = true;
+            self.target += grab - mouse_pos;
+            self.center += grab - mouse_pos;
+        }
+    }
+
+    pub fn view(&self, screen_size: Vec2) -> View {
+        let scale = screen_size.min_element() / (TILE_SIZE.x * self.size);
+        View{ offset: 0.5 * screen_size - (self.center + self.shake_offset) * scale, scale, size: screen_size }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_camera_view() {
+        let world_size = vec2(80.0, 25.0) * TILE_SIZE;
+        let mut cam = Camera::default();
+
+        // clamped so the view doesn't go outside of the world
+        cam.update(vec2(0.0, 0.0), world_size, &[], 0.0);
+        cam.snap();
+        assert_eq!(cam.center, 5.0 * TILE_SIZE);
+
+        // mouse to world and back
+        let view = cam.view(vec2(1600.0, 900.0));
+        assert_eq!(view.scale, 900.0 / 640.0);
+        let mouse = vec2(123.0, 456.0);
+        let world = view.screen_to_world(mouse);
+        assert!((view.world_to_screen(world) - mouse).length() < 0.001);
+        assert_eq!(view.screen_to_world(vec2(800.0, 450.0)), cam.center);
+
+        // world shorter than the view gets centered
+        cam.zoom_level = ZOOM_LEVELS.len() - 1;
+        cam.update(vec2(40.0, 0.0) * TILE_SIZE, vec2(80.0, 20.0) * TILE_SIZE, &[], 0.0);
+        cam.snap();
+        assert_eq!(cam.center, vec2(40.0, 10.0) * TILE_SIZE);
+
+        // shake settles down
+        cam.update(cam.center, world_size, &[GameEvent::Obfuscated{ center: Default::default() }], 0.1);
+        assert!(cam.shake_offset != Vec2::ZERO);
+        cam.update(cam.center, world_size, &[], 1.0);
+        assert_eq!(cam.shake_offset, Vec2::ZERO);
+    }
+}
@@ -0,0 +1,248 @@
+use crate::draw::*;
+
+use glam::{vec2, Vec2};
+use std::fmt::Write;
+
+/// Something the debug overlay can show
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DebugLayer {
+    /// boxes that bullets hit, see drone_aabb
+    Hitboxes,
+    /// where turrets look for drones, see turret_detect_box
+    Turrets,
+    /// where bullets have been and how much further they go
+    Bullets,
+    /// rail_idx of every rail tile
+    Rail,
+    /// coordinates and ids of what's under the cursor
+    Tiles,
+    /// how full each IdReg is
+    Ids
+}
+
+impl DebugLayer {
+    pub const ALL: [DebugLayer; 6] = [DebugLayer::Hitboxes, DebugLayer::Turrets, DebugLayer::Bullets, DebugLayer::Rail, DebugLayer::Tiles, DebugLayer::Ids];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DebugLayer::Hitboxes    => "Drone hitboxes",
+            DebugLayer::Turrets     => "Turret detection",
+            DebugLayer::Bullets     => "Bullet travel",
+            DebugLayer::Rail        => "Rail indices",
+            DebugLayer::Tiles       => "Tile under cursor",
+            DebugLayer::Ids         => "Id counts"
+        }
+    }
+}
+
+const COLOR_HITBOX:     mq::Color = mq::Color::new(1.0, 0.2, 0.2, 0.9);
+const COLOR_DETECT:     mq::Color = mq::Color::new(1.0, 0.6, 0.1, 0.7);
+const COLOR_TRAVELED:   mq::Color = mq::Color::new(1.0, 1.0, 0.3, 0.9);
+const COLOR_AHEAD:      mq::Color = mq::Color::new(1.0, 1.0, 0.3, 0.3);
+
+/// Overlays for seeing what the simulation sees, like when bullets go through drones. Layers
+/// stay on after closing the menu.
+#[derive(Default)]
+pub struct DebugOverlay {
+    pub root:   Option<WidgetId>,
+    buttons:    Vec<(WidgetId, Option<DebugLayer>)>,
+    pub shown:  Vec<DebugLayer>
+}
+
+/// Name, ids in use, and capacity of each IdReg
+pub fn id_counts(main: &GameMain) -> [(&'static str, usize, usize); 4] {
+    [
+        ("drones",      main.drone_ids.iter_ids().count(),  main.drone_ids.capacity()),
+        ("bullets",     main.bullet_ids.iter_ids().count(), main.bullet_ids.capacity()),
+        ("ferals",      main.feral_ids.iter_ids().count(),  main.feral_ids.capacity()),
+        ("machines",    main.mach_ids.iter_ids().count(),   main.mach_ids.capacity())
+    ]
+}
+
+/// Screen space line from world positions
+fn line(view: &View, a: Vec2, b: Vec2, thickness: f32, color: mq::Color) {
+    let (a, b) = (view.world_to_screen(a), view.world_to_screen(b));
+    mq::draw_line(a.x, a.y, b.x, b.y, thickness, color);
+}
+
+fn aabb(view: &View, tl: Vec2, br: Vec2, color: mq::Color) {
+    let (tl, br) = (view.world_to_screen(tl), view.world_to_screen(br));
+    mq::draw_rectangle_lines(tl.x, tl.y, br.x - tl.x, br.y - tl.y, 2.0, color);
+}
+
+impl DebugOverlay {
+
+    pub fn is_shown(&self, layer: DebugLayer) -> bool {
+        self.shown.contains(&layer)
+    }
+
+    pub fn toggle_layer(&mut self, layer: DebugLayer) {
+        match self.shown.iter().position(|&l| l == layer) {
+            Some(i) => { self.shown.remove(i); },
+            None    => self.shown.push(layer)
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.root.is_some()
+    }
+
+    pub fn close(&mut self, ui: &mut Ui) {
+        if let Some(root) = self.root.take() {
+            ui.remove(root);
+        }
+        self.buttons.clear();
+    }
+
+    pub fn toggle(&mut self, ui: &mut Ui) {
+        if self.is_open() { self.close(ui); } else { self.build(ui); }
+    }
+
+    fn build(&mut self, ui: &mut Ui) {
+        self.close(ui);
+
+        let root = ui.add_root(Anchor::TopLeft, Widget::panel(Layout::Stack{ horizontal: false }));
+        let header = ui.add(root, Widget::panel(Layout::Stack{ horizontal: true }));
+        ui.add(header, Widget::label("Debug overlay").with_min_size(vec2(200.0, 0.0)));
+        self.buttons.push((ui.add(header, Widget::button("Close")), None));
+
+        for layer in DebugLayer::ALL {
+            let on = self.is_shown(layer);
+            let button = Widget::button(&format!("{}: {}", layer.label(), if on { "On" } else { "Off" })).with_selected(on);
+            self.buttons.push((ui.add(root, button), Some(layer)));
+        }
+        self.root = Some(root);
+    }
+
+    /// Returns true if the event was for this
+    pub fn handle(&mut self, ui: &mut Ui, event: UiEvent) -> bool {
+        let UiEvent::Clicked(id) = event;
+        let Some(&(_, layer)) = self.buttons.iter().find(|(b, _)| *b == id) else {
+            return false;
+        };
+        match layer {
+            Some(layer) => {
+                self.toggle_layer(layer);
+                self.build(ui);
+            },
+            None => self.close(ui)
+        }
+        true
+    }
+
+    /// On top of the world, under the UI
+    pub fn draw(&self, main: &GameMain, draw: &GameDraw, cursor: Vec2) {
+        if self.shown.is_empty() {
+            return;
+        }
+        let view = &draw.view;
+        let font = draw.font.as_ref();
+        let scale = draw.ui.scale;
+
+        if self.is_shown(DebugLayer::Hitboxes) {
+            for drone in main.drone_ids.iter_ids() {
+                let (tl, br) = drone_aabb(main.drone_pos[drone.0]);
+                aabb(view, tl, br, COLOR_HITBOX);
+            }
+        }
+
+        if self.is_shown(DebugLayer::Turrets) {
+            for mach in main.mach_ids.iter_ids() {
+                let m = &main.mach_data[mach.0];
+                let (Some(pos), MachineSpec::Turret{ target, .. }) = (m.pos, &m.spec) else { continue };
+                match target {
+                    TurretTarget::Ahead => {
+                        let (tl, br) = turret_detect_box(pos, &m.dir);
+                        aabb(view, tl, br, COLOR_DETECT);
+                    },
+                    TurretTarget::Nearest => {
+                        let center = view.world_to_screen((pos.as_vec2() + 0.5) * TILE_SIZE);
+                        mq::draw_circle_lines(center.x, center.y, TURRET_RANGE * TILE_SIZE.x * view.scale, 2.0, COLOR_DETECT);
+                    }
+                }
+            }
+        }
+
+        if self.is_shown(DebugLayer::Bullets) {
+            for bullet in main.bullet_ids.iter_ids() {
+                let (p, b) = (main.bullet_pos[bullet.0], &main.bullet_data[bullet.0]);
+                // straight back along where it's going now, so it's only roughly where it came
+                // from after a deflection
+                line(view, p - b.dir * b.travel, p, 2.0, COLOR_TRAVELED);
+                line(view, p, p + b.dir * (b.travel_max - b.travel).max(0.0), 1.0, COLOR_AHEAD);
+            }
+        }
+
+        if self.is_shown(DebugLayer::Rail) {
+            let font_size = (12.0 * scale) as u16;
+            for (i, r) in main.rail.iter().enumerate() {
+                let pos = view.world_to_screen(r.pos.as_vec2() * TILE_SIZE) + 2.0;
+                if view.on_screen(pos, TILE_SIZE * view.scale) {
+                    mq::draw_text_ex(i.to_string(), pos.x, pos.y + font_size as f32, mq::TextParams{ font, font_size, color: mq::WHITE, ..Default::default() });
+                }
+            }
+        }
+
+        if self.is_shown(DebugLayer::Tiles) {
+            if let Some(c) = draw.cursor_tile {
+                let mut text = format!("{}, {}", c.pos.x, c.pos.y);
+                if let Some(rail) = c.rail {
+                    write!(text, "\nrail_idx {}", rail).unwrap();
+                }
+                if let Some(feral) = c.feral {
+                    write!(text, "\nferal #{}", feral.0).unwrap();
+                }
+                if let Some(mach) = c.mach {
+                    write!(text, "\nmachine #{}", mach.0).unwrap();
+                }
+                draw_tooltip(&text, cursor + vec2(16.0, -48.0) * scale, false, font, view.size, scale);
+            }
+        }
+
+        if self.is_shown(DebugLayer::Ids) {
+            let mut text = String::new();
+            for (name, used, capacity) in id_counts(main) {
+                writeln!(text, "{:<9}{:>5} / {}", name, used, capacity).unwrap();
+            }
+            // top right, under the minimap
+            let y = if draw.minimap.visible { draw.minimap.tl.y + draw.minimap.size.y } else { 0.0 };
+            draw_tooltip(&text, vec2(view.size.x, y), false, font, view.size, scale);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::uvec2;
+
+    #[test]
+    fn test_debug_overlay() {
+        let mut main = GameMain::default();
+        main.drone_ids.resize(64);
+        main.mach_ids.resize(64);
+        main.drone_ids.create().unwrap();
+        main.drone_ids.create().unwrap();
+        let (name, used, capacity) = id_counts(&main)[0];
+        assert_eq!((name, used), ("drones", 2));
+        assert!(capacity >= 64);
+        assert_eq!(id_counts(&main)[1], ("bullets", 0, 0));
+
+        // 5 tiles out from the middle of the turret, whichever way it faces
+        let mid = (uvec2(2, 2).as_vec2() + 0.5) * TILE_SIZE;
+        let (tl, br) = turret_detect_box(uvec2(2, 2), &Dir::Right);
+        assert_eq!(tl.x, mid.x);
+        assert!((br.x - (mid.x + 5.0 * TILE_SIZE.x)).abs() < 0.001);
+        assert!((br.y - tl.y - 1.2 * TILE_SIZE.y).abs() < 0.001);
+        let (tl, br) = turret_detect_box(uvec2(2, 2), &Dir::Up);
+        assert!((tl.y - (mid.y - 5.0 * TILE_SIZE.y)).abs() < 0.001);
+        assert_eq!(br.y, mid.y);
+
+        let mut overlay = DebugOverlay::default();
+        overlay.toggle_layer(DebugLayer::Hitboxes);
+        overlay.toggle_layer(DebugLayer::Ids);
+        overlay.toggle_layer(DebugLayer::Hitboxes);
+        assert_eq!(overlay.shown, vec![DebugLayer::Ids]);
+    }
+}
@@ -0,0 +1,163 @@
+use crate::draw::*;
+
+use glam::{vec2, Vec2, UVec2};
+use std::ops::Not;
+
+/// Whole world shrunk down to a pixel per tile, drawn in the top right corner
+pub struct Minimap {
+    pub visible:    bool,
+    pub image:      mq::Image,
+    texture:        Option<mq::Texture2D>,
+    texture_stale:  bool,
+    /// world size and versions of rail_by_tile, feral_by_tile, and mach_by_tile that image shows
+    built_for:      Option<(UVec2, u64, u64, u64)>,
+    /// top left and size on screen, from the last draw
+    pub tl:         Vec2,
+    pub size:       Vec2
+}
+
+const COLOR_GROUND:     u32 = 0x35552f;
+const COLOR_RAIL:       u32 = 0xc8325a;
+const COLOR_FERAL:      u32 = 0xe0c040;
+const COLOR_TURRET:     u32 = 0x5a9650;
+const COLOR_CONVEYOR:   u32 = 0xc0c0c0;
+
+impl Default for Minimap {
+    fn default() -> Self {
+        Minimap{
+            visible:        true,
+            image:          mq::Image::empty(),
+            texture:        None,
+            texture_stale:  false,
+            built_for:      None,
+            tl:             Vec2::ZERO,
+            size:           Vec2::ZERO
+        }
+    }
+}
+
+impl Minimap {
+
+    /// Rebuilds the image if anything it shows has changed since last time. Returns true if it did.
+    pub fn update(&mut self, main: &GameMain) -> bool {
+        let key = (main.world_size, main.rail_by_tile.version(), main.feral_by_tile.version(), main.mach_by_tile.version());
+        if self.built_for == Some(key) {
+            return false;
+        }
+        self.built_for = Some(key);
+        self.texture_stale = true;
+
+        let size = main.world_size;
+        if (self.image.width as u32, self.image.height as u32) != (size.x, size.y) {
+            self.image = mq::Image::gen_image_color(size.x as u16, size.y as u16, mq::Color::from_hex(COLOR_GROUND));
+        } else {
+            self.image.get_image_data_mut().fill(mq::Color::from_hex(COLOR_GROUND).into());
+        }
+
+        // later ones cover up earlier ones
+        for (pos, _) in main.rail_by_tile.iter() {
+            self.image.set_pixel(pos.x, pos.y, mq::Color::from_hex(COLOR_RAIL));
+        }
+        for (pos, _) in main.feral_by_tile.iter() {
+            self.image.set_pixel(pos.x, pos.y, mq::Color::from_hex(COLOR_FERAL));
+        }
+        for (pos, mach) in main.mach_by_tile.iter() {
+            let color = match main.mach_data[mach.0].spec {
+                MachineSpec::Turret{..} => COLOR_TURRET,
+                _                       => COLOR_CONVEYOR
+            };
+            self.image.set_pixel(pos.x, pos.y, mq::Color::from_hex(color));
+        }
+        true
+    }
+
+    pub fn contains(&self, screen_pos: Vec2) -> bool {
+        self.visible && screen_pos.cmpge(self.tl).all() && screen_pos.cmplt(self.tl + self.size).all()
+    }
+
+    /// World pixel under a point on the minimap
+    pub fn screen_to_world(&self, main: &GameMain, screen_pos: Vec2) -> Vec2 {
+        (screen_pos - self.tl) / self.size * main.world_size.as_vec2() * TILE_SIZE
+    }
+
+    /// Draws in the top right corner of the screen, with drones and the view rectangle on top
+    pub fn draw(&mut self, main: &GameMain, view: &View) {
+        if self.visible.not() || main.world_size.x == 0 || main.world_size.y == 0 {
+            return;
+        }
+
+        self.update(main);
+
+        let texture = self.texture.get_or_insert_with(|| mq::Texture2D::from_image(&self.image));
+        if self.texture_stale {
+            if (texture.width() as u16, texture.height() as u16) == (self.image.width, self.image.height) {
+                texture.update(&self.image);
+            } else {
+                *texture = mq::Texture2D::from_image(&self.image);
+            }
+            texture.set_filter(mq::FilterMode::Nearest);
+            self.texture_stale = false;
+        }
+
+        let margin = 8.0;
+        let px = (view.size.x * 0.3).min(320.0) / main.world_size.x as f32; // screen pixels per tile
+        self.size = main.world_size.as_vec2() * px;
+        self.tl = vec2(view.size.x - self.size.x - margin, margin);
+
+        mq::draw_rectangle(self.tl.x - 2.0, self.tl.y - 2.0, self.size.x + 4.0, self.size.y + 4.0, mq::Color::new(0.0, 0.0, 0.0, 0.6));
+        mq::draw_texture_ex(texture, self.tl.x, self.tl.y, mq::WHITE, mq::DrawTextureParams{
+            dest_size: Some(mq::vec2(self.size.x, self.size.y)),
+            ..Default::default()
+        });
+
+        // drone density, more drones on a tile is more red
+        let mut counts = vec![0_u8; (main.world_size.x * main.world_size.y) as usize];
+        for id in main.drone_ids.iter_ids() {
+            let tile = (main.drone_pos[id.0] / TILE_SIZE).floor();
+            if tile.cmpge(Vec2::ZERO).all() && tile.cmplt(main.world_size.as_vec2()).all() {
+                let i = tile.y as usize * main.world_size.x as usize + tile.x as usize;
+                counts[i] = counts[i].saturating_add(1);
+            }
+        }
+        for (i, &count) in counts.iter().enumerate().filter(|(_, &c)| c != 0) {
+            let tile = vec2((i % main.world_size.x as usize) as f32, (i / main.world_size.x as usize) as f32);
+            let pos = self.tl + tile * px;
+            mq::draw_rectangle(pos.x, pos.y, px, px, mq::Color::new(1.0, 0.1, 0.1, (0.4 + 0.2 * count as f32).min(1.0)));
+        }
+
+        // what the camera sees
+        let to_map = |p: Vec2| (self.tl + p / TILE_SIZE * px).clamp(self.tl, self.tl + self.size);
+        let a = to_map(view.screen_to_world(Vec2::ZERO));
+        let b = to_map(view.screen_to_world(view.size));
+        mq::draw_rectangle_lines(a.x, a.y, b.x - a.x, b.y - a.y, 2.0, mq::WHITE);
+
+        let player = to_map(main.player_pos);
+        mq::draw_rectangle(player.x - 2.0, player.y - 2.0, 4.0, 4.0, mq::WHITE);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::uvec2;
+
+    #[test]
+    fn test_minimap_cached() {
+        let mut main = GameMain{ world_size: uvec2(4, 2), ..Default::default() };
+        main.rail = vec![Rail{ pos: uvec2(1, 1), dir: Dir::Right, bend: Bend::Forward }];
+        regen_rail_by_tile(&main.rail, &mut main.rail_by_tile);
+
+        let mut minimap = Minimap::default();
+        assert!(minimap.update(&main));
+        assert!(minimap.update(&main).not());
+        assert_eq!(minimap.image.get_pixel(1, 1), mq::Color::from_hex(COLOR_RAIL));
+        assert_eq!(minimap.image.get_pixel(0, 1), mq::Color::from_hex(COLOR_GROUND));
+
+        main.itemtype_data.push(Default::default());
+        main.feral_ids.resize(64);
+        place_item(&main.itemtype_data, &mut main.feral_ids, &mut main.feral_data, &mut main.feral_by_tile, uvec2(3, 0), ItemSlot{ itemtype: ItemTypeId(0), count: 1 }).ok();
+        assert!(minimap.update(&main));
+        assert_eq!(minimap.image.get_pixel(3, 0), mq::Color::from_hex(COLOR_FERAL));
+    }
+}
@@ -0,0 +1,841 @@
+pub mod soft;
+pub mod ascii;
+pub mod atlas;
+pub mod anim;
+pub mod particles;
+pub mod camera;
+pub mod minimap;
+pub mod ui;
+pub mod screens;
+pub mod debug;
+
+pub use atlas::*;
+pub use anim::*;
+pub use particles::*;
+pub use camera::*;
+pub use minimap::*;
+pub use ui::*;
+pub use screens::*;
+pub use debug::*;
+
+use crate::game::*;
+use crate::input::{Action, Input, Recipe};
+use crate::assets::{AssetData, AssetKind};
+
+use std::fmt::Write;
+use glam::{vec2, Vec2, uvec2, UVec2, IVec2, Mat2};
+
+use std::ops::Not;
+
+
+
+pub struct GameDraw {
+    pub atlas: SpriteAtlas,
+    pub pages: Vec<mq::Texture2D>,
+    /// None is macroquad's built in font
+    pub font: Option<mq::Font>,
+    pub stupid: String,
+
+    pub camera: Camera,
+    pub mouse_pos: Vec2,
+    /// Tile under the mouse. Only means something if cursor_tile is Some, otherwise the mouse is
+    /// outside of the world
+    pub mouse_select: UVec2,
+    pub cursor_tile: Option<TileContents>,
+    pub under_cursor: TileThing,
+    /// Mouse is over something on the screen instead of the world
+    pub mouse_on_ui: bool,
+    pub minimap: Minimap,
+    pub ui: Ui,
+    pub item_book: ItemBook,
+    pub mach_config: MachineConfig,
+    pub settings_menu: SettingsMenu,
+    pub sandbox_menu: SandboxMenu,
+    pub console: Console,
+    pub debug: DebugOverlay,
+    pub blueprint: BlueprintTool,
+
+    pub view: View,
+    pub world: WorldDraw
+}
+
+/// Copying and pasting blueprints with the Construct tool, see game::Blueprint
+#[derive(Default)]
+pub struct BlueprintTool {
+    /// First corner picked, while copying
+    pub corner:     Option<UVec2>,
+    /// Showing GameMain::blueprint under the cursor, ready to paste
+    pub pasting:    bool
+}
+
+/// Lines of the console log shown above what's being typed
+pub const CONSOLE_LINES: usize = 12;
+
+/// Tint for machines that aren't built yet
+pub const GHOST_COLOR: mq::Color = mq::Color::new(0.6, 0.8, 1.0, 0.45);
+
+/// Drawing state for the world itself. Unlike GameDraw, this doesn't need a GPU
+pub struct WorldDraw {
+    pub sprites: WorldSprites,
+    pub stupidraw: Vec<(f32, Vec2, Mat2, Sprite)>,
+
+    pub anims: AnimLibrary,
+    pub clips: WorldClips,
+    pub drone_anim: Vec<Anim>,
+    pub mach_anim: Vec<Anim>,
+    pub player_anim: Anim,
+    /// One-off animations at a position in the world, removed once they finish
+    pub effects: Vec<(Vec2, Anim)>,
+    /// From animations advanced by update, for the game to take out
+    pub events: Vec<AnimEvent>,
+
+    pub particles: Particles,
+    pub emitters: WorldEmitters,
+    /// false to leave out muzzle flashes and explosions
+    pub flashes: bool,
+
+    pub clock_1s: f32
+}
+
+impl WorldDraw {
+    pub fn new(sprites: WorldSprites) -> Self {
+        let mut anims = AnimLibrary::default();
+        let clips = WorldClips::new(&sprites, &mut anims);
+        WorldDraw{
+            sprites,
+            stupidraw:          Default::default(),
+            anims,
+            clips,
+            drone_anim:         Default::default(),
+            mach_anim:          Default::default(),
+            player_anim:        Anim::ended(clips.player_hop),
+            effects:            Default::default(),
+            events:             Default::default(),
+            particles:          Particles::new(1024),
+            emitters:           Default::default(),
+            flashes:            true,
+            clock_1s:           0.0
+        }
+    }
+
+    /// Advances all animations and particles by delta seconds, and starts new ones for main.events
+    pub fn update(&mut self, main: &GameMain, delta: f32) {
+        self.clock_1s = (self.clock_1s + delta).fract();
+
+        for event in &main.events {
+            let e = self.emitters;
+            match *event {
+                GameEvent::DroneKilled{ pos } => {
+                    self.particles.burst(&e.smoke, pos, Vec2::X);
+                    if self.flashes {
+                        self.particles.burst(&e.explosion, pos, Vec2::X);
+                    }
+                },
+                GameEvent::DroneLeaked{..} => {},
+                GameEvent::BulletDeflected{ pos, dir } => {
+                    self.particles.burst(&e.sparks, pos, dir);
+                },
+                GameEvent::TurretFired{ mach, pos, dir } => {
+                    if self.flashes {
+                        self.particles.burst(&e.muzzle_flash, pos, dir);
+                    }
+                    self.play_mach(mach, self.clips.turret_fire);
+                },
+                GameEvent::PlayerFired{ pos, dir } => {
+                    if self.flashes {
+                        self.particles.burst(&e.muzzle_flash, pos, dir);
+                    }
+                },
+                GameEvent::Obfuscated{ center } => {
+                    self.play_effect((center.as_vec2() + 0.5) * TILE_SIZE, self.clips.obfuscate);
+
+                    // light up the new path of the rail
+                    let tl = center.as_ivec2() - 2;
+                    for rail in &main.rail {
+                        let d = rail.pos.as_ivec2() - tl;
+                        if (0..5).contains(&d.x) && (0..5).contains(&d.y) {
+                            self.particles.burst(&e.obfuscate, (rail.pos.as_vec2() + 0.5) * TILE_SIZE, Vec2::X);
+                        }
+                    }
+                }
+            }
+        }
+        self.particles.update(delta);
+
+        let lib = &self.anims;
+        let events = &mut self.events;
+
+        // drones start at different points so they don't all blink together
+        while self.drone_anim.len() < main.drone_data.len() {
+            let phase = self.drone_anim.len() as f32 * 1.618;
+            let length = lib.clip(self.clips.drone).length();
+            self.drone_anim.push(Anim{ clip: self.clips.drone, time: phase % length });
+        }
+        for id in main.drone_ids.iter_ids() {
+            self.drone_anim[id.0].advance(lib, delta, events);
+        }
+
+        self.mach_anim.resize(main.mach_data.len().max(self.mach_anim.len()), Default::default());
+        for id in main.mach_ids.iter_ids() {
+            let spec = &main.mach_data[id.0].spec;
+            let anim = &mut self.mach_anim[id.0];
+            if anim.finished(lib) || self.clips.mach_can_play(spec, anim.clip).not() {
+                anim.play(self.clips.mach_idle(spec));
+            }
+            anim.advance(lib, delta, events);
+        }
+
+        self.player_anim.advance(lib, delta, events);
+
+        for (_, anim) in &mut self.effects {
+            anim.advance(lib, delta, events);
+        }
+        self.effects.retain(|(_, anim)| anim.finished(lib).not());
+    }
+
+    pub fn play_mach(&mut self, id: MachineId, clip: ClipId) {
+        if self.mach_anim.len() <= id.0 {
+            self.mach_anim.resize(id.0 + 1, Default::default());
+        }
+        self.mach_anim[id.0].play(clip);
+    }
+
+    /// pos is in world pixels
+    pub fn play_effect(&mut self, pos: Vec2, clip: ClipId) {
+        self.effects.push((pos, Anim::new(clip)));
+    }
+
+    /// Frame for a machine, even if update hasn't seen it yet
+    fn mach_frame(&self, id: MachineId, spec: &MachineSpec) -> &AnimFrame {
+        match self.mach_anim.get(id.0) {
+            Some(anim) if self.clips.mach_can_play(spec, anim.clip) => anim.frame(&self.anims),
+            _ => Anim::new(self.clips.mach_idle(spec)).frame(&self.anims)
+        }
+    }
+}
+
+/// Where the world is on the screen. screen_pos = world_pos * scale + offset
+#[derive(Clone, Copy, Debug)]
+pub struct View {
+    pub offset: Vec2,
+    pub scale:  f32,
+    pub size:   Vec2
+}
+
+impl View {
+    pub fn on_screen_mat(&self, pos: Vec2, tf: Mat2) -> bool {
+        let aabb = vec2(f32::max(tf.x_axis.x.abs(), tf.y_axis.x.abs()),
+                        f32::max(tf.x_axis.y.abs(), tf.y_axis.y.abs()));
+
+        self.on_screen(pos, aabb)
+    }
+
+    pub fn screen_to_world(&self, pos: Vec2) -> Vec2 {
+        (pos - self.offset) / self.scale
+    }
+
+    pub fn world_to_screen(&self, pos: Vec2) -> Vec2 {
+        pos * self.scale + self.offset
+    }
+
+    pub fn on_screen(&self, pos: Vec2, aabb: Vec2) -> bool {
+           pos.x + aabb.x > 0.0 && pos.x - aabb.x < self.size.x
+        && pos.y + aabb.y > 0.0 && pos.y - aabb.y < self.size.y
+    }
+}
+
+/// Something the world can be drawn onto. Sprites are a unit square centered on pos, transformed
+/// by tf, and textured by a region of one of the atlas pages.
+pub trait Renderer {
+    fn clear(&mut self, color: mq::Color);
+    fn rectangle(&mut self, pos: Vec2, size: Vec2, color: mq::Color);
+    fn rectangle_lines(&mut self, pos: Vec2, size: Vec2, thickness: f32, color: mq::Color);
+    fn sprite(&mut self, pos: Vec2, tf: Mat2, sprite: Sprite, color: mq::Color);
+}
+
+/// Draws with macroquad onto the screen
+pub struct MqRenderer<'a> {
+    pub pages: &'a [mq::Texture2D]
+}
+
+impl Renderer for MqRenderer<'_> {
+    fn clear(&mut self, color: mq::Color) {
+        mq::clear_background(color);
+    }
+
+    fn rectangle(&mut self, pos: Vec2, size: Vec2, color: mq::Color) {
+        mq::draw_rectangle(pos.x, pos.y, size.x, size.y, color);
+    }
+
+    fn rectangle_lines(&mut self, pos: Vec2, size: Vec2, thickness: f32, color: mq::Color) {
+        mq::draw_rectangle_lines(pos.x, pos.y, size.x, size.y, thickness, color);
+    }
+
+    fn sprite(&mut self, pos: Vec2, tf: Mat2, sprite: Sprite, color: mq::Color) {
+        draw_texture_gwah(&self.pages[sprite.page], pos, tf, sprite.coord, color);
+    }
+}
+
+pub enum TileThing {
+    None, Feral(FeralItemId), Machine(MachineId)
+}
+
+pub mod mq {
+    pub use macroquad::prelude::*;
+    pub use macroquad::audio::*;
+    pub use macroquad::rand::*;
+    pub use macroquad::texture::*;
+}
+
+pub fn texture_from_image(img: &image::RgbaImage) -> mq::Texture2D {
+    mq::Texture2D::from_rgba8(img.width() as u16, img.height() as u16, img.as_raw())
+}
+
+/// Uses the "sprites" atlas and "ui" font. Anything missing from assets gets a placeholder and a
+/// problem.
+pub fn make_game_draw(assets: &mut AssetData) -> GameDraw {
+    let (atlas, images) = assets.atlas("sprites");
+    let pages = images.iter().map(texture_from_image).collect();
+    let world = WorldDraw::new(WorldSprites::new(|name| assets.sprite("sprites", &atlas, name)));
+    let font = match assets.font("ui").map(mq::load_ttf_font_from_bytes) {
+        Some(Err(e)) => { assets.problem(AssetKind::Font, "ui", e.to_string()); None },
+        font => font.and_then(Result::ok)
+    };
+
+    GameDraw{
+        atlas,
+        pages,
+        font,
+        stupid:             Default::default(),
+        camera:             Default::default(),
+        mouse_pos:          vec2(0.0, 0.0),
+        mouse_select:       uvec2(0, 0),
+        cursor_tile:        None,
+        under_cursor:       TileThing::None,
+        mouse_on_ui:        false,
+        minimap:            Default::default(),
+        ui:                 Default::default(),
+        item_book:          Default::default(),
+        mach_config:        Default::default(),
+        settings_menu:      Default::default(),
+        sandbox_menu:       Default::default(),
+        console:            Default::default(),
+        debug:              Default::default(),
+        blueprint:          Default::default(),
+        view:               View{ offset: vec2(0.0, 0.0), scale: 1.0, size: vec2(0.0, 0.0) },
+        world
+    }
+}
+
+pub fn mach_sprite(sprites: &WorldSprites, spec: &MachineSpec) -> (Sprite, bool) {
+    match spec {
+        MachineSpec::None => panic!(),
+        MachineSpec::Turret{ammo: _, can_fire_time_us: _, .. } => (sprites.turret, true),
+        MachineSpec::Conveyor{item: _, filter: true, can_move_time_us: _, can_dump_time_us: _} => (sprites.filterveyor, true),
+        MachineSpec::Conveyor{item: _, filter: false, can_move_time_us: _, can_dump_time_us: _} => (sprites.conveyor[0], true)
+    }
+}
+
+
+/// Tooltip hanging a tile under pos, see ui::draw_tooltip. ui_scale sizes the text, view_scale
+/// is only for how far down a tile is.
+pub fn stupid_rectangle(string: &str, pos: Vec2, center: bool, font: Option<&mq::Font>, screen_size: Vec2, view_scale: f32, ui_scale: f32) {
+    draw_tooltip(string, pos + vec2(0.0, TILE_SIZE.y*view_scale), center, font, screen_size, ui_scale);
+}
+
+pub fn draw_game(main: &GameMain, draw: &mut GameDraw, input: &Input) {
+
+    // Do camera stuff, see Camera::update
+    let screen_size = vec2(mq::screen_width(), mq::screen_height());
+    let view = draw.camera.view(screen_size);
+    let view_scale = view.scale;
+    let view_offset = view.offset;
+    draw.view = view;
+
+    // Mouse
+    let (mouse_x, mouse_y) = (input.cursor.x, input.cursor.y);
+    draw.mouse_pos = view.screen_to_world(vec2(mouse_x, mouse_y));
+    let mouse_tile = (draw.mouse_pos / TILE_SIZE).floor().as_ivec2();
+    draw.mouse_select = mouse_tile.max(IVec2::ZERO).as_uvec2();
+
+    // UI gets the mouse first
+    let font = draw.font.as_ref();
+    draw.ui.layout(screen_size, &|text, font_size| mq::measure_text(text, font, font_size, 1.0).width);
+    let on_widget = draw.ui.input(vec2(mouse_x, mouse_y),
+                                  input.pressed(Action::Use),
+                                  input.released(Action::Use),
+                                  mq::mouse_wheel().1);
+    draw.mouse_on_ui = on_widget || draw.minimap.contains(vec2(mouse_x, mouse_y));
+
+    // what's under the cursor?
+    draw.cursor_tile = if mouse_tile.cmpge(IVec2::ZERO).all() && draw.mouse_on_ui.not() {
+        tile_contents(main.world_size, &main.rail_by_tile, &main.feral_by_tile, &main.mach_by_tile, draw.mouse_select)
+    } else {
+        None
+    };
+    draw.under_cursor = match draw.cursor_tile {
+        Some(TileContents{ feral: Some(feral), .. }) => TileThing::Feral(feral),
+        Some(TileContents{ mach: Some(mach), .. })   => TileThing::Machine(mach),
+        _ => TileThing::None
+    };
+
+
+    let mut out = MqRenderer{ pages: &draw.pages };
+    draw_world(main, &mut draw.world, &view, &mut out);
+
+
+    //use std::f32::consts::PI;
+    //+ 4.0*f32::sin(4.0*2.0*PI*draw.clock_1s)
+
+    // Draw cursor
+
+
+
+    let select_pos = draw.mouse_select.as_vec2() * TILE_SIZE * view_scale + view_offset;
+    let select_size = TILE_SIZE * view_scale;
+    match &main.tool {
+        ToolMode::Construct(drag) => {
+            match drag {
+                Drag::None if main.sandbox.editing_rail() => {
+                    if let Some(last) = main.rail.last() {
+                        let end = last.pos.as_vec2() * TILE_SIZE * view_scale + view_offset;
+                        let size = TILE_SIZE * view_scale;
+                        mq::draw_rectangle_lines(end.x, end.y, size.x, size.y, 4.0, mq::YELLOW);
+                    }
+                    mq::draw_rectangle_lines(select_pos.x, select_pos.y, select_size.x, select_size.y, 2.0, mq::WHITE);
+                    writeln!(draw.stupid, "Editing rail: {} next to the end to extend it, on the end to shorten it", input.bindings.hint(Action::Use)).unwrap();
+                },
+                Drag::None if draw.blueprint.pasting && main.blueprint.is_some() => {
+                    let blueprint = main.blueprint.as_ref().unwrap();
+                    let mat = Mat2::from_diagonal(TILE_SIZE * view_scale);
+                    for part in &blueprint.parts {
+                        let pos = draw.mouse_select + part.offset;
+                        let spos = (pos.as_vec2() + 0.5) * TILE_SIZE * view_scale + view_offset;
+                        let free = tile_contents(main.world_size, &main.rail_by_tile, &main.feral_by_tile, &main.mach_by_tile, pos)
+                            .is_some_and(|c| c.can_place(TileKind::Machine));
+                        let (sprite, _) = mach_sprite(&draw.world.sprites, &part.spec);
+                        out.sprite(spos, mat, draw.world.sprites.tile_cursor, if free {mq::WHITE} else {mq::RED});
+                        out.sprite(spos, dir_to_mat2(&part.dir) * mat, sprite, GHOST_COLOR);
+                    }
+
+                    let cost = blueprint.cost();
+                    let missing = missing_items(&cost, &items_on_ground(main));
+                    writeln!(draw.stupid, "Blueprint: {} machines", blueprint.parts.len()).unwrap();
+                    writeln!(draw.stupid, "Needs {}", items_text(main, &cost)).unwrap();
+                    if missing.is_empty().not() {
+                        writeln!(draw.stupid, "Missing {}", items_text(main, &missing)).unwrap();
+                    }
+                    writeln!(draw.stupid, "{} to paste, {} to turn, {} to stop", input.bindings.hint(Action::Use), input.bindings.hint(Action::Rotate), input.bindings.hint(Action::Paste)).unwrap();
+                },
+                Drag::None => {
+                    // Copying a blueprint, from the first corner to here
+                    if let Some(corner) = draw.blueprint.corner {
+                        let tl = corner.min(draw.mouse_select).as_vec2() * TILE_SIZE * view_scale + view_offset;
+                        let size = ((corner.max(draw.mouse_select) - corner.min(draw.mouse_select)).as_vec2() + 1.0) * TILE_SIZE * view_scale;
+                        mq::draw_rectangle_lines(tl.x, tl.y, size.x, size.y, 4.0, mq::YELLOW);
+                        writeln!(draw.stupid, "{} at the other corner to copy", input.bindings.hint(Action::Copy)).unwrap();
+                    }
+
+                    match draw.under_cursor {
+                        TileThing::None => {
+                            if draw.cursor_tile.is_some() {
+                                mq::draw_rectangle_lines(select_pos.x, select_pos.y, select_size.x, select_size.y, 2.0, mq::WHITE);
+                            }
+                            if let Some(ghost) = main.ghosts.get(draw.mouse_select.into()) {
+                                let cost = machine_cost(machine_recipe(&ghost.spec));
+                                writeln!(draw.stupid, "Planned {}, needs {}", machine_name(&ghost.spec), items_text(main, &cost)).unwrap();
+                                writeln!(draw.stupid, "{} to build ghosts, {} to remove", input.bindings.hint(Action::BuildGhosts), input.bindings.hint(Action::Interact)).unwrap();
+                            }
+                        },
+                        TileThing::Feral(feral) => {
+                            mq::draw_rectangle_lines(select_pos.x, select_pos.y, select_size.x, select_size.y, 8.0, mq::GREEN);
+
+                            for exslot in main.feral_data[feral.0].slots.iter().rev() {
+                                if let Some(slot) = exslot {
+                                    let dit = &main.itemtype_data[slot.itemtype.0];
+                                    write!(draw.stupid, "* {}× {}\n", slot.count, dit.name).unwrap();
+                                }
+                            }
+
+
+
+                            if draw.cursor_tile.is_some_and(|t| t.can_craft()) {
+
+                                // >:)
+                                if craft_item_recipe(&main.itemtype_data, &main.feral_data[feral.0].slots, 0).is_some() {
+                                    write!(draw.stupid, "Press {} to Disassemble\n", input.bindings.hint(Action::Craft(Recipe::Disassemble))).unwrap();
+                                }
+                                if craft_item_recipe(&main.itemtype_data, &main.feral_data[feral.0].slots, 1).is_some() {
+                                    write!(draw.stupid, "Press {} craft Bullets\n", input.bindings.hint(Action::Craft(Recipe::Bullet))).unwrap();
+                                }
+                                if craft_item_recipe(&main.itemtype_data, &main.feral_data[feral.0].slots, 2).is_some() {
+                                    write!(draw.stupid, "Press {} craft Alignite Clump\n", input.bindings.hint(Action::Craft(Recipe::Clump))).unwrap();
+                                }
+                                if craft_machine_recipe(&main.itemtype_data, &main.feral_data[feral.0].slots, 5).is_some() {
+                                    write!(draw.stupid, "Press {} to craft Turret\n", input.bindings.hint(Action::Craft(Recipe::Turret))).unwrap();
+                                }
+                                if craft_machine_recipe(&main.itemtype_data, &main.feral_data[feral.0].slots, 6).is_some() {
+                                    write!(draw.stupid, "Press {} to craft Conveyor\n", input.bindings.hint(Action::Craft(Recipe::Conveyor))).unwrap();
+                                }
+                                if craft_machine_recipe(&main.itemtype_data, &main.feral_data[feral.0].slots, 7).is_some() {
+                                    write!(draw.stupid, "Press {} to craft Filterveyor\n", input.bindings.hint(Action::Craft(Recipe::Filterveyor))).unwrap();
+                                }
+                                if craft_item_recipe(&main.itemtype_data, &main.feral_data[feral.0].slots, 69).is_some() {
+                                    write!(draw.stupid, "Press {} to OBFUSCATE\n", input.bindings.hint(Action::Obfuscate)).unwrap();
+                                }
+                            } else {
+                                write!(draw.stupid, "Note: Cannot craft on rails!\n").unwrap();
+                            }
+                        },
+                        TileThing::Machine(mach) => {
+
+                            let d = &main.mach_data[mach.0];
+                            match &d.spec {
+                                MachineSpec::Turret { ammo, can_fire_time_us: _, .. } => {
+                                    write!(draw.stupid, "Ammo: {}/69\n", ammo).unwrap();
+                                },
+                                MachineSpec::Conveyor { item, filter: true, can_move_time_us: _, can_dump_time_us: _ } => {
+                                    if item.itemtype != Default::default() {
+                                        writeln!(draw.stupid, "Filter: {}", main.itemtype_data[item.itemtype.0].name).unwrap();
+                                    } else {
+                                         writeln!(draw.stupid, "No filter").unwrap();
+                                    }
+                                },
+                                _ => {}
+                            };
+                            if d.enabled.not() {
+                                writeln!(draw.stupid, "Disabled").unwrap();
+                            }
+                            writeln!(draw.stupid, "{} to configure, {} to pick up", input.bindings.hint(Action::Use), input.bindings.hint(Action::Interact)).unwrap();
+
+                            mq::draw_rectangle_lines(select_pos.x, select_pos.y, select_size.x, select_size.y, 8.0, mq::GREEN);
+                        }
+                    };
+
+                },
+                Drag::Item(slot) => {
+                    let dit = &main.itemtype_data[slot.itemtype.0];
+                    let mat = Mat2::from_diagonal(TILE_SIZE * view_scale);
+                    let reloads_turret = slot.itemtype == ITEM_BULLET
+                                      && matches!(draw.cursor_tile.and_then(|t| t.mach).map(|m| &main.mach_data[m.0].spec), Some(MachineSpec::Turret{..}));
+                    let valid = reloads_turret || draw.cursor_tile.is_some_and(|t| t.can_place(TileKind::Feral));
+                    out.sprite(vec2(select_pos.x, select_pos.y) + 0.5*TILE_SIZE*view_scale, mat, draw.world.sprites.tile_cursor, if valid {mq::WHITE} else {mq::RED});
+                    out.sprite(vec2(mouse_x, mouse_y) - vec2(0.0, 0.5*TILE_SIZE.y), mat, dit.sprite, mq::Color::new(1.0, 1.0, 1.0, 0.75));
+                },
+                Drag::Machine(mach) => {
+                    let d = &main.mach_data[mach.0];
+                    let mat = Mat2::from_diagonal(TILE_SIZE * view_scale);
+                    let mat_rot = mat * dir_to_mat2(&d.dir);
+                    let (ssprite, _) = mach_sprite(&draw.world.sprites, &d.spec);
+                    let valid = draw.cursor_tile.is_some_and(|t| t.can_place(TileKind::Machine));
+                    out.sprite(vec2(select_pos.x, select_pos.y) + 0.5*TILE_SIZE*view_scale, mat, draw.world.sprites.tile_cursor, if valid {mq::WHITE} else {mq::RED});
+                    out.sprite(vec2(mouse_x, mouse_y) - vec2(0.0, 0.5*TILE_SIZE.y), mat_rot, ssprite, mq::Color::new(1.0, 1.0, 1.0, 0.75));
+                }
+            }
+
+                if draw.stupid.is_empty().not() {
+
+                    stupid_rectangle(&draw.stupid, select_pos, false, draw.font.as_ref(), screen_size, view_scale, draw.ui.scale);
+
+                    draw.stupid.clear();
+                }
+        },
+        ToolMode::GunPod => {
+
+            write!(draw.stupid, "Ammo: {}/{}\n", main.player_gun_ammo, PLAYER_GUN_AMMO_MAX).unwrap();
+
+            if let TileThing::Feral(feral) = draw.under_cursor {
+                let d = &main.feral_data[feral.0];
+                if slots_contains(d.slots.as_slice(), ITEM_BULLET, 1) {
+                    write!(draw.stupid, "Press {} to Reload\n", input.bindings.hint(Action::Reload)).unwrap();
+                    mq::draw_rectangle_lines(select_pos.x, select_pos.y, select_size.x, select_size.y, 8.0, mq::GREEN);
+                }
+            } else if main.player_gun_ammo == 0 {
+                write!(draw.stupid, "Find some Bullets!\n").unwrap();
+            }
+
+            out.sprite(vec2(mouse_x, mouse_y), Mat2::from_diagonal(vec2(64.0, 64.0)), draw.world.sprites.crosshair, mq::WHITE);
+
+            if draw.stupid.is_empty().not() {
+                stupid_rectangle(&draw.stupid, vec2(mouse_x, mouse_y), true, draw.font.as_ref(), screen_size, view_scale, draw.ui.scale);
+                draw.stupid.clear();
+            }
+        }
+    }
+
+    // pause or sim speed, top middle
+    let mut time_label = main.time.label();
+    if main.sandbox.enabled {
+        time_label = format!("SANDBOX {}", time_label).trim_end().to_owned();
+    }
+    if time_label.is_empty().not() {
+        draw_tooltip(&time_label, vec2(0.5 * screen_size.x, 0.0), true, draw.font.as_ref(), screen_size, draw.ui.scale);
+    }
+
+    draw.debug.draw(main, draw, vec2(mouse_x, mouse_y));
+
+    // console, bottom left
+    if draw.console.open {
+        let log = &draw.console.log;
+        let mut text = log[log.len().saturating_sub(CONSOLE_LINES)..].join("\n");
+        write!(text, "\n> {}_", draw.console.line).unwrap();
+        draw_tooltip(&text, vec2(0.0, screen_size.y), false, draw.font.as_ref(), screen_size, draw.ui.scale);
+    }
+
+    draw.minimap.draw(main, &view);
+    draw.ui.draw(&draw.pages, draw.font.as_ref());
+}
+
+/// Draws everything in the world, but not the cursor or UI
+pub fn draw_world(main: &GameMain, world: &mut WorldDraw, view: &View, out: &mut impl Renderer) {
+
+    let view_offset = view.offset;
+    let view_scale = view.scale;
+
+    out.clear(mq::Color::from_hex(0x274023));
+
+    // grid background
+
+    let ofx = view_offset.x % (TILE_SIZE.x*view_scale*2.0);
+    let ofy = view_offset.y % (TILE_SIZE.y*view_scale*2.0);
+
+    let tile_w = (view.size.x / (TILE_SIZE.x*view_scale)) as i32 / 2 + 3;
+    let tile_h = (view.size.y / (TILE_SIZE.y*view_scale)) as i32 + 4;
+
+
+    for y in 0..tile_h {
+        for x in 0..tile_w {
+
+            let sx = TILE_SIZE.x*view_scale * ((x*2 + (y%2) - 2) as f32);
+            let sy = TILE_SIZE.y*view_scale * ((y - 1) as f32);
+
+            out.rectangle(vec2(ofx + sx, ofy + sy), TILE_SIZE*view_scale, mq::Color::from_hex(0x35552f));
+        }
+    }
+
+
+
+
+    // Draw rail
+    for rail in &main.rail {
+
+        let pos = (rail.pos.as_vec2() + 0.5) * TILE_SIZE * view_scale + view_offset;
+
+        let mat = dir_to_mat2(&rail.dir) * Mat2::from_diagonal(TILE_SIZE * view_scale);
+
+        let coord = match rail.bend {
+            Bend::Forward => world.sprites.rail,
+            Bend::Right   => world.sprites.rail_bend,
+            Bend::Left    => flip_y(world.sprites.rail_bend)
+        };
+
+        if view.on_screen_mat(pos, mat) { out.sprite(pos, mat, coord, mq::WHITE); }
+    }
+
+    // Draw machines
+    for id in main.mach_ids.iter_ids() {
+        let d = &main.mach_data[id.0];
+        if let Some(pos) = d.pos {
+            let dpos = (pos.as_vec2() + 0.5) * TILE_SIZE * view_scale + view_offset;
+
+            if view.on_screen(dpos, TILE_SIZE).not() {
+                continue;
+            }
+
+            let mat = Mat2::from_diagonal(TILE_SIZE * view_scale);
+            let matrot = dir_to_mat2(&d.dir) * mat;
+
+            let (_, on_floor) = mach_sprite(&world.sprites, &d.spec);
+            let frame = world.mach_frame(id, &d.spec);
+            let (fpos, fmat) = frame.apply(dpos, matrot);
+
+            if on_floor {
+                out.sprite(fpos, fmat, frame.sprite, mq::Color::new(1.0, 1.0, 1.0, frame.alpha));
+            } else {
+                world.stupidraw.push((dpos.y, fpos, fmat, frame.sprite));
+            }
+
+            if let MachineSpec::Conveyor { item, filter, can_move_time_us: _, can_dump_time_us: _} = &d.spec {
+                if item.count != 0 {
+                    world.stupidraw.push((dpos.y, dpos, mat, main.itemtype_data[item.itemtype.0].sprite));
+                } else if *filter && item.itemtype != Default::default() {
+                    let mat = Mat2::from_diagonal(TILE_SIZE * view_scale * 0.5);
+                    out.sprite(dpos, mat, main.itemtype_data[item.itemtype.0].sprite, mq::Color::new(1.0, 1.0, 1.0, 0.5));
+                }
+            }
+        }
+    }
+
+    // Draw ghosts
+    for (pos, ghost) in main.ghosts.iter() {
+        let dpos = (pos.as_uvec2().as_vec2() + 0.5) * TILE_SIZE * view_scale + view_offset;
+        if view.on_screen(dpos, TILE_SIZE) {
+            let (sprite, _) = mach_sprite(&world.sprites, &ghost.spec);
+            out.sprite(dpos, dir_to_mat2(&ghost.dir) * Mat2::from_diagonal(TILE_SIZE * view_scale), sprite, GHOST_COLOR);
+        }
+    }
+
+    // Draw drones
+    for id in main.drone_ids.iter_ids() {
+
+        let pos = main.drone_pos[id.0] * view_scale + view_offset;
+
+        let mat = Mat2::from_diagonal(TILE_SIZE * view_scale);
+
+        if view.on_screen_mat(pos, mat) {
+
+            let anim = world.drone_anim.get(id.0).copied().unwrap_or(Anim::new(world.clips.drone));
+            let frame = anim.frame(&world.anims);
+            let (fpos, fmat) = frame.apply(pos, mat);
+
+            world.stupidraw.push((pos.y, fpos, fmat, frame.sprite));
+        }
+    }
+
+    // Draw player
+    {
+        let frame = *world.player_anim.frame(&world.anims);
+
+        // lean the other way every other hop
+        let frame = if main.hop_count % 2 == 0 { frame } else { AnimFrame{ rotation: -frame.rotation, ..frame } };
+
+        let coord = if main.player_facing == 1 { frame.sprite } else { flip_x(frame.sprite) };
+        let pos = main.player_pos * view_scale + view_offset;
+        let (fpos, fmat) = frame.apply(pos, Mat2::from_diagonal(TILE_SIZE * view_scale));
+
+        world.stupidraw.push((pos.y, fpos, fmat, coord));
+    }
+
+    // Draw bullets
+    for id in main.bullet_ids.iter_ids() {
+
+        let dir = main.bullet_data[id.0].dir;
+        let pos = -dir * TILE_SIZE*0.4 + main.bullet_pos[id.0] * view_scale + view_offset;
+
+
+        let mat = Mat2::from_diagonal(TILE_SIZE * view_scale) * Mat2::from_cols(dir, rot_cw_90(dir));
+
+        if view.on_screen_mat(pos, mat)
+        {
+            world.stupidraw.push((pos.y, pos, mat, world.sprites.bullet));
+        }
+    }
+
+    // Draw feral items
+    for id in main.feral_ids.iter_ids() {
+
+        let d = &main.feral_data[id.0];
+
+        let dpos = (d.pos.as_vec2() + 0.5) * TILE_SIZE * view_scale + view_offset;
+
+        let mat = Mat2::from_diagonal(TILE_SIZE * view_scale);
+
+        if view.on_screen(dpos, TILE_SIZE * 2.0).not() {
+            continue;
+        }
+
+        let count = d.slots.iter().filter(|a| a.is_some()).count();
+
+        let mut draw_it_uwu = |idx: usize, offset: Vec2| {
+            let Some(asdf) = &d.slots[idx] else { panic!() };
+            let dit = &main.itemtype_data[asdf.itemtype.0];
+            world.stupidraw.push((dpos.y, dpos + offset*TILE_SIZE, mat, dit.sprite));
+        };
+
+        match count {
+            1 => {
+                draw_it_uwu(0, vec2(0.0, 0.0));
+            },
+            2 => {
+                draw_it_uwu(1, vec2(-0.125, -0.0625));
+                draw_it_uwu(0, vec2(0.125, 0.0625));
+            },
+            3 => {
+                draw_it_uwu(2, vec2(0.125, -0.125));
+                draw_it_uwu(1, vec2(-0.125, 0.0));
+                draw_it_uwu(0, vec2(0.125, 0.125));
+            },
+            4 => {
+                draw_it_uwu(3, vec2(-0.125, -0.1875));
+                draw_it_uwu(2, vec2(0.125, -0.0625));
+                draw_it_uwu(1, vec2(-0.125, 0.0625));
+                draw_it_uwu(0, vec2(0.125, 0.1875));
+            },
+
+            _ => {
+                panic!();
+            }
+        };
+    }
+
+    // Draw sprites
+    world.stupidraw.sort_by(|lhs, rhs| lhs.0.partial_cmp(&rhs.0).unwrap() );
+    for args in &world.stupidraw {
+        out.sprite(args.1, args.2, args.3, mq::WHITE);
+    }
+    world.stupidraw.clear();
+
+    world.particles.draw(view, out);
+
+    // Draw effects
+    for (pos, anim) in &world.effects {
+        let frame = anim.frame(&world.anims);
+        let (fpos, fmat) = frame.apply(*pos * view_scale + view_offset, Mat2::from_diagonal(TILE_SIZE * view_scale));
+        out.sprite(fpos, fmat, frame.sprite, mq::Color::new(1.0, 1.0, 1.0, frame.alpha));
+    }
+
+    {
+        let tl = vec2(0.0, 0.0) + view_offset;
+        let sz = main.world_size.as_vec2() * TILE_SIZE * view_scale;
+        out.rectangle_lines(tl, sz, 2.0, mq::RED);
+    }
+}
+
+pub fn on_screen_mat(pos: Vec2, tf: Mat2) -> bool {
+    let aabb = vec2(f32::max(tf.x_axis.x.abs(), tf.y_axis.x.abs()),
+                    f32::max(tf.x_axis.y.abs(), tf.y_axis.y.abs()));
+
+    return on_screen(pos, aabb);
+}
+
+pub fn on_screen(pos: Vec2, aabb: Vec2) -> bool {
+
+    if pos.x + aabb.x <= 0.0 { return false; }
+    if pos.y + aabb.y <= 0.0 { return false; }
+    if pos.x - aabb.x >= mq::screen_width()  { return false; }
+    if pos.y - aabb.y >= mq::screen_height() { return false; }
+
+    return true;
+}
+
+pub fn draw_texture_gwah_checked(texture: &mq::Texture2D, pos: Vec2, tf: Mat2, coord: (Vec2,Vec2), color: mq::Color) {
+
+    if on_screen_mat(pos, tf) { draw_texture_gwah(&texture, pos, tf, coord, color); }
+}
+
+
+pub fn draw_texture_gwah(texture: &mq::Texture2D, pos: Vec2, tf: Mat2, coord: (Vec2,Vec2), color: mq::Color) {
+
+    let vpos = [
+        pos + tf.mul_vec2(vec2(-0.5, -0.5)),
+        pos + tf.mul_vec2(vec2( 0.5, -0.5)),
+        pos + tf.mul_vec2(vec2( 0.5,  0.5)),
+        pos + tf.mul_vec2(vec2(-0.5,  0.5))
+    ];
+
+    let vrtx = [
+        mq::Vertex::new(vpos[0].x.floor(), vpos[0].y.floor(), 0.0, coord.0.x, coord.0.y, color),
+        mq::Vertex::new(vpos[1].x.floor(), vpos[1].y.floor(), 0.0, coord.1.x, coord.0.y, color),
+        mq::Vertex::new(vpos[2].x.floor(), vpos[2].y.floor(), 0.0, coord.1.x, coord.1.y, color),
+        mq::Vertex::new(vpos[3].x.floor(), vpos[3].y.floor(), 0.0, coord.0.x, coord.1.y, color),
+    ];
+
+    let indx: [u16; 6] = [0, 1, 2, 0, 2, 3];
+
+    let gl = unsafe { mq::get_internal_gl().quad_gl };
+
+    gl.draw_mode(mq::DrawMode::Triangles);
+    gl.texture(Some(texture));
+    gl.geometry(&vrtx, &indx);
+}
+
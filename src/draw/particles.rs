@@ -0,0 +1,177 @@
+use crate::draw::*;
+use crate::lgrn;
+
+use glam::{vec2, Vec2};
+
+lgrn::id_type!(ParticleId);
+
+/// Square that flies off, slows down, and fades from color_start to color_end. In world pixels.
+#[derive(Clone, Copy, Debug)]
+pub struct Particle {
+    pub pos:            Vec2,
+    pub vel:            Vec2,
+    pub drag:           f32,
+    pub size:           f32,
+    pub age:            f32,
+    pub life:           f32,
+    pub color_start:    mq::Color,
+    pub color_end:      mq::Color
+}
+
+impl Particle {
+    pub fn color(&self) -> mq::Color {
+        let t = (self.age / self.life).clamp(0.0, 1.0);
+        let (a, b) = (self.color_start, self.color_end);
+        mq::Color::new(a.r + (b.r - a.r) * t, a.g + (b.g - a.g) * t, a.b + (b.b - a.b) * t, a.a + (b.a - a.a) * t)
+    }
+}
+
+/// How to spawn a burst of particles. Ranges are (min, max) and picked randomly per particle.
+#[derive(Clone, Copy, Debug)]
+pub struct Emitter {
+    pub count:          u32,
+    /// half of the angle that particles spread out over around dir, PI for all directions
+    pub spread:         f32,
+    pub speed:          (f32, f32),
+    pub life:           (f32, f32),
+    pub size:           (f32, f32),
+    pub drag:           f32,
+    pub color_start:    mq::Color,
+    pub color_end:      mq::Color
+}
+
+/// Fixed-size pool of particles. Bursts just spawn fewer once it's full.
+pub struct Particles {
+    pub ids:    lgrn::IdReg<ParticleId>,
+    pub data:   Vec<Particle>
+}
+
+impl Particles {
+    pub fn new(max: usize) -> Particles {
+        let mut ids = lgrn::IdReg::default();
+        ids.resize(max);
+        let data = vec![Particle{ pos: Vec2::ZERO, vel: Vec2::ZERO, drag: 0.0, size: 0.0, age: 0.0, life: 0.0, color_start: mq::WHITE, color_end: mq::WHITE }; ids.capacity()];
+        Particles{ ids, data }
+    }
+
+    pub fn spawn(&mut self, particle: Particle) -> Option<ParticleId> {
+        let id = self.ids.create()?;
+        self.data[id.0] = particle;
+        Some(id)
+    }
+
+    pub fn burst(&mut self, emitter: &Emitter, pos: Vec2, dir: Vec2) {
+        let base = dir.y.atan2(dir.x);
+        let range = |r: (f32, f32)| mq::gen_range(r.0, r.1.max(r.0 + f32::EPSILON));
+
+        for _ in 0..emitter.count {
+            let angle = base + mq::gen_range(-emitter.spread, emitter.spread);
+            let particle = Particle{
+                pos,
+                vel:            Vec2::from_angle(angle) * range(emitter.speed),
+                drag:           emitter.drag,
+                size:           range(emitter.size),
+                age:            0.0,
+                life:           range(emitter.life),
+                color_start:    emitter.color_start,
+                color_end:      emitter.color_end
+            };
+            if self.spawn(particle).is_none() {
+                break;
+            }
+        }
+    }
+
+    pub fn update(&mut self, delta: f32) {
+        let mut dead = Vec::new();
+        for id in self.ids.iter_ids() {
+            let p = &mut self.data[id.0];
+            p.age += delta;
+            if p.age >= p.life {
+                dead.push(id);
+                continue;
+            }
+            p.pos += p.vel * delta;
+            p.vel *= (1.0 - p.drag * delta).max(0.0);
+        }
+        for id in dead {
+            self.ids.remove(id);
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.ids.iter_ids().count()
+    }
+
+    pub fn draw(&self, view: &View, out: &mut impl Renderer) {
+        for id in self.ids.iter_ids() {
+            let p = &self.data[id.0];
+            let size = vec2(p.size, p.size) * view.scale;
+            let pos = p.pos * view.scale + view.offset;
+            if view.on_screen(pos, size) {
+                out.rectangle(pos - size * 0.5, size, p.color());
+            }
+        }
+    }
+}
+
+/// Bursts used for game events
+#[derive(Clone, Copy, Debug)]
+pub struct WorldEmitters {
+    pub explosion:      Emitter,
+    pub smoke:          Emitter,
+    pub muzzle_flash:   Emitter,
+    pub sparks:         Emitter,
+    pub obfuscate:      Emitter
+}
+
+impl Default for WorldEmitters {
+    fn default() -> Self {
+        use std::f32::consts::PI;
+        WorldEmitters{
+            explosion: Emitter{
+                count: 24, spread: PI, speed: (150.0, 450.0), life: (0.2, 0.5), size: (6.0, 14.0), drag: 6.0,
+                color_start: mq::Color::from_hex(0xffe070), color_end: mq::Color::new(0.8, 0.1, 0.0, 0.0)
+            },
+            smoke: Emitter{
+                count: 10, spread: PI, speed: (20.0, 80.0), life: (0.5, 1.0), size: (16.0, 28.0), drag: 2.0,
+                color_start: mq::Color::new(0.3, 0.3, 0.3, 0.6), color_end: mq::Color::new(0.2, 0.2, 0.2, 0.0)
+            },
+            muzzle_flash: Emitter{
+                count: 6, spread: 0.3, speed: (300.0, 700.0), life: (0.05, 0.12), size: (4.0, 8.0), drag: 10.0,
+                color_start: mq::Color::from_hex(0xfff4b0), color_end: mq::Color::new(1.0, 0.6, 0.1, 0.0)
+            },
+            sparks: Emitter{
+                count: 8, spread: 0.7, speed: (200.0, 500.0), life: (0.1, 0.25), size: (3.0, 5.0), drag: 8.0,
+                color_start: mq::WHITE, color_end: mq::Color::new(1.0, 0.9, 0.4, 0.0)
+            },
+            obfuscate: Emitter{
+                count: 4, spread: PI, speed: (10.0, 60.0), life: (0.4, 0.9), size: (6.0, 12.0), drag: 1.0,
+                color_start: mq::Color::from_hex(0xff3c8c), color_end: mq::Color::new(0.6, 0.1, 0.9, 0.0)
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_particles_capped() {
+        let mut particles = Particles::new(64);
+        let emitter = WorldEmitters::default().explosion;
+
+        for _ in 0..10 {
+            particles.burst(&emitter, vec2(100.0, 100.0), vec2(1.0, 0.0));
+        }
+        assert_eq!(particles.count(), 64);
+
+        // everything fades out and gets reused
+        particles.update(emitter.life.1);
+        assert_eq!(particles.count(), 0);
+        particles.burst(&emitter, vec2(0.0, 0.0), vec2(1.0, 0.0));
+        assert_eq!(particles.count(), emitter.count as usize);
+    }
+}
@@ -0,0 +1,519 @@
+use crate::draw::*;
+use crate::input::{Action, Bindings};
+use crate::settings::{Settings, UI_SCALE_MAX, UI_SCALE_MIN, WINDOW_SIZES};
+
+use glam::vec2;
+use std::ops::Not;
+
+/// Every item type with its icon. Click one to read what it is.
+#[derive(Default)]
+pub struct ItemBook {
+    pub root:   Option<WidgetId>,
+    close:      Option<WidgetId>,
+    rows:       Vec<(WidgetId, ItemTypeId)>,
+    desc:       Option<WidgetId>
+}
+
+impl ItemBook {
+
+    pub fn is_open(&self) -> bool {
+        self.root.is_some()
+    }
+
+    pub fn open(&mut self, ui: &mut Ui, main: &GameMain) {
+        if self.is_open() {
+            return;
+        }
+        let root = ui.add_root(Anchor::Center, Widget::panel(Layout::Stack{ horizontal: false }).with_min_size(vec2(360.0, 0.0)));
+        let header = ui.add(root, Widget::panel(Layout::Stack{ horizontal: true }));
+        ui.add(header, Widget::label("Item Book").with_min_size(vec2(240.0, 0.0)));
+        self.close = Some(ui.add(header, Widget::button("Close")));
+
+        let list = ui.add(root, Widget::list(320.0));
+        self.rows = main.itemtype_data.iter().enumerate()
+            .map(|(i, it)| {
+                let row = Widget::button(it.name).with_icon(it.sprite).with_tooltip(it.desc).with_min_size(vec2(320.0, 0.0));
+                (ui.add(list, row), ItemTypeId(i))
+            })
+            .collect();
+        self.desc = Some(ui.add(root, Widget::label("")));
+        self.root = Some(root);
+    }
+
+    pub fn close(&mut self, ui: &mut Ui) {
+        if let Some(root) = self.root.take() {
+            ui.remove(root);
+        }
+        self.rows.clear();
+        self.close = None;
+        self.desc = None;
+    }
+
+    pub fn toggle(&mut self, ui: &mut Ui, main: &GameMain) {
+        if self.is_open() { self.close(ui); } else { self.open(ui, main); }
+    }
+
+    /// Returns true if the event was for this
+    pub fn handle(&mut self, ui: &mut Ui, main: &GameMain, event: UiEvent) -> bool {
+        let UiEvent::Clicked(id) = event;
+        if self.close == Some(id) {
+            self.close(ui);
+            return true;
+        }
+        let Some(&(_, itemtype)) = self.rows.iter().find(|(row, _)| *row == id) else {
+            return false;
+        };
+        for &(row, _) in &self.rows {
+            ui[row].selected = row == id;
+        }
+        if let Some(desc) = self.desc {
+            let it = &main.itemtype_data[itemtype.0];
+            ui[desc].kind = WidgetKind::Label(format!("{}\n{}\nStacks up to {}", it.name, it.desc, it.stackable));
+        }
+        true
+    }
+}
+
+/// Settings for one placed machine, opened by clicking on it. Changes go through
+/// Machine::configure.
+#[derive(Default)]
+pub struct MachineConfig {
+    pub mach:   Option<MachineId>,
+    root:       Option<WidgetId>,
+    close:      Option<WidgetId>,
+    buttons:    Vec<(WidgetId, MachineSetting)>,
+    /// dir, enabled, filter, and target that the panel was last built with
+    shown:      Option<(u8, bool, ItemTypeId, TurretTarget)>
+}
+
+fn config_key(m: &Machine) -> (u8, bool, ItemTypeId, TurretTarget) {
+    let filter = match &m.spec {
+        MachineSpec::Conveyor{ item, filter: true, .. } => item.itemtype,
+        _ => Default::default()
+    };
+    let target = match &m.spec {
+        MachineSpec::Turret{ target, .. } => *target,
+        _ => Default::default()
+    };
+    (m.dir as u8, m.enabled, filter, target)
+}
+
+impl MachineConfig {
+
+    pub fn open(&mut self, mach: MachineId) {
+        self.mach = Some(mach);
+        self.shown = None;
+    }
+
+    pub fn close(&mut self, ui: &mut Ui) {
+        if let Some(root) = self.root.take() {
+            ui.remove(root);
+        }
+        self.mach = None;
+        self.close = None;
+        self.buttons.clear();
+        self.shown = None;
+    }
+
+    /// Call every frame. Closes if the machine got picked up, and rebuilds if its settings changed
+    pub fn update(&mut self, ui: &mut Ui, main: &GameMain, sprites: &WorldSprites) {
+        let Some(mach) = self.mach else {
+            return;
+        };
+        if main.mach_ids.exists(mach).not() || main.mach_data[mach.0].pos.is_none() {
+            self.close(ui);
+            return;
+        }
+        let key = config_key(&main.mach_data[mach.0]);
+        if self.shown != Some(key) {
+            self.shown = Some(key);
+            self.build(ui, main, sprites, mach);
+        }
+    }
+
+    fn build(&mut self, ui: &mut Ui, main: &GameMain, sprites: &WorldSprites, mach: MachineId) {
+        if let Some(root) = self.root.take() {
+            ui.remove(root);
+        }
+        self.buttons.clear();
+
+        let m = &main.mach_data[mach.0];
+        let pos = m.pos.unwrap_or_default();
+        let name = match &m.spec {
+            MachineSpec::None                           => "Machine",
+            MachineSpec::Turret{..}                     => "Turret",
+            MachineSpec::Conveyor{ filter: false, .. }  => "Conveyor",
+            MachineSpec::Conveyor{ filter: true, .. }   => "Filterveyor"
+        };
+
+        let root = ui.add_root(Anchor::TopLeft, Widget::panel(Layout::Stack{ horizontal: false }));
+        let header = ui.add(root, Widget::panel(Layout::Stack{ horizontal: true }));
+        ui.add(header, Widget::label(&format!("{} at {}, {}", name, pos.x, pos.y)).with_icon(mach_sprite(sprites, &m.spec).0).with_min_size(vec2(220.0, 0.0)));
+        self.close = Some(ui.add(header, Widget::button("Close")));
+
+        let rotation = ui.add(root, Widget::panel(Layout::Stack{ horizontal: true }));
+        ui.add(rotation, Widget::label(&format!("Facing {:?}", m.dir)).with_min_size(vec2(120.0, 0.0)));
+        let left = ui.add(rotation, Widget::button("Rotate Left"));
+        let right = ui.add(rotation, Widget::button("Rotate Right"));
+        let power = ui.add(root, Widget::button(if m.enabled { "Enabled" } else { "Disabled" }).with_selected(m.enabled).with_tooltip("Disabled machines don't do anything"));
+        self.buttons.extend([
+            (left,  MachineSetting::Rotate{ clockwise: false }),
+            (right, MachineSetting::Rotate{ clockwise: true }),
+            (power, MachineSetting::Enabled(m.enabled.not()))
+        ]);
+
+        match &m.spec {
+            MachineSpec::Conveyor{ item, filter: true, .. } => {
+                ui.add(root, Widget::label("Filter"));
+                let grid = ui.add(root, Widget::panel(Layout::Grid{ columns: 5 }));
+                let current = (item.itemtype != Default::default()).then_some(item.itemtype);
+                let any = ui.add(grid, Widget::button("Any").with_selected(current.is_none()).with_enabled(item.count == 0));
+                self.buttons.push((any, MachineSetting::Filter(None)));
+                for (i, it) in main.itemtype_data.iter().enumerate() {
+                    let itemtype = ItemTypeId(i);
+                    let button = Widget::button("").with_icon(it.sprite).with_tooltip(it.name)
+                        .with_selected(current == Some(itemtype))
+                        .with_enabled(item.count == 0 || current == Some(itemtype));
+                    self.buttons.push((ui.add(grid, button), MachineSetting::Filter(Some(itemtype))));
+                }
+            },
+            MachineSpec::Turret{ target, .. } => {
+                ui.add(root, Widget::label("Targeting"));
+                let row = ui.add(root, Widget::panel(Layout::Stack{ horizontal: true }));
+                let ahead = ui.add(row, Widget::button("Ahead").with_selected(*target == TurretTarget::Ahead).with_tooltip("Shoots whatever crosses in front"));
+                let nearest = ui.add(row, Widget::button("Nearest").with_selected(*target == TurretTarget::Nearest).with_tooltip("Shoots the closest drone in range, any direction"));
+                self.buttons.push((ahead, MachineSetting::Target(TurretTarget::Ahead)));
+                self.buttons.push((nearest, MachineSetting::Target(TurretTarget::Nearest)));
+            },
+            // machines with recipes would get a recipe picker here
+            _ => {}
+        }
+
+        self.root = Some(root);
+    }
+
+    /// Returns true if the event was for this
+    pub fn handle(&mut self, ui: &mut Ui, main: &mut GameMain, event: UiEvent) -> bool {
+        let UiEvent::Clicked(id) = event;
+        if self.close == Some(id) {
+            self.close(ui);
+            return true;
+        }
+        let (Some(mach), Some(&(_, setting))) = (self.mach, self.buttons.iter().find(|(button, _)| *button == id)) else {
+            return false;
+        };
+        // panel gets rebuilt by update if anything changed
+        main.mach_data[mach.0].configure(setting);
+        true
+    }
+}
+
+/// What a button in the settings menu does
+#[derive(Clone, Copy, Debug)]
+enum SettingsButton {
+    Close,
+    MasterVolume(f32),
+    SfxVolume(f32),
+    MusicVolume(f32),
+    MusicMuted,
+    WindowSize,
+    Fullscreen,
+    UiScale(f32),
+    ScreenShake,
+    Flashes,
+    Rebind(Action),
+    ResetBindings
+}
+
+/// Volume, window, UI scale, accessibility, and controls. Changes go into Settings right away,
+/// and changed gets set for the caller to apply and save them.
+#[derive(Default)]
+pub struct SettingsMenu {
+    pub root:       Option<WidgetId>,
+    buttons:        Vec<(WidgetId, SettingsButton)>,
+    list:           Option<WidgetId>,
+    /// action waiting for a key to get bound to it
+    pub rebinding:  Option<Action>,
+    pub changed:    bool
+}
+
+fn step_volume(volume: f32, step: f32) -> f32 {
+    ((volume + step) * 10.0).round().clamp(0.0, 10.0) / 10.0
+}
+
+fn on_off(on: bool) -> &'static str {
+    if on { "On" } else { "Off" }
+}
+
+impl SettingsMenu {
+
+    pub fn is_open(&self) -> bool {
+        self.root.is_some()
+    }
+
+    pub fn open(&mut self, ui: &mut Ui, settings: &Settings) {
+        if self.is_open().not() {
+            self.build(ui, settings);
+        }
+    }
+
+    pub fn close(&mut self, ui: &mut Ui) {
+        if let Some(root) = self.root.take() {
+            ui.remove(root);
+        }
+        self.buttons.clear();
+        self.list = None;
+        self.rebinding = None;
+    }
+
+    pub fn toggle(&mut self, ui: &mut Ui, settings: &Settings) {
+        if self.is_open() { self.close(ui); } else { self.open(ui, settings); }
+    }
+
+    fn row(&mut self, ui: &mut Ui, root: WidgetId, name: &str, value: &str, minus: SettingsButton, plus: SettingsButton) {
+        let row = ui.add(root, Widget::panel(Layout::Stack{ horizontal: true }));
+        ui.add(row, Widget::label(name).with_min_size(vec2(160.0, 0.0)));
+        let minus_id = ui.add(row, Widget::button("-"));
+        ui.add(row, Widget::label(value).with_min_size(vec2(70.0, 0.0)));
+        let plus_id = ui.add(row, Widget::button("+"));
+        self.buttons.extend([(minus_id, minus), (plus_id, plus)]);
+    }
+
+    fn toggle_button(&mut self, ui: &mut Ui, parent: WidgetId, text: &str, on: bool, tooltip: &str, button: SettingsButton) {
+        let id = ui.add(parent, Widget::button(&format!("{}: {}", text, on_off(on))).with_selected(on).with_tooltip(tooltip));
+        self.buttons.push((id, button));
+    }
+
+    /// Rebuilds everything, keeping where the controls list was scrolled to
+    fn build(&mut self, ui: &mut Ui, settings: &Settings) {
+        let scroll = self.list.filter(|&list| ui.exists(list)).map_or(0.0, |list| ui[list].scroll);
+        if let Some(root) = self.root.take() {
+            ui.remove(root);
+        }
+        self.buttons.clear();
+
+        let root = ui.add_root(Anchor::Center, Widget::panel(Layout::Stack{ horizontal: false }));
+        let header = ui.add(root, Widget::panel(Layout::Stack{ horizontal: true }));
+        ui.add(header, Widget::label("Settings").with_min_size(vec2(340.0, 0.0)));
+        let close = ui.add(header, Widget::button("Close"));
+        self.buttons.push((close, SettingsButton::Close));
+
+        let percent = |v: f32| format!("{}%", (v * 100.0).round());
+        self.row(ui, root, "Master volume", &percent(settings.master_volume), SettingsButton::MasterVolume(-0.1), SettingsButton::MasterVolume(0.1));
+        self.row(ui, root, "Effects volume", &percent(settings.sfx_volume), SettingsButton::SfxVolume(-0.1), SettingsButton::SfxVolume(0.1));
+        self.row(ui, root, "Music volume", &percent(settings.music_volume), SettingsButton::MusicVolume(-0.1), SettingsButton::MusicVolume(0.1));
+        self.toggle_button(ui, root, "Music", settings.music_muted.not(), "Same as the mute music binding", SettingsButton::MusicMuted);
+
+        let window = ui.add(root, Widget::panel(Layout::Stack{ horizontal: true }));
+        ui.add(window, Widget::label("Window").with_min_size(vec2(160.0, 0.0)));
+        let size = ui.add(window, Widget::button(&format!("{} x {}", settings.window_size.x, settings.window_size.y))
+            .with_enabled(settings.fullscreen.not()).with_tooltip("Click for the next size"));
+        self.buttons.push((size, SettingsButton::WindowSize));
+        self.toggle_button(ui, window, "Fullscreen", settings.fullscreen, "Fill the whole screen", SettingsButton::Fullscreen);
+
+        self.row(ui, root, "UI scale", &percent(settings.ui_scale), SettingsButton::UiScale(-0.25), SettingsButton::UiScale(0.25));
+
+        let access = ui.add(root, Widget::panel(Layout::Stack{ horizontal: true }));
+        self.toggle_button(ui, access, "Screen shake", settings.screen_shake, "Shake the view when things blow up", SettingsButton::ScreenShake);
+        self.toggle_button(ui, access, "Flashes", settings.flashes, "Muzzle flashes and explosions", SettingsButton::Flashes);
+
+        let controls = ui.add(root, Widget::panel(Layout::Stack{ horizontal: true }));
+        ui.add(controls, Widget::label("Controls, click one then press a key").with_min_size(vec2(340.0, 0.0)));
+        let reset = ui.add(controls, Widget::button("Reset").with_tooltip("Back to the bindings file"));
+        self.buttons.push((reset, SettingsButton::ResetBindings));
+
+        let list = ui.add(root, Widget::list(240.0));
+        for action in Action::ALL {
+            let text = if self.rebinding == Some(action) {
+                format!("{}: press a key, Escape to cancel", action.name())
+            } else {
+                let bindings: Vec<String> = settings.bindings.get(action).map(|b| b.label()).collect();
+                format!("{}: {}", action.name(), if bindings.is_empty() { "unbound".to_owned() } else { bindings.join(", ") })
+            };
+            let row = ui.add(list, Widget::button(&text).with_selected(self.rebinding == Some(action)).with_min_size(vec2(420.0, 0.0)));
+            self.buttons.push((row, SettingsButton::Rebind(action)));
+        }
+        ui[list].scroll = scroll;
+
+        self.list = Some(list);
+        self.root = Some(root);
+    }
+
+    /// Returns true if the event was for this. base is the bindings file, for Reset.
+    pub fn handle(&mut self, ui: &mut Ui, settings: &mut Settings, base: &Bindings, event: UiEvent) -> bool {
+        let UiEvent::Clicked(id) = event;
+        let Some(&(_, button)) = self.buttons.iter().find(|(b, _)| *b == id) else {
+            return false;
+        };
+        match button {
+            SettingsButton::Close => {
+                self.close(ui);
+                return true;
+            },
+            SettingsButton::MasterVolume(step)  => settings.master_volume = step_volume(settings.master_volume, step),
+            SettingsButton::SfxVolume(step)     => settings.sfx_volume = step_volume(settings.sfx_volume, step),
+            SettingsButton::MusicVolume(step)   => settings.music_volume = step_volume(settings.music_volume, step),
+            SettingsButton::MusicMuted          => settings.music_muted = settings.music_muted.not(),
+            SettingsButton::WindowSize => {
+                let next = WINDOW_SIZES.iter().position(|&s| s == settings.window_size).map_or(0, |i| (i + 1) % WINDOW_SIZES.len());
+                settings.window_size = WINDOW_SIZES[next];
+            },
+            SettingsButton::Fullscreen          => settings.fullscreen = settings.fullscreen.not(),
+            SettingsButton::UiScale(step)       => settings.ui_scale = (settings.ui_scale + step).clamp(UI_SCALE_MIN, UI_SCALE_MAX),
+            SettingsButton::ScreenShake         => settings.screen_shake = settings.screen_shake.not(),
+            SettingsButton::Flashes             => settings.flashes = settings.flashes.not(),
+            SettingsButton::Rebind(action) => {
+                // nothing changed yet, that happens in key_pressed
+                self.rebinding = if self.rebinding == Some(action) { None } else { Some(action) };
+                self.build(ui, settings);
+                return true;
+            },
+            SettingsButton::ResetBindings       => settings.bindings = base.clone()
+        }
+        self.changed = true;
+        self.build(ui, settings);
+        true
+    }
+
+    /// Call with keys pressed while rebinding is Some. Escape cancels. Returns true if the key
+    /// got used up by this.
+    pub fn key_pressed(&mut self, ui: &mut Ui, settings: &mut Settings, key: mq::KeyCode) -> bool {
+        let Some(action) = self.rebinding else {
+            return false;
+        };
+        if key != mq::KeyCode::Escape {
+            if settings.bindings.set_key(action, key).not() {
+                // can't be saved, keep waiting for another
+                return true;
+            }
+            self.changed = true;
+        }
+        self.rebinding = None;
+        self.build(ui, settings);
+        true
+    }
+}
+
+/// What a button in the sandbox menu does
+#[derive(Clone, Copy, Debug)]
+enum SandboxButton {
+    Close,
+    Enabled,
+    Drones,
+    RailEdit,
+    /// recipe number
+    Machine(u32),
+    Item(ItemTypeId)
+}
+
+/// Creative mode switches, and buttons to grab any item or machine for free. See game::Sandbox
+#[derive(Default)]
+pub struct SandboxMenu {
+    pub root:   Option<WidgetId>,
+    buttons:    Vec<(WidgetId, SandboxButton)>
+}
+
+impl SandboxMenu {
+
+    pub fn is_open(&self) -> bool {
+        self.root.is_some()
+    }
+
+    pub fn open(&mut self, ui: &mut Ui, main: &GameMain) {
+        if self.is_open().not() {
+            self.build(ui, main);
+        }
+    }
+
+    pub fn close(&mut self, ui: &mut Ui) {
+        if let Some(root) = self.root.take() {
+            ui.remove(root);
+        }
+        self.buttons.clear();
+    }
+
+    pub fn toggle(&mut self, ui: &mut Ui, main: &GameMain) {
+        if self.is_open() { self.close(ui); } else { self.open(ui, main); }
+    }
+
+    /// Greyed out while the sandbox is off, except for the one that turns it on
+    fn toggle_button(&mut self, ui: &mut Ui, main: &GameMain, parent: WidgetId, text: &str, tooltip: &str, button: SandboxButton) {
+        let sandbox = &main.sandbox;
+        let on = match button {
+            SandboxButton::Drones   => sandbox.drones,
+            SandboxButton::RailEdit => sandbox.rail_edit,
+            _                       => sandbox.enabled
+        };
+        let enabled = sandbox.enabled || matches!(button, SandboxButton::Enabled);
+        let id = ui.add(parent, Widget::button(&format!("{}: {}", text, on_off(on))).with_selected(on).with_enabled(enabled).with_tooltip(tooltip));
+        self.buttons.push((id, button));
+    }
+
+    fn build(&mut self, ui: &mut Ui, main: &GameMain) {
+        if let Some(root) = self.root.take() {
+            ui.remove(root);
+        }
+        self.buttons.clear();
+        let on = main.sandbox.enabled;
+
+        let root = ui.add_root(Anchor::TopLeft, Widget::panel(Layout::Stack{ horizontal: false }));
+        let header = ui.add(root, Widget::panel(Layout::Stack{ horizontal: true }));
+        ui.add(header, Widget::label("Sandbox").with_min_size(vec2(240.0, 0.0)));
+        let close = ui.add(header, Widget::button("Close"));
+        self.buttons.push((close, SandboxButton::Close));
+
+        self.toggle_button(ui, main, root, "Sandbox", "Free items and machines, for trying out layouts", SandboxButton::Enabled);
+        self.toggle_button(ui, main, root, "Drones", "Keep spawning drones", SandboxButton::Drones);
+        self.toggle_button(ui, main, root, "Edit rail", "Use next to the end of the rail to extend it, or on the end to shorten it", SandboxButton::RailEdit);
+
+        ui.add(root, Widget::label("Machines"));
+        let machines = ui.add(root, Widget::panel(Layout::Stack{ horizontal: true }));
+        for (name, recipe) in [("Turret", 5), ("Conveyor", 6), ("Filterveyor", 7)] {
+            let id = ui.add(machines, Widget::button(name).with_enabled(on));
+            self.buttons.push((id, SandboxButton::Machine(recipe)));
+        }
+
+        ui.add(root, Widget::label("Items, a full stack"));
+        let grid = ui.add(root, Widget::panel(Layout::Grid{ columns: 5 }));
+        for (i, it) in main.itemtype_data.iter().enumerate() {
+            let id = ui.add(grid, Widget::button("").with_icon(it.sprite).with_tooltip(it.name).with_enabled(on));
+            self.buttons.push((id, SandboxButton::Item(ItemTypeId(i))));
+        }
+
+        self.root = Some(root);
+    }
+
+    /// Returns true if the event was for this. Spawning goes through construct so it can be undone.
+    pub fn handle(&mut self, ui: &mut Ui, main: &mut GameMain, event: UiEvent) -> bool {
+        let UiEvent::Clicked(id) = event;
+        let Some(&(_, button)) = self.buttons.iter().find(|(b, _)| *b == id) else {
+            return false;
+        };
+        let spawn = match button {
+            SandboxButton::Machine(recipe)  => Some(Construct::SpawnMachine(recipe)),
+            SandboxButton::Item(itemtype)   => Some(Construct::SpawnItem(itemtype)),
+            _                               => None
+        };
+        if let Some(cmd) = spawn {
+            // ends up in the player's hands
+            if matches!(main.tool, ToolMode::GunPod) {
+                main.tool = ToolMode::Construct(Drag::None);
+            }
+            construct(main, cmd);
+        }
+
+        let sandbox = &mut main.sandbox;
+        match button {
+            SandboxButton::Close => {
+                self.close(ui);
+                return true;
+            },
+            SandboxButton::Enabled      => sandbox.enabled = sandbox.enabled.not(),
+            SandboxButton::Drones       => sandbox.drones = sandbox.drones.not(),
+            SandboxButton::RailEdit     => sandbox.rail_edit = sandbox.rail_edit.not(),
+            SandboxButton::Machine(_) | SandboxButton::Item(_) => {}
+        }
+        self.build(ui, main);
+        true
+    }
+}
@@ -0,0 +1,210 @@
+use crate::draw::*;
+
+use glam::{vec2, Vec2, UVec2, Mat2};
+use image::{RgbaImage, ImageResult};
+
+/// Draws into an image on the CPU, for when there's no GPU around (tests, servers)
+pub struct SoftRenderer {
+    pub pages: Vec<RgbaImage>,
+    pub target: RgbaImage
+}
+
+impl SoftRenderer {
+
+    pub fn new(pages: Vec<RgbaImage>, size: UVec2) -> SoftRenderer {
+        SoftRenderer{ pages, target: RgbaImage::new(size.x, size.y) }
+    }
+
+    /// Loads every page of the atlas
+    pub fn load(atlas: &SpriteAtlas, size: UVec2) -> ImageResult<SoftRenderer> {
+        let pages = atlas.pages.iter().map(|page| Ok(image::open(&page.path)?.to_rgba8())).collect::<ImageResult<_>>()?;
+        Ok(SoftRenderer::new(pages, size))
+    }
+
+    pub fn save_png(&self, path: &str) -> ImageResult<()> {
+        self.target.save_with_format(path, image::ImageFormat::Png)
+    }
+
+    fn blend(target: &mut RgbaImage, x: u32, y: u32, src: [f32; 4]) {
+        let dst = target.get_pixel_mut(x, y);
+        let a = src[3];
+        for (d, s) in dst.0.iter_mut().zip(src).take(3) {
+            *d = (s * 255.0 * a + *d as f32 * (1.0 - a)).round() as u8;
+        }
+        dst.0[3] = ((a + dst.0[3] as f32 / 255.0 * (1.0 - a)) * 255.0).round() as u8;
+    }
+
+    /// Pixel range covering [tl, br), clipped to the target
+    fn clip(&self, tl: Vec2, br: Vec2) -> (UVec2, UVec2) {
+        let size = vec2(self.target.width() as f32, self.target.height() as f32);
+        (tl.floor().clamp(Vec2::ZERO, size).as_uvec2(), br.floor().clamp(Vec2::ZERO, size).as_uvec2())
+    }
+}
+
+fn color_to_f32(color: mq::Color) -> [f32; 4] {
+    [color.r, color.g, color.b, color.a]
+}
+
+impl Renderer for SoftRenderer {
+    fn clear(&mut self, color: mq::Color) {
+        let c = color_to_f32(color).map(|x| (x * 255.0).round() as u8);
+        self.target.pixels_mut().for_each(|p| p.0 = c);
+    }
+
+    fn rectangle(&mut self, pos: Vec2, size: Vec2, color: mq::Color) {
+        let (a, b) = self.clip(pos, pos + size);
+        for y in a.y..b.y {
+            for x in a.x..b.x {
+                SoftRenderer::blend(&mut self.target, x, y, color_to_f32(color));
+            }
+        }
+    }
+
+    fn rectangle_lines(&mut self, pos: Vec2, size: Vec2, thickness: f32, color: mq::Color) {
+        // same layout as mq::draw_rectangle_lines, lines are on the inside
+        let t = thickness;
+        self.rectangle(pos,                                 vec2(size.x, t),             color);
+        self.rectangle(pos + vec2(0.0, size.y - t),         vec2(size.x, t),             color);
+        self.rectangle(pos + vec2(0.0, t),                  vec2(t, size.y - 2.0*t),     color);
+        self.rectangle(pos + vec2(size.x - t, t),           vec2(t, size.y - 2.0*t),     color);
+    }
+
+    fn sprite(&mut self, pos: Vec2, tf: Mat2, sprite: Sprite, color: mq::Color) {
+        if tf.determinant().abs() < f32::EPSILON {
+            return;
+        }
+        let inv = tf.inverse();
+
+        let extent = 0.5 * (tf.x_axis.abs() + tf.y_axis.abs());
+        let (a, b) = self.clip(pos - extent, pos + extent + 1.0);
+
+        let page = &self.pages[sprite.page];
+        let coord = sprite.coord;
+        let tex_size = vec2(page.width() as f32, page.height() as f32);
+        let tint = color_to_f32(color);
+
+        for y in a.y..b.y {
+            for x in a.x..b.x {
+                // nearest texel, sampled at the pixel center
+                let local = inv.mul_vec2(vec2(x as f32 + 0.5, y as f32 + 0.5) - pos) + 0.5;
+                if local.x < 0.0 || 1.0 <= local.x || local.y < 0.0 || 1.0 <= local.y {
+                    continue;
+                }
+                let uv = coord.0 + local * (coord.1 - coord.0);
+                let texel = (uv * tex_size).floor().clamp(Vec2::ZERO, tex_size - 1.0).as_uvec2();
+                let texel = page.get_pixel(texel.x, texel.y).0;
+
+                let src = [0, 1, 2, 3].map(|i| texel[i] as f32 / 255.0 * tint[i]);
+                if src[3] > 0.0 {
+                    SoftRenderer::blend(&mut self.target, x, y, src);
+                }
+            }
+        }
+    }
+}
+
+/// Draws the world as seen through view into a PNG file
+pub fn snapshot_png(main: &GameMain, world: &mut WorldDraw, view: &View, atlas: &SpriteAtlas, out_path: &str) -> ImageResult<()> {
+    let mut out = SoftRenderer::load(atlas, view.size.as_uvec2())?;
+    draw_world(main, world, view, &mut out);
+    out.save_png(out_path)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::uvec2;
+
+    // Set OBFUSCATION_BLESS=1 to overwrite the golden images with whatever gets drawn
+    const GOLDEN_DIR: &str = "tests/golden";
+
+    const ATLAS_PATH: &str = "tf/custom/sprites.atlas";
+
+    fn make_scene(world_size: UVec2) -> GameMain {
+        let atlas = SpriteAtlas::load(ATLAS_PATH).unwrap();
+        let mut main = GameMain{ world_size, player_pos: vec2(-1000.0, -1000.0), ..Default::default() };
+        main.feral_ids.resize(64);
+        main.mach_ids.resize(64);
+        for name in ["item_dead_drone", "item_scrap", "item_battery", "item_alignite", "item_gunpowder", "item_bullet", "item_alignite_clump", "item_obfuscator"] {
+            main.itemtype_data.push(ItemType{ sprite: atlas.sprite(name).unwrap(), stackable: 69, name: "", desc: "" });
+        }
+        main
+    }
+
+    fn check_golden(main: &GameMain, name: &str) {
+        let view = View{ offset: vec2(0.0, 0.0), scale: 0.5, size: main.world_size.as_vec2() * TILE_SIZE * 0.5 };
+        let atlas = SpriteAtlas::load(ATLAS_PATH).unwrap();
+        let mut out = SoftRenderer::load(&atlas, view.size.as_uvec2()).unwrap();
+        let mut world = WorldDraw::new(WorldSprites::from_atlas(&atlas).unwrap());
+        draw_world(main, &mut world, &view, &mut out);
+
+        let path = format!("{}/{}.png", GOLDEN_DIR, name);
+
+        if std::env::var("OBFUSCATION_BLESS").is_ok() {
+            std::fs::create_dir_all(GOLDEN_DIR).unwrap();
+            out.save_png(&path).unwrap();
+            return;
+        }
+
+        let golden = image::open(&path).unwrap().to_rgba8();
+        assert_eq!(golden.dimensions(), out.target.dimensions());
+
+        // a little slack for float differences between platforms
+        let mismatched = golden.pixels().zip(out.target.pixels())
+            .filter(|(a, b)| (0..4).any(|i| a.0[i].abs_diff(b.0[i]) > 8))
+            .count();
+
+        if mismatched > (golden.width() * golden.height() / 500) as usize {
+            out.save_png(&format!("{}/{}.actual.png", GOLDEN_DIR, name)).unwrap();
+            panic!("{} pixels differ from {}", mismatched, path);
+        }
+    }
+
+    #[test]
+    fn test_golden_rail_bends() {
+        let mut main = make_scene(uvec2(5, 4));
+        main.rail = vec![
+            Rail{ pos: uvec2(0, 1), dir: Dir::Right, bend: Bend::Forward },
+            Rail{ pos: uvec2(1, 1), dir: Dir::Right, bend: Bend::Right },
+            Rail{ pos: uvec2(1, 2), dir: Dir::Down,  bend: Bend::Left },
+            Rail{ pos: uvec2(2, 2), dir: Dir::Right, bend: Bend::Left },
+            Rail{ pos: uvec2(2, 1), dir: Dir::Up,    bend: Bend::Right },
+            Rail{ pos: uvec2(3, 1), dir: Dir::Right, bend: Bend::Forward },
+            Rail{ pos: uvec2(4, 1), dir: Dir::Right, bend: Bend::Forward },
+        ];
+        check_golden(&main, "rail_bends");
+    }
+
+    #[test]
+    fn test_golden_machine_rotations() {
+        let mut main = make_scene(uvec2(4, 3));
+        let specs = [
+            MachineSpec::Turret{ ammo: 0, can_fire_time_us: 0, target: Default::default() },
+            MachineSpec::Conveyor{ item: Default::default(), filter: false, can_move_time_us: 0, can_dump_time_us: 0 },
+            MachineSpec::Conveyor{ item: Default::default(), filter: true, can_move_time_us: 0, can_dump_time_us: 0 },
+        ];
+        for (y, spec) in specs.iter().enumerate() {
+            for (x, dir) in [Dir::Right, Dir::Down, Dir::Left, Dir::Up].into_iter().enumerate() {
+                let mach = main.mach_ids.create().unwrap();
+                main.mach_data.resize(main.mach_ids.capacity(), Default::default());
+                let pos = uvec2(x as u32, y as u32);
+                main.mach_data[mach.0] = Machine{ pos: Some(pos), dir, spec: spec.clone(), ..Default::default() };
+                main.mach_by_tile.insert(pos.into(), mach);
+            }
+        }
+        check_golden(&main, "machine_rotations");
+    }
+
+    #[test]
+    fn test_golden_feral_stacking() {
+        let mut main = make_scene(uvec2(4, 1));
+        for x in 0..4 {
+            for i in 0..=x {
+                let slot = ItemSlot{ itemtype: ItemTypeId(i as usize), count: 1 };
+                place_item(&main.itemtype_data, &mut main.feral_ids, &mut main.feral_data, &mut main.feral_by_tile, uvec2(x, 0), slot).ok().unwrap();
+            }
+        }
+        check_golden(&main, "feral_stacking");
+    }
+}
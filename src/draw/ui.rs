@@ -0,0 +1,582 @@
+use crate::draw::*;
+use crate::lgrn;
+
+use glam::{vec2, Vec2, Mat2};
+use std::ops::Not;
+
+lgrn::id_type!(WidgetId);
+
+/// Sizes before Ui::scale
+pub const UI_PADDING:   f32 = 6.0;
+pub const UI_GAP:       f32 = 4.0;
+pub const UI_ICON:      f32 = 32.0;
+pub const UI_FONT_SIZE: f32 = 20.0;
+pub const UI_SCROLL:    f32 = 40.0;
+
+/// How children of a widget are arranged
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Layout {
+    Stack{ horizontal: bool },
+    /// Left to right, top to bottom, every cell as big as the biggest child
+    Grid{ columns: u32 }
+}
+
+#[derive(Clone, Debug)]
+pub enum WidgetKind {
+    /// Background and border around its children
+    Panel,
+    Label(String),
+    Button(String),
+    Icon,
+    /// Children stacked vertically and scrolled with the mouse wheel. height is before Ui::scale
+    List{ height: f32 }
+}
+
+/// Where a root widget goes on the screen
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Anchor {
+    TopLeft, TopRight, BottomLeft, BottomRight, Center,
+    At(Vec2)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UiEvent {
+    Clicked(WidgetId)
+}
+
+#[derive(Clone, Debug)]
+pub struct Widget {
+    pub kind:       WidgetKind,
+    pub layout:     Layout,
+    /// Drawn left of the text, or on its own for WidgetKind::Icon
+    pub icon:       Option<Sprite>,
+    /// Small text over the bottom right of the icon, like a stack count
+    pub badge:      Option<String>,
+    pub tooltip:    Option<String>,
+    pub enabled:    bool,
+    pub visible:    bool,
+    pub selected:   bool,
+    /// before Ui::scale
+    pub min_size:   Vec2,
+    pub children:   Vec<WidgetId>,
+    parent:         Option<WidgetId>,
+
+    // from Ui::layout
+    pub pos:        Vec2,
+    pub size:       Vec2,
+    pub scroll:     f32,
+    inline:         Vec2,
+    content_height: f32
+}
+
+impl Widget {
+    pub fn new(kind: WidgetKind) -> Widget {
+        Widget{
+            kind,
+            layout:         Layout::Stack{ horizontal: false },
+            icon:           None,
+            badge:          None,
+            tooltip:        None,
+            enabled:        true,
+            visible:        true,
+            selected:       false,
+            min_size:       Vec2::ZERO,
+            children:       Vec::new(),
+            parent:         None,
+            pos:            Vec2::ZERO,
+            size:           Vec2::ZERO,
+            scroll:         0.0,
+            inline:         Vec2::ZERO,
+            content_height: 0.0
+        }
+    }
+
+    pub fn panel(layout: Layout) -> Widget       { Widget{ layout, ..Widget::new(WidgetKind::Panel) } }
+    pub fn label(text: &str) -> Widget           { Widget::new(WidgetKind::Label(text.to_owned())) }
+    pub fn button(text: &str) -> Widget          { Widget::new(WidgetKind::Button(text.to_owned())) }
+    pub fn icon(sprite: Sprite) -> Widget        { Widget{ icon: Some(sprite), ..Widget::new(WidgetKind::Icon) } }
+    pub fn list(height: f32) -> Widget           { Widget::new(WidgetKind::List{ height }) }
+
+    pub fn with_icon(self, sprite: Sprite) -> Widget     { Widget{ icon: Some(sprite), ..self } }
+    pub fn with_badge(self, badge: String) -> Widget     { Widget{ badge: Some(badge), ..self } }
+    pub fn with_tooltip(self, tooltip: &str) -> Widget   { Widget{ tooltip: Some(tooltip.to_owned()), ..self } }
+    pub fn with_min_size(self, min_size: Vec2) -> Widget { Widget{ min_size, ..self } }
+    pub fn with_enabled(self, enabled: bool) -> Widget   { Widget{ enabled, ..self } }
+    pub fn with_selected(self, selected: bool) -> Widget { Widget{ selected, ..self } }
+
+    pub fn text(&self) -> Option<&str> {
+        match &self.kind {
+            WidgetKind::Label(text) | WidgetKind::Button(text) => Some(text.as_str()),
+            _ => None
+        }
+    }
+
+    pub fn contains(&self, pos: Vec2) -> bool {
+        pos.cmpge(self.pos).all() && pos.cmplt(self.pos + self.size).all()
+    }
+}
+
+/// Widgets that stay around between frames. Each frame: layout, then input, then draw.
+pub struct Ui {
+    pub ids:        lgrn::IdReg<WidgetId>,
+    pub widgets:    Vec<Widget>,
+    /// later ones are on top
+    pub roots:      Vec<(WidgetId, Anchor)>,
+    pub scale:      f32,
+    pub hovered:    Option<WidgetId>,
+    pub pressed:    Option<WidgetId>,
+    /// for the game to take out
+    pub events:     Vec<UiEvent>,
+    pub screen_size: Vec2
+}
+
+impl Default for Ui {
+    fn default() -> Self {
+        Ui{
+            ids:            Default::default(),
+            widgets:        Vec::new(),
+            roots:          Vec::new(),
+            scale:          1.0,
+            hovered:        None,
+            pressed:        None,
+            events:         Vec::new(),
+            screen_size:    Vec2::ZERO
+        }
+    }
+}
+
+impl std::ops::Index<WidgetId> for Ui {
+    type Output = Widget;
+    fn index(&self, id: WidgetId) -> &Widget { &self.widgets[id.0] }
+}
+
+impl std::ops::IndexMut<WidgetId> for Ui {
+    fn index_mut(&mut self, id: WidgetId) -> &mut Widget { &mut self.widgets[id.0] }
+}
+
+impl Ui {
+
+    fn create(&mut self, widget: Widget) -> WidgetId {
+        let id = match self.ids.create() {
+            Some(id) => id,
+            None => {
+                self.ids.resize((self.ids.capacity() * 2).max(64));
+                self.ids.create().unwrap()
+            }
+        };
+        if self.widgets.len() < self.ids.capacity() {
+            self.widgets.resize(self.ids.capacity(), Widget::new(WidgetKind::Panel));
+        }
+        self.widgets[id.0] = widget;
+        id
+    }
+
+    pub fn add_root(&mut self, anchor: Anchor, widget: Widget) -> WidgetId {
+        let id = self.create(widget);
+        self.roots.push((id, anchor));
+        id
+    }
+
+    pub fn add(&mut self, parent: WidgetId, widget: Widget) -> WidgetId {
+        let id = self.create(Widget{ parent: Some(parent), ..widget });
+        self.widgets[parent.0].children.push(id);
+        id
+    }
+
+    pub fn exists(&self, id: WidgetId) -> bool {
+        self.ids.exists(id)
+    }
+
+    /// Removes a widget and everything in it
+    pub fn remove(&mut self, id: WidgetId) {
+        if self.ids.exists(id).not() {
+            return;
+        }
+        match self.widgets[id.0].parent {
+            Some(parent) => self.widgets[parent.0].children.retain(|&c| c != id),
+            None         => self.roots.retain(|&(r, _)| r != id)
+        }
+        self.remove_recurse(id);
+    }
+
+    fn remove_recurse(&mut self, id: WidgetId) {
+        for child in std::mem::take(&mut self.widgets[id.0].children) {
+            self.remove_recurse(child);
+        }
+        if self.hovered == Some(id) { self.hovered = None; }
+        if self.pressed == Some(id) { self.pressed = None; }
+        self.ids.remove(id);
+    }
+
+    pub fn clear_children(&mut self, id: WidgetId) {
+        for child in std::mem::take(&mut self.widgets[id.0].children) {
+            self.remove_recurse(child);
+        }
+    }
+
+    pub fn font_size(&self) -> u16 {
+        (UI_FONT_SIZE * self.scale).round() as u16
+    }
+
+    /// measure gives the width of a single line of text at a font size
+    pub fn layout(&mut self, screen_size: Vec2, measure: &dyn Fn(&str, u16) -> f32) {
+        self.screen_size = screen_size;
+        let margin = UI_PADDING * self.scale;
+        for (id, anchor) in self.roots.clone() {
+            let size = self.measure(id, measure);
+            let pos = match anchor {
+                Anchor::TopLeft     => vec2(margin, margin),
+                Anchor::TopRight    => vec2(screen_size.x - size.x - margin, margin),
+                Anchor::BottomLeft  => vec2(margin, screen_size.y - size.y - margin),
+                Anchor::BottomRight => screen_size - size - margin,
+                Anchor::Center      => 0.5 * (screen_size - size),
+                Anchor::At(pos)     => pos
+            };
+            self.place(id, pos.floor());
+        }
+    }
+
+    fn text_size(&self, text: &str, measure: &dyn Fn(&str, u16) -> f32) -> Vec2 {
+        let font_size = self.font_size();
+        let width = text.lines().map(|line| measure(line, font_size)).fold(0.0, f32::max);
+        vec2(width, (text.lines().count() as f32) * font_size as f32)
+    }
+
+    fn measure(&mut self, id: WidgetId, measure: &dyn Fn(&str, u16) -> f32) -> Vec2 {
+        let s = self.scale;
+        let (pad, gap) = (UI_PADDING * s, UI_GAP * s);
+
+        let children: Vec<WidgetId> = self.widgets[id.0].children.iter().copied().filter(|c| self.widgets[c.0].visible).collect();
+        let sizes: Vec<Vec2> = children.iter().map(|&c| self.measure(c, measure)).collect();
+
+        let w = &self.widgets[id.0];
+
+        // icon and text side by side
+        let text = w.text().map(|t| self.text_size(t, measure)).unwrap_or(Vec2::ZERO);
+        let icon = if w.icon.is_some() { Vec2::splat(UI_ICON * s) } else { Vec2::ZERO };
+        let inline = vec2(icon.x + text.x + if icon.x > 0.0 && text.x > 0.0 { gap } else { 0.0 }, icon.y.max(text.y));
+
+        let n = sizes.len() as f32;
+        let content = if sizes.is_empty() {
+            Vec2::ZERO
+        } else {
+            match w.layout {
+                Layout::Stack{ horizontal: true } => vec2(sizes.iter().map(|s| s.x).sum::<f32>() + gap * (n - 1.0), sizes.iter().map(|s| s.y).fold(0.0, f32::max)),
+                Layout::Stack{ horizontal: false } => vec2(sizes.iter().map(|s| s.x).fold(0.0, f32::max), sizes.iter().map(|s| s.y).sum::<f32>() + gap * (n - 1.0)),
+                Layout::Grid{ columns } => {
+                    let cell = sizes.iter().fold(Vec2::ZERO, |a, &b| a.max(b));
+                    let cols = (columns.max(1) as f32).min(n);
+                    let rows = (n / cols).ceil();
+                    cell * vec2(cols, rows) + gap * vec2(cols - 1.0, rows - 1.0)
+                }
+            }
+        };
+
+        let both = inline.y > 0.0 && content.y > 0.0;
+        let inner = vec2(inline.x.max(content.x), inline.y + content.y + if both { gap } else { 0.0 });
+        let padded = match w.kind {
+            WidgetKind::Label(_) | WidgetKind::Icon => inner,
+            _ => inner + 2.0 * pad
+        };
+        let mut size = padded.max(w.min_size * s);
+        if let WidgetKind::List{ height } = w.kind {
+            size.y = size.y.min(height * s);
+        }
+
+        let w = &mut self.widgets[id.0];
+        w.inline = inline;
+        w.content_height = padded.y;
+        w.size = size;
+        if let WidgetKind::List{ .. } = w.kind {
+            w.scroll = w.scroll.clamp(0.0, (w.content_height - w.size.y).max(0.0));
+        }
+        size
+    }
+
+    fn place(&mut self, id: WidgetId, pos: Vec2) {
+        let s = self.scale;
+        let (pad, gap) = (UI_PADDING * s, UI_GAP * s);
+
+        let children: Vec<WidgetId> = self.widgets[id.0].children.iter().copied().filter(|c| self.widgets[c.0].visible).collect();
+        let w = &mut self.widgets[id.0];
+        w.pos = pos;
+
+        let pad = match w.kind {
+            WidgetKind::Label(_) | WidgetKind::Icon => 0.0,
+            _ => pad
+        };
+        let mut start = pos + pad;
+        if w.inline.y > 0.0 {
+            start.y += w.inline.y + gap;
+        }
+        start.y -= w.scroll;
+
+        let layout = w.layout;
+
+        let cell = children.iter().fold(Vec2::ZERO, |a, c| a.max(self.widgets[c.0].size));
+        let mut cursor = start;
+        for (i, &child) in children.iter().enumerate() {
+            let size = self.widgets[child.0].size;
+            match layout {
+                Layout::Stack{ horizontal } => {
+                    self.place(child, cursor);
+                    if horizontal { cursor.x += size.x + gap; } else { cursor.y += size.y + gap; }
+                },
+                Layout::Grid{ columns } => {
+                    let columns = columns.max(1) as usize;
+                    let at = vec2((i % columns) as f32, (i / columns) as f32);
+                    self.place(child, start + at * (cell + gap));
+                }
+            }
+        }
+    }
+
+    /// Lists cut off whatever's scrolled outside of them
+    fn clipped(&self, id: WidgetId) -> bool {
+        let w = &self.widgets[id.0];
+        let mut parent = w.parent;
+        while let Some(p) = parent {
+            let pw = &self.widgets[p.0];
+            if let WidgetKind::List{ .. } = pw.kind {
+                if w.pos.y < pw.pos.y || w.pos.y + w.size.y > pw.pos.y + pw.size.y {
+                    return true;
+                }
+            }
+            parent = pw.parent;
+        }
+        false
+    }
+
+    /// Topmost widget under pos
+    pub fn hit(&self, pos: Vec2) -> Option<WidgetId> {
+        self.roots.iter().rev().find_map(|&(id, _)| self.hit_recurse(id, pos))
+    }
+
+    fn hit_recurse(&self, id: WidgetId, pos: Vec2) -> Option<WidgetId> {
+        let w = &self.widgets[id.0];
+        if w.visible.not() || w.contains(pos).not() || self.clipped(id) {
+            return None;
+        }
+        w.children.iter().rev().find_map(|&c| self.hit_recurse(c, pos)).or(Some(id))
+    }
+
+    /// Returns true if the mouse is over the UI, so the world shouldn't get the click
+    pub fn input(&mut self, mouse: Vec2, pressed: bool, released: bool, wheel: f32) -> bool {
+        self.hovered = self.hit(mouse);
+
+        if wheel != 0.0 {
+            let mut at = self.hovered;
+            while let Some(id) = at {
+                let w = &mut self.widgets[id.0];
+                if let WidgetKind::List{ .. } = w.kind {
+                    w.scroll = (w.scroll - wheel.signum() * UI_SCROLL * self.scale).clamp(0.0, (w.content_height - w.size.y).max(0.0));
+                    break;
+                }
+                at = w.parent;
+            }
+        }
+
+        let clickable = |id: WidgetId| matches!(self.widgets[id.0].kind, WidgetKind::Button(_)) && self.widgets[id.0].enabled;
+        if pressed {
+            self.pressed = self.hovered.filter(|&id| clickable(id));
+        }
+        if released {
+            if let Some(id) = self.pressed.take() {
+                if self.hovered == Some(id) {
+                    self.events.push(UiEvent::Clicked(id));
+                }
+            }
+        }
+
+        self.hovered.is_some()
+    }
+
+    pub fn draw(&self, pages: &[mq::Texture2D], font: Option<&mq::Font>) {
+        for &(id, _) in &self.roots {
+            self.draw_recurse(id, pages, font);
+        }
+
+        // tooltip of whatever's under the mouse, or the closest thing it's in that has one
+        let mut at = self.hovered;
+        while let Some(id) = at {
+            let w = &self.widgets[id.0];
+            if let Some(tooltip) = &w.tooltip {
+                draw_tooltip(tooltip, w.pos + vec2(0.5 * w.size.x, w.size.y), true, font, self.screen_size, self.scale);
+                break;
+            }
+            at = w.parent;
+        }
+    }
+
+    fn draw_recurse(&self, id: WidgetId, pages: &[mq::Texture2D], font: Option<&mq::Font>) {
+        let w = &self.widgets[id.0];
+        if w.visible.not() || self.clipped(id) {
+            return;
+        }
+        let s = self.scale;
+        let pad = UI_PADDING * s;
+        let (pos, size) = (w.pos, w.size);
+
+        let text_color = if w.enabled { mq::WHITE } else { mq::GRAY };
+        match w.kind {
+            WidgetKind::Panel | WidgetKind::List{ .. } => {
+                mq::draw_rectangle(pos.x, pos.y, size.x, size.y, mq::Color::new(0.0, 0.0, 0.0, 0.75));
+                mq::draw_rectangle_lines(pos.x, pos.y, size.x, size.y, 2.0, mq::WHITE);
+            },
+            WidgetKind::Button(_) => {
+                let shade = match (self.pressed == Some(id), self.hovered == Some(id) && w.enabled) {
+                    (true, _)       => 0.1,
+                    (false, true)   => 0.35,
+                    (false, false)  => 0.2
+                };
+                mq::draw_rectangle(pos.x, pos.y, size.x, size.y, mq::Color::new(shade, shade, shade, 0.9));
+                let border = if w.selected { mq::YELLOW } else { text_color };
+                mq::draw_rectangle_lines(pos.x, pos.y, size.x, size.y, 2.0, border);
+            },
+            _ => {}
+        }
+
+        let inline_pos = match w.kind {
+            WidgetKind::Label(_) | WidgetKind::Icon => pos,
+            _ => pos + pad
+        } - vec2(0.0, if matches!(w.kind, WidgetKind::List{ .. }) { w.scroll } else { 0.0 });
+
+        let mut x = inline_pos.x;
+        if let Some(sprite) = w.icon {
+            let icon = UI_ICON * s;
+            let center = vec2(x, inline_pos.y) + 0.5 * icon;
+            draw_texture_gwah(&pages[sprite.page], center, Mat2::from_diagonal(Vec2::splat(icon)), sprite.coord, mq::WHITE);
+            if let Some(badge) = &w.badge {
+                let font_size = (UI_FONT_SIZE * 0.75 * s) as u16;
+                mq::draw_text_ex(badge, x + 0.5 * icon, inline_pos.y + icon, mq::TextParams{ font, font_size, color: mq::WHITE, ..Default::default() });
+            }
+            x += icon + UI_GAP * s;
+        }
+        if let Some(text) = w.text() {
+            let font_size = self.font_size();
+            let top = inline_pos.y + 0.5 * (w.inline.y - text.lines().count() as f32 * font_size as f32);
+            for (i, line) in text.lines().enumerate() {
+                let baseline = top + (i as f32 + 0.8) * font_size as f32;
+                mq::draw_text_ex(line, x, baseline, mq::TextParams{ font, font_size, color: text_color, ..Default::default() });
+            }
+        }
+
+        for &child in &w.children {
+            self.draw_recurse(child, pages, font);
+        }
+    }
+}
+
+/// Top left of a tooltip sized size that hangs below anchor (centered on it if center). Flips
+/// above anchor if there's no room below, and stays inside the screen horizontally.
+pub fn anchor_tooltip(anchor: Vec2, size: Vec2, center: bool, gap: f32, screen_size: Vec2) -> Vec2 {
+    let x = if center { anchor.x - 0.5 * size.x } else { anchor.x };
+    let x = x.min(screen_size.x - size.x).max(0.0);
+
+    let below = anchor.y + gap;
+    let above = anchor.y - gap - size.y;
+    let y = if below + size.y <= screen_size.y || above < 0.0 { below.min(screen_size.y - size.y).max(0.0) } else { above };
+
+    vec2(x, y)
+}
+
+/// Box of text under anchor. Lines are split by \n
+pub fn draw_tooltip(text: &str, anchor: Vec2, center: bool, font: Option<&mq::Font>, screen_size: Vec2, scale: f32) {
+    let text = text.trim_end_matches('\n');
+    let font_size = (UI_FONT_SIZE * scale) as u16;
+    let text_width = text.lines().map(|line| mq::measure_text(line, font, font_size, 1.0).width).fold(0.0, f32::max);
+    let lines = text.lines().count();
+
+    let size = vec2(text_width + 9.0 * scale, 10.0 * scale + (lines as u32 * font_size as u32) as f32);
+    let pos = anchor_tooltip(anchor, size, center, 4.0 * scale, screen_size);
+
+    mq::draw_rectangle(pos.x, pos.y, size.x, size.y, mq::Color::new(0.0, 0.0, 0.0, 0.75));
+    mq::draw_rectangle_lines(pos.x, pos.y, size.x, size.y, 2.0, mq::WHITE);
+
+    let mut yoffset = font_size as f32;
+    for line in text.lines() {
+        mq::draw_text_ex(line, pos.x + 4.0 * scale, pos.y + 2.0 * scale + yoffset, mq::TextParams {
+            font,
+            font_size,
+            color: mq::WHITE,
+            ..Default::default()
+        });
+        yoffset += font_size as f32;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // every character is 10 wide
+    fn measure(text: &str, _: u16) -> f32 {
+        text.chars().count() as f32 * 10.0
+    }
+
+    #[test]
+    fn test_ui_layout() {
+        let mut ui = Ui::default();
+        let panel = ui.add_root(Anchor::TopRight, Widget::panel(Layout::Stack{ horizontal: false }));
+        let title = ui.add(panel, Widget::label("Items"));
+        let grid = ui.add(panel, Widget::panel(Layout::Grid{ columns: 2 }));
+        let cells: Vec<WidgetId> = (0..3).map(|i| ui.add(grid, Widget::button(&"x".repeat(i + 1)))).collect();
+
+        ui.layout(vec2(800.0, 600.0), &measure);
+
+        assert_eq!(ui[title].size, vec2(50.0, 20.0));
+        // buttons are padded, cells are as big as the largest one
+        assert_eq!(ui[cells[2]].size, vec2(42.0, 32.0));
+        assert_eq!(ui[cells[1]].pos - ui[cells[0]].pos, vec2(42.0 + UI_GAP, 0.0));
+        assert_eq!(ui[cells[2]].pos - ui[cells[0]].pos, vec2(0.0, 32.0 + UI_GAP));
+        assert_eq!(ui[grid].size, vec2(2.0 * 42.0 + UI_GAP + 2.0 * UI_PADDING, 2.0 * 32.0 + UI_GAP + 2.0 * UI_PADDING));
+        // stuck to the right edge
+        assert_eq!(ui[panel].pos.x + ui[panel].size.x, 800.0 - UI_PADDING);
+
+        // clicks need a press and release on the same button
+        let center = |ui: &Ui, id: WidgetId| ui[id].pos + 0.5 * ui[id].size;
+        assert!(ui.input(center(&ui, cells[0]), true, false, 0.0));
+        ui.input(center(&ui, cells[1]), false, true, 0.0);
+        assert!(ui.events.is_empty());
+        ui.input(center(&ui, cells[1]), true, false, 0.0);
+        ui.input(center(&ui, cells[1]), false, true, 0.0);
+        assert_eq!(ui.events, [UiEvent::Clicked(cells[1])]);
+        assert!(ui.input(vec2(0.0, 0.0), false, false, 0.0).not());
+
+        ui.remove(grid);
+        assert!(ui.exists(cells[0]).not());
+        assert_eq!(ui[panel].children, [title]);
+    }
+
+    #[test]
+    fn test_ui_list_scroll() {
+        let mut ui = Ui::default();
+        let list = ui.add_root(Anchor::TopLeft, Widget::list(100.0));
+        let rows: Vec<WidgetId> = (0..10).map(|_| ui.add(list, Widget::button("row"))).collect();
+        ui.layout(vec2(800.0, 600.0), &measure);
+        assert_eq!(ui[list].size.y, 100.0);
+
+        // scroll down until the last row shows up
+        let inside = ui[list].pos + vec2(10.0, 50.0);
+        for _ in 0..20 {
+            ui.input(inside, false, false, -1.0);
+            ui.layout(vec2(800.0, 600.0), &measure);
+        }
+        assert!(ui.clipped(rows[0]));
+        assert!(ui.clipped(rows[9]).not());
+        assert!(ui.hit(ui[rows[0]].pos + 1.0) != Some(rows[0]));
+    }
+
+    #[test]
+    fn test_tooltip_edges() {
+        let screen = vec2(800.0, 600.0);
+        let size = vec2(200.0, 100.0);
+        assert_eq!(anchor_tooltip(vec2(400.0, 100.0), size, true, 4.0, screen), vec2(300.0, 104.0));
+        // pushed in from the sides
+        assert_eq!(anchor_tooltip(vec2(10.0, 100.0), size, true, 4.0, screen), vec2(0.0, 104.0));
+        assert_eq!(anchor_tooltip(vec2(790.0, 100.0), size, false, 4.0, screen), vec2(600.0, 104.0));
+        // flipped above when there's no room below
+        assert_eq!(anchor_tooltip(vec2(400.0, 550.0), size, true, 4.0, screen), vec2(300.0, 446.0));
+    }
+}
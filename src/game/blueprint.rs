@@ -0,0 +1,366 @@
+use crate::game::*;
+
+use std::fmt;
+use std::fmt::Write;
+use std::ops::Not;
+use glam::{uvec2, UVec2};
+
+/// Widest and tallest a blueprint can be, so offsets added to a tile stay small
+pub const BLUEPRINT_SIZE_MAX: u32 = 256;
+
+/// One machine in a blueprint
+#[derive(Clone)]
+pub struct BlueprintPart {
+    /// tiles from the blueprint's top left
+    pub offset:     UVec2,
+    pub dir:        Dir,
+    /// see plan_spec
+    pub spec:       MachineSpec
+}
+
+/// A layout of machines copied from the world, to be pasted as ghosts. See Construct::Paste.
+#[derive(Clone, Default)]
+pub struct Blueprint {
+    pub size:       UVec2,
+    pub parts:      Vec<BlueprintPart>
+}
+
+#[derive(Debug)]
+pub enum BlueprintError {
+    Io(String, std::io::Error),
+    Parse{ line: usize, msg: String }
+}
+
+impl fmt::Display for BlueprintError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlueprintError::Io(path, err)       => write!(f, "can't read {}: {}", path, err),
+            BlueprintError::Parse{ line, msg }  => write!(f, "line {}: {}", line, msg)
+        }
+    }
+}
+
+impl std::error::Error for BlueprintError {}
+
+/// How a machine is set up, without what it's doing right now like ammo, timers, and items
+pub fn plan_spec(spec: &MachineSpec) -> MachineSpec {
+    match spec {
+        MachineSpec::None => MachineSpec::None,
+        MachineSpec::Turret{ target, .. } => MachineSpec::Turret{ ammo: 0, can_fire_time_us: 0, target: *target },
+        MachineSpec::Conveyor{ item, filter, .. } => MachineSpec::Conveyor{
+            item:               ItemSlot{ itemtype: if *filter { item.itemtype } else { Default::default() }, count: 0 },
+            filter:             *filter,
+            can_move_time_us:   0,
+            can_dump_time_us:   0
+        }
+    }
+}
+
+/// Recipe number for craft_machine_recipe and machine_cost
+pub fn machine_recipe(spec: &MachineSpec) -> u32 {
+    match spec {
+        MachineSpec::Turret{..}                     => 5,
+        MachineSpec::Conveyor{ filter: false, .. }  => 6,
+        _                                           => 7
+    }
+}
+
+/// Name in blueprint files
+pub fn machine_name(spec: &MachineSpec) -> &'static str {
+    match spec {
+        MachineSpec::None                           => "nothing",
+        MachineSpec::Turret{..}                     => "turret",
+        MachineSpec::Conveyor{ filter: false, .. }  => "conveyor",
+        MachineSpec::Conveyor{ filter: true, .. }   => "filterveyor"
+    }
+}
+
+/// Adds up slots of the same item type
+pub fn total_items(slots: impl IntoIterator<Item = ItemSlot>) -> Vec<ItemSlot> {
+    let mut total: Vec<ItemSlot> = Vec::new();
+    for slot in slots {
+        match total.iter_mut().find(|t| t.itemtype == slot.itemtype) {
+            Some(t) => t.count += slot.count,
+            None    => total.push(slot)
+        }
+    }
+    total
+}
+
+/// Everything in cost that have doesn't have enough of
+pub fn missing_items(cost: &[ItemSlot], have: &[ItemSlot]) -> Vec<ItemSlot> {
+    cost.iter().filter_map(|c| {
+        let got = have.iter().find(|h| h.itemtype == c.itemtype).map_or(0, |h| h.count);
+        (got < c.count).then(|| ItemSlot{ itemtype: c.itemtype, count: c.count - got })
+    }).collect()
+}
+
+/// Every item lying around, which is what ghosts get built out of
+pub fn items_on_ground(main: &GameMain) -> Vec<ItemSlot> {
+    total_items(main.feral_ids.iter_ids().flat_map(|f| main.feral_data[f.0].slots.iter().flatten().cloned()))
+}
+
+/// "2 Scrap, 1 Battery"
+pub fn items_text(main: &GameMain, slots: &[ItemSlot]) -> String {
+    let mut out = String::new();
+    for (i, slot) in slots.iter().enumerate() {
+        let sep = if i == 0 { "" } else { ", " };
+        write!(out, "{}{} {}", sep, slot.count, main.itemtype_data[slot.itemtype.0].name).unwrap();
+    }
+    out
+}
+
+fn dir_name(dir: Dir) -> &'static str {
+    match dir {
+        Dir::Right  => "right",
+        Dir::Down   => "down",
+        Dir::Left   => "left",
+        Dir::Up     => "up"
+    }
+}
+
+fn dir_from_name(name: &str) -> Option<Dir> {
+    [Dir::Right, Dir::Down, Dir::Left, Dir::Up].into_iter().find(|&d| dir_name(d) == name)
+}
+
+impl Blueprint {
+
+    /// Machines placed between two corners, in any order. Cut down to BLUEPRINT_SIZE_MAX from the
+    /// top left.
+    pub fn capture(main: &GameMain, a: UVec2, b: UVec2) -> Blueprint {
+        let (tl, br) = (a.min(b), a.max(b).min(a.min(b) + BLUEPRINT_SIZE_MAX - 1));
+        let mut blueprint = Blueprint{ size: br - tl + 1, parts: Vec::new() };
+        for y in tl.y..=br.y {
+            for x in tl.x..=br.x {
+                if let Some(mach) = main.mach_by_tile.get(tile_pos(x, y)) {
+                    let d = &main.mach_data[mach.0];
+                    blueprint.parts.push(BlueprintPart{ offset: uvec2(x, y) - tl, dir: d.dir, spec: plan_spec(&d.spec) });
+                }
+            }
+        }
+        blueprint
+    }
+
+    /// Turned a quarter clockwise, machines and all
+    pub fn rotated(&self) -> Blueprint {
+        Blueprint{
+            size:   uvec2(self.size.y, self.size.x),
+            parts:  self.parts.iter().map(|p| {
+                let mut turned = Machine{ dir: p.dir, ..Default::default() };
+                turned.configure(MachineSetting::Rotate{ clockwise: true });
+                BlueprintPart{ offset: uvec2(self.size.y - 1 - p.offset.y, p.offset.x), dir: turned.dir, spec: p.spec.clone() }
+            }).collect()
+        }
+    }
+
+    /// Items it takes to build every machine
+    pub fn cost(&self) -> Vec<ItemSlot> {
+        total_items(self.parts.iter().flat_map(|p| machine_cost(machine_recipe(&p.spec))))
+    }
+
+    /// Lines of "<machine> <x> <y> <dir> <setting>", after the size. Filterveyor items are
+    /// written by item_key.
+    pub fn to_text(&self, itemtypes: &[ItemType]) -> String {
+        let mut out = String::from("# Blueprint. Offsets are tiles from the top left.\n\n");
+        writeln!(out, "size {} {}", self.size.x, self.size.y).unwrap();
+        for part in &self.parts {
+            let setting = match &part.spec {
+                MachineSpec::Turret{ target: TurretTarget::Ahead, .. }      => "ahead".to_owned(),
+                MachineSpec::Turret{ target: TurretTarget::Nearest, .. }    => "nearest".to_owned(),
+                MachineSpec::Conveyor{ filter: false, .. }                  => String::new(),
+                MachineSpec::Conveyor{ item, .. } if item.itemtype == Default::default() => "any".to_owned(),
+                MachineSpec::Conveyor{ item, .. }                           => itemtypes.get(item.itemtype.0).map_or("any".to_owned(), item_key),
+                MachineSpec::None                                           => continue
+            };
+            writeln!(out, "{:<12}{} {} {:<6}{}", machine_name(&part.spec), part.offset.x, part.offset.y, dir_name(part.dir), setting).unwrap();
+        }
+        out
+    }
+
+    /// Filterveyor items have to be one of itemtypes
+    pub fn parse(text: &str, itemtypes: &[ItemType]) -> Result<Blueprint, BlueprintError> {
+        let mut blueprint = Blueprint::default();
+
+        for (i, line) in text.lines().enumerate() {
+            let line_no = i + 1;
+            let err = |msg: String| BlueprintError::Parse{ line: line_no, msg };
+
+            let line = line.split('#').next().unwrap().trim();
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let Some((&name, rest)) = words.split_first() else { continue };
+
+            if name == "size" {
+                // parts are only checked against the size they came after
+                if blueprint.size != UVec2::ZERO {
+                    return Err(err("size given twice".to_owned()));
+                }
+                let size = match rest {
+                    [w, h] => w.parse::<u32>().ok().zip(h.parse::<u32>().ok()),
+                    _ => None
+                };
+                blueprint.size = size.map(|(w, h)| uvec2(w, h)).filter(|s| s.cmpgt(UVec2::ZERO).all())
+                    .ok_or_else(|| err("expected: size <width> <height>".to_owned()))?;
+                if blueprint.size.cmpgt(UVec2::splat(BLUEPRINT_SIZE_MAX)).any() {
+                    return Err(err(format!("can't be bigger than {} by {}", BLUEPRINT_SIZE_MAX, BLUEPRINT_SIZE_MAX)));
+                }
+                continue;
+            }
+
+            let (x, y, dir, setting) = match rest {
+                [x, y, dir] => (x, y, dir, None),
+                [x, y, dir, setting] => (x, y, dir, Some(*setting)),
+                _ => return Err(err(format!("expected: {} <x> <y> <dir> [setting]", name)))
+            };
+            let offset = x.parse::<u32>().ok().zip(y.parse::<u32>().ok()).map(|(x, y)| uvec2(x, y))
+                .filter(|o| o.cmplt(blueprint.size).all())
+                .ok_or_else(|| err(format!("'{} {}' isn't a tile inside the size", x, y)))?;
+            let dir = dir_from_name(dir).ok_or_else(|| err(format!("'{}' isn't right, down, left, or up", dir)))?;
+
+            let spec = match (name, setting) {
+                ("turret", Some("ahead") | None)    => MachineSpec::Turret{ ammo: 0, can_fire_time_us: 0, target: TurretTarget::Ahead },
+                ("turret", Some("nearest"))         => MachineSpec::Turret{ ammo: 0, can_fire_time_us: 0, target: TurretTarget::Nearest },
+                ("conveyor", None)                  => MachineSpec::Conveyor{ item: Default::default(), filter: false, can_move_time_us: 0, can_dump_time_us: 0 },
+                ("filterveyor", Some("any") | None) => MachineSpec::Conveyor{ item: Default::default(), filter: true, can_move_time_us: 0, can_dump_time_us: 0 },
+                ("filterveyor", Some(item)) => {
+                    let itemtype = itemtypes.iter().position(|it| item_key(it) == item).ok_or_else(|| err(format!("'{}' isn't an item or any", item)))?;
+                    MachineSpec::Conveyor{ item: ItemSlot{ itemtype: ItemTypeId(itemtype), count: 0 }, filter: true, can_move_time_us: 0, can_dump_time_us: 0 }
+                },
+                ("turret" | "conveyor", Some(setting)) => return Err(err(format!("'{}' isn't a setting for a {}", setting, name))),
+                _ => return Err(err(format!("'{}' isn't a machine, expected turret, conveyor, or filterveyor", name)))
+            };
+            if blueprint.parts.iter().any(|p| p.offset == offset) {
+                return Err(err(format!("two machines at {} {}", offset.x, offset.y)));
+            }
+            blueprint.parts.push(BlueprintPart{ offset, dir, spec });
+        }
+        if blueprint.size == UVec2::ZERO {
+            return Err(BlueprintError::Parse{ line: text.lines().count(), msg: "no size".to_owned() });
+        }
+        Ok(blueprint)
+    }
+
+    pub fn load(path: &str, itemtypes: &[ItemType]) -> Result<Blueprint, BlueprintError> {
+        let text = std::fs::read_to_string(path).map_err(|e| BlueprintError::Io(path.to_owned(), e))?;
+        Blueprint::parse(&text, itemtypes)
+    }
+
+    pub fn save(&self, path: &str, itemtypes: &[ItemType]) -> std::io::Result<()> {
+        std::fs::write(path, self.to_text(itemtypes))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parts.is_empty()
+    }
+}
+
+/// Builds every ghost there are items on the ground for, taking them from whichever piles have
+/// them. Returns how many got built.
+pub fn build_ghosts(main: &mut GameMain) -> usize {
+    let mut built = 0;
+    let ghosts: Vec<(UVec2, Machine)> = main.ghosts.iter().map(|(pos, m)| (pos.as_uvec2(), m.clone())).collect();
+
+    for (pos, ghost) in ghosts {
+        let placeable = tile_contents(main.world_size, &main.rail_by_tile, &main.feral_by_tile, &main.mach_by_tile, pos)
+            .is_some_and(|c| c.can_place(TileKind::Machine));
+        let cost = machine_cost(machine_recipe(&ghost.spec));
+        if placeable.not() || missing_items(&cost, &items_on_ground(main)).is_empty().not() {
+            continue;
+        }
+
+        for slot in cost {
+            let mut remaining = slot.count;
+            let ferals: Vec<FeralItemId> = main.feral_ids.iter_ids().collect();
+            for feral in ferals {
+                remaining -= take_items(&mut main.feral_data[feral.0].slots, slot.itemtype, remaining);
+                feral_remove_if_empty(&mut main.feral_ids, &mut main.feral_data, &mut main.feral_by_tile, feral);
+                if remaining == 0 {
+                    break;
+                }
+            }
+        }
+
+        let mach = main.mach_ids.create_resize();
+        main.mach_data.resize(main.mach_ids.capacity(), Default::default());
+        let mut spec = ghost.spec.clone();
+        match &mut spec {
+            MachineSpec::Turret{ can_fire_time_us, .. } => *can_fire_time_us = main.time_us,
+            MachineSpec::Conveyor{ can_move_time_us, can_dump_time_us, .. } => {
+                *can_move_time_us = main.time_us;
+                *can_dump_time_us = main.time_us;
+            },
+            MachineSpec::None => {}
+        }
+        main.mach_data[mach.0] = Machine{ pos: Some(pos), dir: ghost.dir, enabled: true, spec };
+        main.mach_by_tile.insert(pos.into(), mach);
+        main.ghosts.remove(pos.into());
+        built += 1;
+    }
+    built
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blueprint() {
+        let mut main = GameMain{ world_size: uvec2(8, 8), ..Default::default() };
+        main.mach_ids.resize(64);
+        let items: &[ItemType] = &["Destroyed Logistics Drone", "Scrap Metal", "Battery", "Red Alignite crystal", "Gunpowder", "Bullet"]
+            .map(|name| ItemType{ name, stackable: 69, ..Default::default() });
+        for (pos, dir, spec) in [
+            (uvec2(2, 1), Dir::Right, MachineSpec::Turret{ ammo: 40, can_fire_time_us: 99, target: TurretTarget::Nearest }),
+            (uvec2(3, 1), Dir::Down, MachineSpec::Conveyor{ item: ItemSlot{ itemtype: ITEM_SCRAP, count: 1 }, filter: false, can_move_time_us: 5, can_dump_time_us: 5 }),
+            (uvec2(3, 2), Dir::Left, MachineSpec::Conveyor{ item: ItemSlot{ itemtype: ITEM_BULLET, count: 1 }, filter: true, can_move_time_us: 5, can_dump_time_us: 5 })
+        ] {
+            let mach = main.mach_ids.create().unwrap();
+            main.mach_data.resize(64, Default::default());
+            main.mach_data[mach.0] = Machine{ pos: Some(pos), dir, enabled: true, spec };
+            main.mach_by_tile.insert(pos.into(), mach);
+        }
+
+        // corners can be either way around, and what the machines are doing is left out
+        let blueprint = Blueprint::capture(&main, uvec2(4, 2), uvec2(2, 1));
+        assert_eq!(blueprint.size, uvec2(3, 2));
+        assert_eq!(Blueprint::capture(&main, uvec2(0, 0), uvec2(999, 1)).size, uvec2(BLUEPRINT_SIZE_MAX, 2));
+        assert_eq!(blueprint.parts.len(), 3);
+        assert!(matches!(blueprint.parts[0].spec, MachineSpec::Turret{ ammo: 0, target: TurretTarget::Nearest, .. }));
+        assert!(matches!(&blueprint.parts[1].spec, MachineSpec::Conveyor{ item, .. } if item.count == 0 && item.itemtype == Default::default()));
+        assert!(matches!(&blueprint.parts[2].spec, MachineSpec::Conveyor{ item, .. } if item.itemtype == ITEM_BULLET));
+
+        let cost = blueprint.cost();
+        assert_eq!(cost.iter().map(|s| (s.itemtype, s.count)).collect::<Vec<_>>(), [(ITEM_SCRAP, 10), (ITEM_BATTERY, 8), (ITEM_ALIGNITE, 1)]);
+        let have = [ItemSlot{ itemtype: ITEM_SCRAP, count: 20 }, ItemSlot{ itemtype: ITEM_BATTERY, count: 3 }];
+        assert_eq!(missing_items(&cost, &have).iter().map(|s| (s.itemtype, s.count)).collect::<Vec<_>>(), [(ITEM_BATTERY, 5), (ITEM_ALIGNITE, 1)]);
+
+        // four turns go all the way around
+        let turned = blueprint.rotated();
+        assert_eq!(turned.size, uvec2(2, 3));
+        assert_eq!(turned.parts[0].offset, uvec2(1, 0));
+        assert!(matches!(turned.parts[0].dir, Dir::Down));
+        let around = turned.rotated().rotated().rotated();
+        assert_eq!(around.to_text(items), blueprint.to_text(items));
+
+        // round trips through text, with items by name
+        let text = blueprint.to_text(items);
+        assert!(text.contains("left  bullet\n"));
+        assert_eq!(Blueprint::parse(&text, items).unwrap().to_text(items), text);
+
+        let line_of = |text| match Blueprint::parse(text, items) {
+            Err(BlueprintError::Parse{ line, .. }) => line,
+            _ => 0
+        };
+        assert_eq!(line_of("size 2 2\nturret 2 0 up"), 2);
+        assert_eq!(line_of("size 2 2\n\nturret 0 0 sideways"), 3);
+        assert_eq!(line_of("size 2 2\nconveyor 0 0 up fast"), 2);
+        assert_eq!(line_of("size 2 2\nfilterveyor 0 0 up 99"), 2);
+        assert_eq!(line_of("size 2 2\nfilterveyor 0 0 up scrap"), 2);
+        assert_eq!(line_of("size 2 2\nturret 0 0 up\nconveyor 0 0 up"), 3);
+        assert_eq!(line_of("size 0 2"), 1);
+        assert_eq!(line_of("size 4 4\nturret 3 3 up\nsize 1 1"), 3);
+        assert_eq!(line_of("size 4294967295 1\nturret 4294967294 0 up"), 1);
+        assert_eq!(line_of("size 256 256\nturret 255 255 up"), 0);
+        assert_eq!(line_of("turret 0 0 up"), 1);
+    }
+}
@@ -0,0 +1,433 @@
+use crate::game::*;
+
+use std::fmt;
+use std::fmt::Write;
+use std::ops::Not;
+use std::str::FromStr;
+use glam::{uvec2, UVec2};
+
+/// Lines of output the console keeps
+pub const CONSOLE_LOG_MAX: usize = 200;
+/// Commands kept for going back through with up and down
+pub const CONSOLE_HISTORY_MAX: usize = 100;
+/// Most drones spawn_drone makes at once
+pub const SPAWN_DRONES_MAX: u32 = 1000;
+
+/// Every command with how to use it, for help and completion
+pub const COMMANDS: [(&str, &str); 8] = [
+    ("spawn_item",  "spawn_item <item> <count> <x> <y>"),
+    ("spawn_drone", "spawn_drone [count]"),
+    ("set",         "set <drone_per_second|player_gun_ammo|sandbox|drones> <value>"),
+    ("obfuscate",   "obfuscate <x> <y>"),
+    ("tp",          "tp <x> <y>"),
+    ("give",        "give ammo"),
+    ("dump",        "dump <machines|drones|items>"),
+    ("help",        "help [command]")
+];
+
+const SET_VARS:     [&str; 4] = ["drone_per_second", "player_gun_ammo", "sandbox", "drones"];
+const DUMP_WHAT:    [&str; 3] = ["machines", "drones", "items"];
+const GIVE_WHAT:    [&str; 1] = ["ammo"];
+const ON_OFF:       [&str; 2] = ["on", "off"];
+
+#[derive(Debug, PartialEq)]
+pub enum ConsoleError {
+    UnknownCommand(String),
+    /// wrong number of arguments, with how to use the command
+    Usage(&'static str),
+    BadArg{ arg: String, msg: String },
+    /// from run_script
+    AtLine{ line: usize, err: Box<ConsoleError> }
+}
+
+impl fmt::Display for ConsoleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConsoleError::UnknownCommand(cmd)   => write!(f, "unknown command '{}', try help", cmd),
+            ConsoleError::Usage(usage)          => write!(f, "usage: {}", usage),
+            ConsoleError::BadArg{ arg, msg }    => write!(f, "'{}': {}", arg, msg),
+            ConsoleError::AtLine{ line, err }   => write!(f, "line {}: {}", line, err)
+        }
+    }
+}
+
+impl std::error::Error for ConsoleError {}
+
+fn bad_arg(arg: &str, msg: &str) -> ConsoleError {
+    ConsoleError::BadArg{ arg: arg.to_owned(), msg: msg.to_owned() }
+}
+
+fn number<T: FromStr>(word: &str) -> Result<T, ConsoleError> {
+    word.parse().map_err(|_| bad_arg(word, "expected a number"))
+}
+
+fn on_off(word: &str) -> Result<bool, ConsoleError> {
+    match word {
+        "on" | "true" | "1"     => Ok(true),
+        "off" | "false" | "0"   => Ok(false),
+        _                       => Err(bad_arg(word, "expected on or off"))
+    }
+}
+
+fn tile(main: &GameMain, x: &str, y: &str) -> Result<UVec2, ConsoleError> {
+    let pos = uvec2(number(x)?, number(y)?);
+    if TilePos::from(pos).in_world(main.world_size).not() {
+        return Err(bad_arg(&format!("{} {}", x, y), "outside of the world"));
+    }
+    Ok(pos)
+}
+
+/// Name of an item type in commands: "Scrap Metal" is scrap_metal
+pub fn item_key(it: &ItemType) -> String {
+    it.name.to_lowercase().split_whitespace().collect::<Vec<_>>().join("_")
+}
+
+/// Item by number, full key, or the start of any word in its key if only one matches.
+/// "scrap" is Scrap Metal, but "alignite" could be the crystal or the clump.
+pub fn find_item(main: &GameMain, word: &str) -> Result<ItemTypeId, ConsoleError> {
+    if let Ok(i) = word.parse::<usize>() {
+        return (i < main.itemtype_data.len()).then_some(ItemTypeId(i)).ok_or_else(|| bad_arg(word, "no item with that number"));
+    }
+    let keys: Vec<String> = main.itemtype_data.iter().map(item_key).collect();
+    if let Some(i) = keys.iter().position(|k| k == word) {
+        return Ok(ItemTypeId(i));
+    }
+    let found: Vec<usize> = (0..keys.len())
+        .filter(|&i| keys[i].starts_with(word) || keys[i].split('_').any(|w| w.starts_with(word)))
+        .collect();
+    match found.as_slice() {
+        [i] => Ok(ItemTypeId(*i)),
+        []  => Err(bad_arg(word, "no item called that")),
+        _   => Err(bad_arg(word, &format!("could be {}", found.iter().map(|&i| keys[i].as_str()).collect::<Vec<_>>().join(", "))))
+    }
+}
+
+/// Does one command, and returns what it has to say. This is all the console does, so tests can
+/// set up the same things with the same strings.
+pub fn run_command(main: &mut GameMain, line: &str) -> Result<String, ConsoleError> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let Some((&cmd, args)) = words.split_first() else { return Ok(String::new()) };
+    let usage = COMMANDS.iter().find(|(name, _)| *name == cmd).map(|&(_, usage)| usage)
+        .ok_or_else(|| ConsoleError::UnknownCommand(cmd.to_owned()))?;
+    let mut out = String::new();
+
+    match (cmd, args) {
+        ("spawn_item", [item, count, x, y]) => {
+            let itemtype = find_item(main, item)?;
+            let count: u32 = number(count)?;
+            let pos = tile(main, x, y)?;
+
+            // a stack at a time, until the pile is full
+            let stackable = main.itemtype_data[itemtype.0].stackable.max(1);
+            let mut left = count;
+            while left != 0 {
+                let slot = ItemSlot{ itemtype, count: left.min(stackable) };
+                match place_item(&main.itemtype_data, &mut main.feral_ids, &mut main.feral_data, &mut main.feral_by_tile, pos, slot.clone()) {
+                    Ok(_)               => left -= slot.count,
+                    Err((_, rest, _))   => { left -= slot.count - rest.count; break; }
+                }
+            }
+            write!(out, "spawned {} {} at {}, {}", count - left, main.itemtype_data[itemtype.0].name, pos.x, pos.y).unwrap();
+            if left != 0 {
+                write!(out, ", no room for {}", left).unwrap();
+            }
+        },
+        ("spawn_drone", [] | [_]) => {
+            let count: u32 = args.first().map_or(Ok(1), |c| number(c))?;
+            if count > SPAWN_DRONES_MAX {
+                return Err(bad_arg(args[0], &format!("at most {} at a time", SPAWN_DRONES_MAX)));
+            }
+            for _ in 0..count {
+                spawn_drone(main);
+            }
+            write!(out, "spawned {} drones", count).unwrap();
+        },
+        ("set", [var, value]) => {
+            match *var {
+                "drone_per_second" => {
+                    let rate: f32 = number(value)?;
+                    if (rate > 0.0).not() {
+                        return Err(bad_arg(value, "has to be more than 0, 'set drones off' stops them"));
+                    }
+                    main.drone_per_second = rate;
+                    // don't wait out the old rate
+                    main.drone_timer = main.drone_timer.min(1.0 / rate);
+                },
+                "player_gun_ammo"   => {
+                    let ammo: u32 = number(value)?;
+                    if ammo > PLAYER_GUN_AMMO_MAX {
+                        return Err(bad_arg(value, &format!("the gun only holds {}", PLAYER_GUN_AMMO_MAX)));
+                    }
+                    main.player_gun_ammo = ammo;
+                },
+                "sandbox"           => main.sandbox.enabled = on_off(value)?,
+                "drones"            => main.sandbox.drones = on_off(value)?,
+                _                   => return Err(bad_arg(var, &format!("can only set {}", SET_VARS.join(", "))))
+            }
+            write!(out, "{} = {}", var, value).unwrap();
+            if *var == "drones" && main.sandbox.enabled.not() {
+                write!(out, ", only while the sandbox is on, see 'set sandbox on'").unwrap();
+            }
+        },
+        ("obfuscate", [x, y]) => {
+            let pos = tile(main, x, y)?;
+            if world_obfuscate(pos, &mut main.rail, main.world_size, &main.feral_by_tile, &main.mach_by_tile).not() {
+                return Err(bad_arg(&format!("{} {}", x, y), "can't obfuscate the rail there"));
+            }
+            regen_rail_by_tile(&main.rail, &mut main.rail_by_tile);
+            main.events.push(GameEvent::Obfuscated{ center: pos });
+            main.sounds.push(AudioEvent::flat(SoundKind::Obfuscate, 1.0));
+            write!(out, "obfuscated around {}, {}", pos.x, pos.y).unwrap();
+        },
+        ("tp", [x, y]) => {
+            let pos = tile(main, x, y)?;
+            main.player_pos = (pos.as_vec2() + 0.5) * TILE_SIZE;
+            write!(out, "player at {}, {}", pos.x, pos.y).unwrap();
+        },
+        ("give", [what]) => {
+            match *what {
+                "ammo" => main.player_gun_ammo = PLAYER_GUN_AMMO_MAX,
+                _ => return Err(bad_arg(what, "can only give ammo"))
+            }
+            write!(out, "gave {}", what).unwrap();
+        },
+        ("dump", [what]) => match *what {
+            "machines" => for mach in main.mach_ids.iter_ids() {
+                let m = &main.mach_data[mach.0];
+                let pos = m.pos.map_or("held".to_owned(), |p| format!("at {}, {}", p.x, p.y));
+                let state = match &m.spec {
+                    MachineSpec::Turret{ ammo, target, .. }  => format!("{} ammo, {:?}", ammo, target),
+                    MachineSpec::Conveyor{ item, .. }        => format!("carrying {}", item.count),
+                    MachineSpec::None                        => String::new()
+                };
+                writeln!(out, "#{} {} {} facing {:?}{}, {}", mach.0, machine_name(&m.spec), pos, m.dir, if m.enabled { "" } else { " disabled" }, state).unwrap();
+            },
+            "drones" => for drone in main.drone_ids.iter_ids() {
+                let (d, p) = (&main.drone_data[drone.0], main.drone_pos[drone.0]);
+                writeln!(out, "#{} rail_idx {} rail_pos {:.2} speed {:.1} at {:.0}, {:.0}", drone.0, d.rail_idx, d.rail_pos, d.speed, p.x, p.y).unwrap();
+            },
+            "items" => for feral in main.feral_ids.iter_ids() {
+                let d = &main.feral_data[feral.0];
+                let slots: Vec<ItemSlot> = d.slots.iter().flatten().cloned().collect();
+                writeln!(out, "{}, {}: {}", d.pos.x, d.pos.y, items_text(main, &slots)).unwrap();
+            },
+            _ => return Err(bad_arg(what, &format!("can only dump {}", DUMP_WHAT.join(", "))))
+        },
+        ("help", []) => for (_, usage) in COMMANDS {
+            writeln!(out, "{}", usage).unwrap();
+        },
+        ("help", [name]) => {
+            let (_, usage) = COMMANDS.iter().find(|(n, _)| n == name).ok_or_else(|| ConsoleError::UnknownCommand(name.to_string()))?;
+            write!(out, "{}", usage).unwrap();
+        },
+        _ => return Err(ConsoleError::Usage(usage))
+    }
+    Ok(out.trim_end().to_owned())
+}
+
+/// Runs a command a line, skipping blank lines and # comments. Stops at the first one that fails.
+pub fn run_script(main: &mut GameMain, text: &str) -> Result<String, ConsoleError> {
+    let mut out = String::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap();
+        let said = run_command(main, line).map_err(|err| ConsoleError::AtLine{ line: i + 1, err: Box::new(err) })?;
+        if said.is_empty().not() {
+            writeln!(out, "{}", said).unwrap();
+        }
+    }
+    Ok(out)
+}
+
+/// What the word being typed at the end of line could turn into
+pub fn completions(main: &GameMain, line: &str) -> Vec<String> {
+    let words: Vec<&str> = line.split(' ').collect();
+    let (partial, before) = words.split_last().unwrap();
+    let items: Vec<String>;
+    let options: Vec<&str> = match before {
+        []                      => COMMANDS.iter().map(|(name, _)| *name).collect(),
+        ["spawn_item"]          => {
+            items = main.itemtype_data.iter().map(item_key).collect();
+            items.iter().map(String::as_str).collect()
+        },
+        ["set"]                 => SET_VARS.to_vec(),
+        ["set", "sandbox" | "drones"] => ON_OFF.to_vec(),
+        ["dump"]                => DUMP_WHAT.to_vec(),
+        ["give"]                => GIVE_WHAT.to_vec(),
+        ["help"]                => COMMANDS.iter().map(|(name, _)| *name).collect(),
+        _                       => Vec::new()
+    };
+    options.into_iter().filter(|o| o.starts_with(partial)).map(str::to_owned).collect()
+}
+
+/// Text box for typing commands, with history and tab completion. See run_command.
+#[derive(Default)]
+pub struct Console {
+    pub open:       bool,
+    /// what's being typed
+    pub line:       String,
+    /// commands and what they printed, oldest first
+    pub log:        Vec<String>,
+    history:        Vec<String>,
+    /// index into history while going back through it
+    browsing:       Option<usize>
+}
+
+impl Console {
+
+    pub fn toggle(&mut self) {
+        self.open = self.open.not();
+    }
+
+    pub fn print(&mut self, text: &str) {
+        self.log.extend(text.lines().map(str::to_owned));
+        if self.log.len() > CONSOLE_LOG_MAX {
+            self.log.drain(..self.log.len() - CONSOLE_LOG_MAX);
+        }
+    }
+
+    /// Runs what's been typed
+    pub fn submit(&mut self, main: &mut GameMain) {
+        let line = std::mem::take(&mut self.line);
+        self.browsing = None;
+        if line.trim().is_empty() {
+            return;
+        }
+        self.print(&format!("> {}", line));
+        match run_command(main, &line) {
+            Ok(said)    => self.print(&said),
+            Err(err)    => self.print(&err.to_string())
+        }
+        if self.history.last() != Some(&line) {
+            self.history.push(line);
+            if self.history.len() > CONSOLE_HISTORY_MAX {
+                self.history.remove(0);
+            }
+        }
+    }
+
+    /// Up: the command before the one shown
+    pub fn history_back(&mut self) {
+        if self.history.is_empty() {
+            return;
+        }
+        let i = self.browsing.map_or(self.history.len() - 1, |i| i.saturating_sub(1));
+        self.browsing = Some(i);
+        self.line = self.history[i].clone();
+    }
+
+    /// Down: the command after the one shown, or nothing after the newest
+    pub fn history_forward(&mut self) {
+        let Some(i) = self.browsing else { return };
+        if i + 1 < self.history.len() {
+            self.browsing = Some(i + 1);
+            self.line = self.history[i + 1].clone();
+        } else {
+            self.browsing = None;
+            self.line.clear();
+        }
+    }
+
+    /// Tab: fills in the word being typed as far as all the options agree, and lists them if
+    /// there's more than one
+    pub fn complete(&mut self, main: &GameMain) {
+        let options = completions(main, &self.line);
+        let Some(first) = options.first() else { return };
+        let common = options.iter().fold(first.as_str(), |common, o| {
+            let same = common.char_indices().zip(o.chars()).find(|((_, a), b)| a != b).map_or(common.len().min(o.len()), |((i, _), _)| i);
+            &common[..same]
+        });
+        let start = self.line.rfind(' ').map_or(0, |i| i + 1);
+        self.line.truncate(start);
+        self.line.push_str(common);
+        if options.len() == 1 {
+            self.line.push(' ');
+        } else {
+            self.print(&options.join("  "));
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_console() {
+        let mut main = GameMain{ world_size: uvec2(8, 8), ..Default::default() };
+        main.rail = (0..8).map(|x| Rail{ pos: uvec2(x, 4), dir: Dir::Right, bend: Bend::Forward }).collect();
+        regen_rail_by_tile(&main.rail, &mut main.rail_by_tile);
+        main.itemtype_data = ["Destroyed Logistics Drone", "Scrap Metal", "Battery", "Red Alignite crystal", "Gunpowder", "Bullet", "Red Alignite clump"]
+            .into_iter().map(|name| ItemType{ name, stackable: 69, ..Default::default() }).collect();
+        main.feral_ids.resize(64);
+        main.mach_ids.resize(64);
+        main.drone_ids.resize(64);
+        main.drone_grid.resize(main.world_size, TILE_SIZE);
+
+        // the same strings a test would script with
+        let said = run_script(&mut main, "
+            # a pile and a couple of drones
+            spawn_item scrap 40 3 2
+            spawn_item 2 100 3 2
+            spawn_drone 2
+            set drone_per_second 12
+            tp 1 1
+            give ammo
+        ").unwrap();
+        assert!(said.contains("spawned 40 Scrap Metal at 3, 2"));
+        assert!(said.contains("spawned 100 Battery at 3, 2"));
+        let pile = &main.feral_data[main.feral_by_tile.get(tile_pos(3, 2)).unwrap().0];
+        assert_eq!(pile.slots.iter().flatten().map(|s| s.count).collect::<Vec<_>>(), vec![40, 69, 31]);
+        assert_eq!(main.drone_ids.iter_ids().count(), 2);
+        assert_eq!(main.drone_per_second, 12.0);
+        assert_eq!(main.player_pos, (uvec2(1, 1).as_vec2() + 0.5) * TILE_SIZE);
+        assert_eq!(main.player_gun_ammo, PLAYER_GUN_AMMO_MAX);
+        assert_eq!(run_command(&mut main, "dump items").unwrap(), "3, 2: 40 Scrap Metal, 69 Battery, 31 Battery");
+
+        // mistakes say what went wrong
+        assert_eq!(run_command(&mut main, "fly"), Err(ConsoleError::UnknownCommand("fly".to_owned())));
+        assert!(matches!(run_command(&mut main, "tp 1"), Err(ConsoleError::Usage(_))));
+        assert!(matches!(run_command(&mut main, "tp 1 99"), Err(ConsoleError::BadArg{ .. })));
+        assert!(matches!(run_command(&mut main, "set player_gun_ammo 133"), Err(ConsoleError::BadArg{ .. })));
+        assert!(matches!(run_command(&mut main, "spawn_drone 4000000000"), Err(ConsoleError::BadArg{ .. })));
+        assert!(run_command(&mut main, "set drones off").unwrap().contains("only while the sandbox is on"));
+        assert!(run_command(&mut main, "set sandbox on").unwrap().contains("only").not());
+        assert!(run_command(&mut main, "set drones on").unwrap().contains("only").not());
+        assert!(matches!(run_command(&mut main, "set damage off"), Err(ConsoleError::BadArg{ .. })));
+        main.sandbox = Default::default();
+        assert_eq!(main.player_gun_ammo, PLAYER_GUN_AMMO_MAX);
+        assert!(run_command(&mut main, "spawn_item alignite 1 0 0").unwrap_err().to_string().contains("red_alignite_crystal, red_alignite_clump"));
+        assert!(matches!(run_script(&mut main, "give ammo\nset drones maybe"), Err(ConsoleError::AtLine{ line: 2, .. })));
+
+        // tab fills in what it can
+        let mut console = Console{ line: "sp".to_owned(), ..Default::default() };
+        console.complete(&main);
+        assert_eq!(console.line, "spawn_");
+        console.line = "spawn_item gun".to_owned();
+        console.complete(&main);
+        assert_eq!(console.line, "spawn_item gunpowder ");
+        assert_eq!(completions(&main, "spawn_item red"), vec!["red_alignite_crystal", "red_alignite_clump"]);
+        assert_eq!(completions(&main, "set drones "), vec!["on", "off"]);
+        main.itemtype_data.extend(["Ätherium", "Äzurite"].map(|name| ItemType{ name, stackable: 69, ..Default::default() }));
+        console.line = "spawn_item ä".to_owned();
+        console.complete(&main);
+        assert_eq!(console.line, "spawn_item ä");
+        main.itemtype_data.truncate(7);
+
+        // up and down go through what was typed
+        for line in ["spawn_drone", "dump drones", "dump drones"] {
+            console.line = line.to_owned();
+            console.submit(&mut main);
+        }
+        assert_eq!(main.drone_ids.iter_ids().count(), 3);
+        assert!(console.log.iter().any(|l| l.starts_with("#2 rail_idx 0")));
+        console.history_back();
+        assert_eq!(console.line, "dump drones");
+        console.history_back();
+        console.history_back();
+        assert_eq!(console.line, "spawn_drone");
+        console.history_forward();
+        console.history_forward();
+        assert!(console.line.is_empty());
+    }
+}
@@ -0,0 +1,549 @@
+use crate::game::*;
+
+use std::ops::Not;
+use glam::UVec2;
+
+/// Edits kept for undo, oldest get dropped first
+pub const HISTORY_MAX: usize = 100;
+
+/// Something done with the Construct tool. Everything that changes what's on tiles or in the
+/// player's hands goes through construct, so it can be undone.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Construct {
+    /// Take the front item slot or the machine on a tile, with nothing held. Removes a ghost if
+    /// there's nothing else.
+    PickUp(UVec2),
+    /// Put down whatever's held. Bullets dropped on a turret go in it.
+    Drop(UVec2),
+    /// Turn the held machine counterclockwise
+    Rotate,
+    /// Recipe numbers are the same as craft_item_recipe and craft_machine_recipe
+    Craft{ recipe: u32, tile: UVec2 },
+    /// Use an obfuscator charge to scramble the rail around a tile
+    Obfuscate(UVec2),
+    /// Put ghosts of GameMain::blueprint's machines down with its top left here, then build
+    /// what there's items for
+    Paste(UVec2),
+    /// See build_ghosts
+    BuildGhosts,
+    /// Sandbox only: hold a full stack of any item
+    SpawnItem(ItemTypeId),
+    /// Sandbox only: hold a new machine without crafting it, by recipe number
+    SpawnMachine(u32),
+    /// Sandbox only: see rail_edit
+    EditRail(UVec2)
+}
+
+impl Construct {
+    /// Can only be done, or redone, while the sandbox is on
+    pub fn sandbox_only(&self) -> bool {
+        matches!(self, Construct::SpawnItem(_) | Construct::SpawnMachine(_) | Construct::EditRail(_))
+    }
+}
+
+/// Recipes from here up make machines
+const RECIPE_MACHINES: u32 = 5;
+
+/// What some tiles, machines, the rail, and the player's hands looked like at one point
+#[derive(Clone, Default, PartialEq)]
+struct Snapshot {
+    /// None for no items on the tile
+    ferals:     Vec<(UVec2, Option<Burger>)>,
+    /// None for an id that's not in use
+    machines:   Vec<(MachineId, Option<Machine>)>,
+    rail:       Option<Vec<Rail>>,
+    ghosts:     Option<Vec<(UVec2, Machine)>>,
+    drag:       Drag
+}
+
+/// A command, and the same things before and after it. The simulation keeps running between
+/// edits, so these only get put back if nothing else has touched them since.
+struct Edit {
+    cmd:        Construct,
+    before:     Snapshot,
+    after:      Snapshot
+}
+
+/// Undo and redo stacks for construct
+#[derive(Default)]
+pub struct History {
+    undo:       Vec<Edit>,
+    redo:       Vec<Edit>
+}
+
+impl History {
+    pub fn can_undo(&self) -> bool { self.undo.is_empty().not() }
+    pub fn can_redo(&self) -> bool { self.redo.is_empty().not() }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+fn held(main: &GameMain) -> Drag {
+    match &main.tool {
+        ToolMode::Construct(drag)   => drag.clone(),
+        ToolMode::GunPod            => Drag::None
+    }
+}
+
+impl Snapshot {
+    fn take(main: &GameMain, tiles: &[UVec2], machines: &[MachineId], rail: bool, ghosts: bool) -> Snapshot {
+        Snapshot{
+            ferals:     tiles.iter().map(|&tile| (tile, main.feral_by_tile.get(tile.into()).map(|f| main.feral_data[f.0].slots.clone()))).collect(),
+            machines:   machines.iter().map(|&mach| (mach, main.mach_ids.exists(mach).then(|| main.mach_data[mach.0].clone()))).collect(),
+            rail:       rail.then(|| main.rail.clone()),
+            ghosts:     ghosts.then(|| main.ghosts.iter().map(|(pos, m)| (pos.as_uvec2(), m.clone())).collect()),
+            drag:       held(main)
+        }
+    }
+
+    /// The same things, as they are now
+    fn retake(&self, main: &GameMain) -> Snapshot {
+        let tiles: Vec<UVec2> = self.ferals.iter().map(|(tile, _)| *tile).collect();
+        let machines: Vec<MachineId> = self.machines.iter().map(|(mach, _)| *mach).collect();
+        Snapshot::take(main, &tiles, &machines, self.rail.is_some(), self.ghosts.is_some())
+    }
+
+    fn restore(&self, main: &mut GameMain) {
+        for (tile, slots) in &self.ferals {
+            match (main.feral_by_tile.get((*tile).into()).copied(), slots) {
+                (Some(feral), Some(slots)) => main.feral_data[feral.0].slots = slots.clone(),
+                (Some(feral), None) => {
+                    main.feral_ids.remove(feral);
+                    main.feral_by_tile.remove((*tile).into());
+                },
+                (None, Some(slots)) => {
+                    let feral = main.feral_ids.create_resize();
+                    main.feral_data.resize(main.feral_ids.capacity(), Default::default());
+                    main.feral_data[feral.0] = FeralItem{ pos: *tile, slots: slots.clone() };
+                    main.feral_by_tile.insert((*tile).into(), feral);
+                },
+                (None, None) => {}
+            }
+        }
+
+        // take every machine off the map first, in case two swapped places
+        for (mach, _) in &self.machines {
+            if main.mach_ids.exists(*mach) {
+                if let Some(pos) = main.mach_data[mach.0].pos {
+                    if main.mach_by_tile.get(pos.into()) == Some(mach) {
+                        main.mach_by_tile.remove(pos.into());
+                    }
+                }
+            }
+        }
+        for (mach, data) in &self.machines {
+            match data {
+                Some(data) => {
+                    main.mach_ids.create_at(*mach);
+                    main.mach_data.resize(main.mach_data.len().max(main.mach_ids.capacity()), Default::default());
+                    main.mach_data[mach.0] = data.clone();
+                    if let Some(pos) = data.pos {
+                        main.mach_by_tile.insert(pos.into(), *mach);
+                    }
+                },
+                None => {
+                    main.mach_ids.remove(*mach);
+                    main.mach_data[mach.0] = Default::default();
+                }
+            }
+        }
+
+        if let Some(rail) = &self.rail {
+            main.rail = rail.clone();
+            regen_rail_by_tile(&main.rail, &mut main.rail_by_tile);
+        }
+
+        if let Some(ghosts) = &self.ghosts {
+            main.ghosts.clear();
+            for (pos, ghost) in ghosts {
+                main.ghosts.insert((*pos).into(), ghost.clone());
+            }
+        }
+
+        main.tool = ToolMode::Construct(self.drag.clone());
+    }
+}
+
+/// Does cmd if it can, and remembers how to undo it. Returns false if nothing happened.
+pub fn construct(main: &mut GameMain, cmd: Construct) -> bool {
+    let tile = match cmd {
+        Construct::PickUp(tile) | Construct::Drop(tile) | Construct::Obfuscate(tile) | Construct::Craft{ tile, .. } => Some(tile),
+        Construct::Rotate | Construct::Paste(_) | Construct::BuildGhosts => None,
+        Construct::SpawnItem(_) | Construct::SpawnMachine(_) | Construct::EditRail(_) => None
+    };
+    // building ghosts takes items from anywhere
+    let builds = matches!(cmd, Construct::Paste(_) | Construct::BuildGhosts);
+    let tiles: Vec<UVec2> = match builds {
+        true    => main.feral_ids.iter_ids().map(|f| main.feral_data[f.0].pos).collect(),
+        false   => tile.into_iter().collect()
+    };
+    let mut machines: Vec<MachineId> = tile.and_then(|t| main.mach_by_tile.get(t.into()).copied()).into_iter().collect();
+    if let Drag::Machine(mach) = held(main) {
+        machines.push(mach);
+    }
+    let existed: Vec<MachineId> = main.mach_ids.iter_ids().collect();
+    let mut before = Snapshot::take(main, &tiles, &machines, matches!(cmd, Construct::Obfuscate(_) | Construct::EditRail(_)), builds || matches!(cmd, Construct::PickUp(_)));
+
+    if apply(main, cmd).not() {
+        return false;
+    }
+
+    // machines that were just crafted or built didn't exist before
+    for mach in main.mach_ids.iter_ids() {
+        if existed.contains(&mach).not() {
+            before.machines.push((mach, None));
+        }
+    }
+
+    let after = before.retake(main);
+    let history = &mut main.history;
+    history.redo.clear();
+    history.undo.push(Edit{ cmd, before, after });
+    if history.undo.len() > HISTORY_MAX {
+        history.undo.remove(0);
+    }
+    true
+}
+
+/// Puts back everything the last command changed, and returns it. If something else changed
+/// the same things since, like a conveyor carrying items off, going back would duplicate or
+/// lose items, so the whole history gets dropped instead.
+pub fn undo(main: &mut GameMain) -> Option<Construct> {
+    let edit = main.history.undo.pop()?;
+    if edit.before.retake(main) != edit.after {
+        main.history.clear();
+        return None;
+    }
+    edit.before.restore(main);
+    let cmd = edit.cmd;
+    main.history.redo.push(edit);
+    Some(cmd)
+}
+
+/// Puts back what the last undo took away, and returns the command. Same as undo if anything
+/// changed since.
+pub fn redo(main: &mut GameMain) -> Option<Construct> {
+    let edit = main.history.redo.last()?;
+    if edit.cmd.sandbox_only() && main.sandbox.enabled.not() {
+        return None;
+    }
+    let edit = main.history.redo.pop()?;
+    if edit.after.retake(main) != edit.before {
+        main.history.clear();
+        return None;
+    }
+    edit.after.restore(main);
+    let cmd = edit.cmd;
+    main.history.undo.push(edit);
+    Some(cmd)
+}
+
+fn apply(main: &mut GameMain, cmd: Construct) -> bool {
+    let ToolMode::Construct(drag) = &mut main.tool else { return false };
+
+    let contents = |main: &GameMain, tile: UVec2| tile_contents(main.world_size, &main.rail_by_tile, &main.feral_by_tile, &main.mach_by_tile, tile);
+
+    match cmd {
+        Construct::PickUp(tile) => {
+            if matches!(drag, Drag::None).not() {
+                return false;
+            }
+            if let Some(&feral) = main.feral_by_tile.get(tile.into()) {
+                let d = &mut main.feral_data[feral.0];
+                let Some(slot) = d.slots[0].take() else { return false };
+                d.slots.rotate_left(1);
+                feral_remove_if_empty(&mut main.feral_ids, &mut main.feral_data, &mut main.feral_by_tile, feral);
+                *drag = Drag::Item(slot);
+            } else if let Some(&mach) = main.mach_by_tile.get(tile.into()) {
+                main.mach_data[mach.0].pos = None;
+                main.mach_by_tile.remove(tile.into());
+                *drag = Drag::Machine(mach);
+            } else {
+                return main.ghosts.remove(tile.into()).is_some();
+            }
+            main.sounds.push(AudioEvent::flat(SoundKind::Pickup, 0.5));
+            true
+        },
+        Construct::Drop(tile) => {
+            let Some(contents) = contents(main, tile) else { return false };
+            let ToolMode::Construct(drag) = &mut main.tool else { return false };
+
+            match std::mem::take(drag) {
+                Drag::None => false,
+                Drag::Item(slot) if contents.can_place(TileKind::Feral) => {
+                    match place_item(&main.itemtype_data, &mut main.feral_ids, &mut main.feral_data, &mut main.feral_by_tile, tile, slot) {
+                        Ok(_) => {
+                            main.sounds.push(AudioEvent::flat(SoundKind::Place, 0.5));
+                            true
+                        },
+                        Err((_, slot, transfered)) => {
+                            *drag = Drag::Item(slot);
+                            if transfered {
+                                main.sounds.push(AudioEvent::flat(SoundKind::Place, 0.5));
+                            }
+                            transfered
+                        }
+                    }
+                },
+                Drag::Item(mut slot) => {
+                    // Reload turrets
+                    let mut transfer = 0;
+                    if let (Some(mach), ITEM_BULLET) = (contents.mach, slot.itemtype) {
+                        if let MachineSpec::Turret{ ammo, .. } = &mut main.mach_data[mach.0].spec {
+                            transfer = u32::min(69_u32.saturating_sub(*ammo), slot.count);
+                            *ammo += transfer;
+                            slot.count -= transfer;
+                            main.sounds.push(AudioEvent::flat(SoundKind::Reload, 0.8));
+                        }
+                    }
+                    if slot.count != 0 {
+                        *drag = Drag::Item(slot);
+                    }
+                    transfer != 0
+                },
+                Drag::Machine(mach) => {
+                    if contents.can_place(TileKind::Machine).not() {
+                        *drag = Drag::Machine(mach);
+                        return false;
+                    }
+                    main.mach_by_tile.insert(tile.into(), mach);
+                    main.mach_data[mach.0].pos = Some(tile);
+                    main.sounds.push(AudioEvent::flat(SoundKind::Place, 0.5));
+                    true
+                }
+            }
+        },
+        Construct::Rotate => {
+            let Drag::Machine(mach) = drag else { return false };
+            main.mach_data[mach.0].configure(MachineSetting::Rotate{ clockwise: false })
+        },
+        Construct::Craft{ recipe, tile } => {
+            if matches!(drag, Drag::None).not() || contents(main, tile).is_some_and(|c| c.can_craft()).not() {
+                return false;
+            }
+            let feral = main.feral_by_tile.get(tile.into()).copied().unwrap();
+            let slots = &main.feral_data[feral.0].slots;
+
+            if recipe < RECIPE_MACHINES {
+                let Some(crafted) = craft_item_recipe(&main.itemtype_data, slots, recipe) else { return false };
+                main.feral_data[feral.0].slots = crafted;
+                main.sounds.push(AudioEvent::flat(SoundKind::Craft, 1.0));
+                return true;
+            }
+
+            let Some(crafted) = craft_machine_recipe(&main.itemtype_data, slots, recipe) else { return false };
+            main.feral_data[feral.0].slots = crafted;
+            main.sounds.push(AudioEvent::flat(SoundKind::Craft, 1.0));
+            feral_remove_if_empty(&mut main.feral_ids, &mut main.feral_data, &mut main.feral_by_tile, feral);
+
+            let mach = new_machine(main, recipe);
+            main.tool = ToolMode::Construct(Drag::Machine(mach));
+            true
+        },
+        Construct::Obfuscate(tile) => {
+            if matches!(drag, Drag::None).not() || contents(main, tile).is_some_and(|c| c.can_craft()).not() {
+                return false;
+            }
+            let feral = main.feral_by_tile.get(tile.into()).copied().unwrap();
+            let Some(crafted) = craft_item_recipe(&main.itemtype_data, &main.feral_data[feral.0].slots, 69) else { return false };
+
+            if world_obfuscate(tile, &mut main.rail, main.world_size, &main.feral_by_tile, &main.mach_by_tile).not() {
+                return false;
+            }
+            main.feral_data[feral.0].slots = crafted;
+            feral_remove_if_empty(&mut main.feral_ids, &mut main.feral_data, &mut main.feral_by_tile, feral);
+
+            main.sounds.push(AudioEvent::flat(SoundKind::Obfuscate, 1.0));
+            main.events.push(GameEvent::Obfuscated{ center: tile });
+            regen_rail_by_tile(&main.rail, &mut main.rail_by_tile);
+            true
+        },
+        Construct::Paste(tile) => {
+            if matches!(drag, Drag::None).not() {
+                return false;
+            }
+            let Some(blueprint) = &main.blueprint else { return false };
+            let mut pasted = false;
+            for part in &blueprint.parts {
+                let pos = tile + part.offset;
+                let free = tile_contents(main.world_size, &main.rail_by_tile, &main.feral_by_tile, &main.mach_by_tile, pos)
+                    .is_some_and(|c| c.can_place(TileKind::Machine));
+                if free {
+                    main.ghosts.insert(pos.into(), Machine{ pos: Some(pos), dir: part.dir, enabled: true, spec: part.spec.clone() });
+                    pasted = true;
+                }
+            }
+            if pasted {
+                build_ghosts(main);
+                main.sounds.push(AudioEvent::flat(SoundKind::Place, 0.5));
+            }
+            pasted
+        },
+        Construct::BuildGhosts => {
+            if build_ghosts(main) == 0 {
+                return false;
+            }
+            main.sounds.push(AudioEvent::flat(SoundKind::Craft, 1.0));
+            true
+        },
+        Construct::SpawnItem(itemtype) => {
+            if main.sandbox.enabled.not() || matches!(drag, Drag::None).not() {
+                return false;
+            }
+            let Some(it) = main.itemtype_data.get(itemtype.0) else { return false };
+            *drag = Drag::Item(ItemSlot{ itemtype, count: it.stackable });
+            main.sounds.push(AudioEvent::flat(SoundKind::Pickup, 0.5));
+            true
+        },
+        Construct::SpawnMachine(recipe) => {
+            if main.sandbox.enabled.not() || matches!(drag, Drag::None).not() || recipe < RECIPE_MACHINES {
+                return false;
+            }
+            let mach = new_machine(main, recipe);
+            main.tool = ToolMode::Construct(Drag::Machine(mach));
+            main.sounds.push(AudioEvent::flat(SoundKind::Craft, 1.0));
+            true
+        },
+        Construct::EditRail(tile) => {
+            if main.sandbox.enabled.not() || matches!(drag, Drag::None).not() {
+                return false;
+            }
+            if rail_edit(main, tile).not() {
+                return false;
+            }
+            main.sounds.push(AudioEvent::flat(SoundKind::Place, 0.5));
+            true
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::uvec2;
+
+    fn make_main() -> GameMain {
+        let mut main = GameMain{ world_size: uvec2(8, 8), tool: ToolMode::Construct(Drag::None), ..Default::default() };
+        main.rail = (0..8).map(|x| Rail{ pos: uvec2(x, 4), dir: Dir::Right, bend: Bend::Forward }).collect();
+        regen_rail_by_tile(&main.rail, &mut main.rail_by_tile);
+        main.itemtype_data = (0..8).map(|_| ItemType{ stackable: 69, ..Default::default() }).collect();
+        main.feral_ids.resize(64);
+        main.mach_ids.resize(64);
+        main
+    }
+
+    fn items(main: &GameMain, tile: UVec2) -> Vec<(ItemTypeId, u32)> {
+        main.feral_by_tile.get(tile.into()).map(|f| main.feral_data[f.0].slots.iter().flatten().map(|s| (s.itemtype, s.count)).collect()).unwrap_or_default()
+    }
+
+    #[test]
+    fn test_construct_undo() {
+        let mut main = make_main();
+        place_item(&main.itemtype_data, &mut main.feral_ids, &mut main.feral_data, &mut main.feral_by_tile, uvec2(1, 1), ItemSlot{ itemtype: ITEM_SCRAP, count: 3 }).ok();
+
+        // craft a turret, rotate it, put it down
+        assert!(construct(&mut main, Construct::Craft{ recipe: 5, tile: uvec2(1, 1) }));
+        assert_eq!(items(&main, uvec2(1, 1)), vec![(ITEM_SCRAP, 1)]);
+        let ToolMode::Construct(Drag::Machine(turret)) = main.tool else { panic!() };
+        assert!(construct(&mut main, Construct::Rotate));
+        assert!(construct(&mut main, Construct::Drop(uvec2(1, 4))).not(), "can't put it on the rail");
+        assert!(construct(&mut main, Construct::Drop(uvec2(2, 2))));
+        assert_eq!(main.mach_by_tile.get(tile_pos(2, 2)), Some(&turret));
+        assert!(matches!(main.mach_data[turret.0].dir, Dir::Up));
+
+        // undo all the way back to the pile of scrap
+        assert_eq!(undo(&mut main), Some(Construct::Drop(uvec2(2, 2))));
+        assert!(main.mach_by_tile.get(tile_pos(2, 2)).is_none());
+        assert!(matches!(main.tool, ToolMode::Construct(Drag::Machine(m)) if m == turret));
+        undo(&mut main);
+        assert!(matches!(main.mach_data[turret.0].dir, Dir::Right));
+        undo(&mut main);
+        assert!(main.mach_ids.exists(turret).not());
+        assert!(matches!(main.tool, ToolMode::Construct(Drag::None)));
+        assert_eq!(items(&main, uvec2(1, 1)), vec![(ITEM_SCRAP, 3)]);
+        assert_eq!(undo(&mut main), None);
+
+        // and redo gets the same turret back
+        redo(&mut main);
+        redo(&mut main);
+        assert_eq!(redo(&mut main), Some(Construct::Drop(uvec2(2, 2))));
+        assert_eq!(main.mach_by_tile.get(tile_pos(2, 2)), Some(&turret));
+        assert!(matches!(main.mach_data[turret.0].dir, Dir::Up));
+        assert_eq!(items(&main, uvec2(1, 1)), vec![(ITEM_SCRAP, 1)]);
+        assert!(main.history.can_redo().not());
+
+        // picking up splits a pile, undo puts it back together
+        assert!(construct(&mut main, Construct::PickUp(uvec2(1, 1))));
+        assert!(main.feral_by_tile.get(tile_pos(1, 1)).is_none());
+        undo(&mut main);
+        assert_eq!(items(&main, uvec2(1, 1)), vec![(ITEM_SCRAP, 1)]);
+
+        // a new command clears redo
+        assert!(construct(&mut main, Construct::PickUp(uvec2(2, 2))));
+        assert!(main.history.can_redo().not());
+
+        // a conveyor carrying off what was dropped means it can't come back
+        assert!(construct(&mut main, Construct::Drop(uvec2(2, 2))));
+        assert!(construct(&mut main, Construct::PickUp(uvec2(1, 1))));
+        assert!(construct(&mut main, Construct::Drop(uvec2(3, 3))));
+        let feral = *main.feral_by_tile.get(tile_pos(3, 3)).unwrap();
+        main.feral_data[feral.0].slots[0] = None;
+        feral_remove_if_empty(&mut main.feral_ids, &mut main.feral_data, &mut main.feral_by_tile, feral);
+        assert_eq!(undo(&mut main), None);
+        assert!(main.history.can_undo().not());
+        assert!(main.feral_by_tile.get(tile_pos(1, 1)).is_none());
+        assert_eq!(items(&main, uvec2(3, 3)), vec![]);
+    }
+
+    #[test]
+    fn test_construct_obfuscate_undo() {
+        let mut main = make_main();
+        place_item(&main.itemtype_data, &mut main.feral_ids, &mut main.feral_data, &mut main.feral_by_tile, uvec2(3, 3), ItemSlot{ itemtype: ITEM_OBFUSCATOR, count: 1 }).ok();
+        let rail: Vec<UVec2> = main.rail.iter().map(|r| r.pos).collect();
+
+        // a misclick that uses up the charge can be taken back
+        assert!(construct(&mut main, Construct::Obfuscate(uvec2(3, 3))));
+        assert!(main.feral_by_tile.get(tile_pos(3, 3)).is_none());
+        undo(&mut main);
+        assert_eq!(main.rail.iter().map(|r| r.pos).collect::<Vec<UVec2>>(), rail);
+        assert_eq!(main.rail_by_tile.get(tile_pos(5, 4)), Some(&5));
+        assert_eq!(items(&main, uvec2(3, 3)), vec![(ITEM_OBFUSCATOR, 1)]);
+    }
+
+    #[test]
+    fn test_construct_paste_undo() {
+        let mut main = make_main();
+        place_item(&main.itemtype_data, &mut main.feral_ids, &mut main.feral_data, &mut main.feral_by_tile, uvec2(0, 0), ItemSlot{ itemtype: ITEM_SCRAP, count: 10 }).ok();
+        place_item(&main.itemtype_data, &mut main.feral_ids, &mut main.feral_data, &mut main.feral_by_tile, uvec2(0, 1), ItemSlot{ itemtype: ITEM_BATTERY, count: 4 }).ok();
+        main.blueprint = Some(Blueprint::parse("size 2 1\nturret 0 0 right\nconveyor 1 0 up", &main.itemtype_data).unwrap());
+
+        // the first paste can pay for everything
+        assert!(construct(&mut main, Construct::Paste(uvec2(2, 1))));
+        assert_eq!(main.mach_by_tile.len(), 2);
+        assert_eq!(items(&main, uvec2(0, 0)), vec![(ITEM_SCRAP, 4)]);
+        assert!(main.feral_by_tile.get(tile_pos(0, 1)).is_none());
+
+        // the second runs out of batteries, so the conveyor stays a ghost
+        assert!(construct(&mut main, Construct::Paste(uvec2(2, 2))));
+        assert_eq!(main.mach_by_tile.len(), 3);
+        assert!(main.ghosts.get(tile_pos(3, 2)).is_some());
+        assert!(construct(&mut main, Construct::BuildGhosts).not());
+
+        // taking the ghost away, then all of it back
+        assert!(construct(&mut main, Construct::PickUp(uvec2(3, 2))));
+        assert!(main.ghosts.is_empty());
+        undo(&mut main);
+        assert!(main.ghosts.get(tile_pos(3, 2)).is_some());
+        undo(&mut main);
+        undo(&mut main);
+        assert!(main.ghosts.is_empty());
+        assert!(main.mach_by_tile.is_empty());
+        assert_eq!(main.mach_ids.iter_ids().count(), 0);
+        assert_eq!(items(&main, uvec2(0, 0)), vec![(ITEM_SCRAP, 10)]);
+        assert_eq!(items(&main, uvec2(0, 1)), vec![(ITEM_BATTERY, 4)]);
+    }
+}
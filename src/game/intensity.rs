@@ -0,0 +1,102 @@
+use crate::game::{GameEvent, GameMain};
+
+/// Drones alive at once for the drone part of intensity to max out
+pub const INTENSITY_DRONES: f32 = 60.0;
+/// Drones spawned per second for the pressure part of intensity to max out
+pub const INTENSITY_SPAWN_RATE: f32 = 10.0;
+/// Heat added by each drone that makes it to the end of the rail
+pub const LEAK_HEAT: f32 = 0.35;
+/// Heat lost per second
+pub const LEAK_COOLDOWN: f32 = 0.05;
+
+/// Numbers about what the simulation did over one frame
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SimStats {
+    pub drones:     usize,
+    pub kills:      u32,
+    /// drones that made it to the end of the rail
+    pub leaks:      u32,
+    /// drones per second
+    pub spawn_rate: f32
+}
+
+impl SimStats {
+    /// Counts main.events, so call before they're cleared
+    pub fn of(main: &GameMain) -> SimStats {
+        let mut stats = SimStats{
+            drones:     main.drone_ids.iter_ids().count(),
+            spawn_rate: main.drone_per_second,
+            ..Default::default()
+        };
+        for event in &main.events {
+            match event {
+                GameEvent::DroneKilled{..}  => stats.kills += 1,
+                GameEvent::DroneLeaked{..}  => stats.leaks += 1,
+                _ => {}
+            }
+        }
+        stats
+    }
+}
+
+/// How much is going on, 0 for nothing to 1 for everything on fire. Driven only by SimStats, so
+/// anything can follow it: music now, difficulty maybe later.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Intensity {
+    pub level:      f32,
+    /// goes up with leaks, cools down over time
+    pub leak_heat:  f32
+}
+
+impl Intensity {
+    /// Where level is heading, before smoothing
+    pub fn target(&self, stats: &SimStats) -> f32 {
+        let drones = (stats.drones as f32 / INTENSITY_DRONES).min(1.0);
+        let pressure = (stats.spawn_rate / INTENSITY_SPAWN_RATE).min(1.0);
+        (drones * 0.5 + pressure * 0.2 + self.leak_heat * 0.6).min(1.0)
+    }
+
+    pub fn update(&mut self, stats: &SimStats, delta: f32) {
+        self.leak_heat = (self.leak_heat + stats.leaks as f32 * LEAK_HEAT - LEAK_COOLDOWN * delta).clamp(0.0, 1.0);
+
+        // rise quickly so it reacts, fall slowly so it doesn't flicker
+        let target = self.target(stats);
+        let rate = if target > self.level { 0.5 } else { 0.05 };
+        self.level += (target - self.level).clamp(-rate * delta, rate * delta);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intensity() {
+        let mut intensity = Intensity::default();
+        let calm = SimStats{ drones: 3, spawn_rate: 1.0, ..Default::default() };
+        for _ in 0..100 {
+            intensity.update(&calm, 0.1);
+        }
+        let calm_level = intensity.level;
+        assert!(calm_level < 0.1);
+
+        // a few leaks push it up fast
+        intensity.update(&SimStats{ leaks: 2, ..calm }, 0.1);
+        assert!(intensity.leak_heat > 0.6);
+        for _ in 0..20 {
+            intensity.update(&calm, 0.1);
+        }
+        assert!(intensity.level > 0.35);
+
+        // and it settles back down once the heat is gone
+        for _ in 0..1000 {
+            intensity.update(&calm, 0.1);
+        }
+        assert_eq!(intensity.leak_heat, 0.0);
+        assert!((intensity.level - calm_level).abs() < 0.01);
+
+        let swarm = SimStats{ drones: 1000, spawn_rate: 100.0, kills: 0, leaks: 0 };
+        assert!((Intensity::default().target(&swarm) - 0.7).abs() < 0.001);
+    }
+}
@@ -23,19 +23,17 @@ pub struct FeralItem {
     pub slots: Burger
 }
 
-#[derive(Clone, Default)]
+#[derive(Clone, Default, PartialEq)]
 pub struct ItemSlot {
     pub itemtype: ItemTypeId,
     pub count: u32,
 }
 
-type Burger = [Option<ItemSlot>; 4];
+pub type Burger = [Option<ItemSlot>; 4];
 
-pub fn place_item(item_types: &Vec<ItemType>, feral_ids: &mut lgrn::IdReg<FeralItemId>, feral_data: &mut Vec<FeralItem>, feral_by_tile: &mut BTreeMap<(u8, u8), FeralItemId>, pos: UVec2, slot: ItemSlot) -> Result<FeralItemId, (FeralItemId, ItemSlot, bool)> {
-    use std::collections::btree_map::Entry;
-    match feral_by_tile.entry((pos.x as u8, pos.y as u8)) {
-        Entry::Occupied(gwah) => {
-            let feral: FeralItemId = *gwah.get();
+pub fn place_item(item_types: &Vec<ItemType>, feral_ids: &mut lgrn::IdReg<FeralItemId>, feral_data: &mut Vec<FeralItem>, feral_by_tile: &mut TileMap<FeralItemId>, pos: UVec2, slot: ItemSlot) -> Result<FeralItemId, (FeralItemId, ItemSlot, bool)> {
+    match feral_by_tile.get(pos.into()) {
+        Some(&feral) => {
             let d: &mut FeralItem = &mut feral_data[feral.0];
             let itype = &item_types[slot.itemtype.0];
 
@@ -73,8 +71,9 @@ pub fn place_item(item_types: &Vec<ItemType>, feral_ids: &mut lgrn::IdReg<FeralI
 
             return Ok(feral);
         },
-        Entry::Vacant(gwah) => {
-            let feral: FeralItemId = *gwah.insert(feral_ids.create_resize());
+        None => {
+            let feral: FeralItemId = feral_ids.create_resize();
+            feral_by_tile.insert(pos.into(), feral);
             feral_data.resize(feral_ids.capacity(), Default::default());
             let d: &mut FeralItem = &mut feral_data[feral.0];
             d.pos = pos;
@@ -124,12 +123,12 @@ pub fn take_items(slots: &mut Burger, itemtype: ItemTypeId, amount: u32) -> u32
     amount - remaining
 }
 
-pub fn feral_remove_if_empty(feral_ids: &mut lgrn::IdReg<FeralItemId>, feral_data: &mut Vec<FeralItem>, feral_by_tile: &mut BTreeMap<(u8, u8), FeralItemId>, feral: FeralItemId) {
+pub fn feral_remove_if_empty(feral_ids: &mut lgrn::IdReg<FeralItemId>, feral_data: &mut Vec<FeralItem>, feral_by_tile: &mut TileMap<FeralItemId>, feral: FeralItemId) {
     let d = &mut feral_data[feral.0];
     if d.slots.iter().all(|x| x.is_none()) {
         // No more slots left. feral item is gone 🦀
         feral_ids.remove(feral);
-        feral_by_tile.remove(&(d.pos.x as u8, d.pos.y as u8));
+        feral_by_tile.remove(d.pos.into());
     }
 }
 
@@ -149,15 +148,20 @@ pub fn craft_item_recipe(item_types: &Vec<ItemType>, slots: &Burger, recipe: u32
     }
 }
 
-pub fn craft_machine_recipe(item_types: &Vec<ItemType>, slots: &Burger, recipe: u32) -> Option<Burger> {
+/// What it takes to make a machine, for craft_machine_recipe and blueprints
+pub fn machine_cost(recipe: u32) -> Vec<ItemSlot> {
     match recipe {
-        5 => craftt(item_types, slots, &mut [ItemSlot{itemtype: ITEM_SCRAP, count: 2}], &mut []),
-        6 => craftt(item_types, slots, &mut [ItemSlot{itemtype: ITEM_SCRAP, count: 4}, ItemSlot{itemtype: ITEM_BATTERY, count: 4}], &mut []),
-        7 => craftt(item_types, slots, &mut [ItemSlot{itemtype: ITEM_SCRAP, count: 4}, ItemSlot{itemtype: ITEM_BATTERY, count: 4}, ItemSlot{itemtype: ITEM_ALIGNITE, count: 1}], &mut []),
+        5 => vec![ItemSlot{itemtype: ITEM_SCRAP, count: 2}],
+        6 => vec![ItemSlot{itemtype: ITEM_SCRAP, count: 4}, ItemSlot{itemtype: ITEM_BATTERY, count: 4}],
+        7 => vec![ItemSlot{itemtype: ITEM_SCRAP, count: 4}, ItemSlot{itemtype: ITEM_BATTERY, count: 4}, ItemSlot{itemtype: ITEM_ALIGNITE, count: 1}],
         _ => panic!()
     }
 }
 
+pub fn craft_machine_recipe(item_types: &Vec<ItemType>, slots: &Burger, recipe: u32) -> Option<Burger> {
+    craftt(item_types, slots, &mut machine_cost(recipe), &mut [])
+}
+
 pub fn craftt(item_types: &Vec<ItemType>, slots: &Burger, req: &mut [ItemSlot], out: &mut [ItemSlot]) -> Option<Burger> {
 
     let mut draft: Burger = slots.clone();
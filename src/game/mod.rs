@@ -1,10 +1,37 @@
 pub mod items;
 pub use items::*;
 
+pub mod spatial;
+pub use spatial::*;
+
+pub mod tiles;
+pub use tiles::*;
+
+pub mod occupancy;
+pub use occupancy::*;
+
+pub mod intensity;
+pub use intensity::*;
+
+pub mod time;
+pub use time::*;
+
+pub mod construct;
+pub use construct::*;
+
+pub mod blueprint;
+pub use blueprint::*;
+
+pub mod sandbox;
+pub use sandbox::*;
+
+pub mod console;
+pub use console::*;
+
 use std::iter;
 
 use glam::{vec2, Vec2, UVec2, IVec2, ivec2, uvec2, Mat2};
-use std::{collections::BTreeMap, default};
+use std::default;
 use crate::lgrn;
 
 use std::ops::Not;
@@ -29,12 +56,12 @@ pub struct GameMain {
     pub hop_count:      u64,
 
     pub rail:           Vec<Rail>,
-    pub rail_by_tile:   BTreeMap<(u8, u8), u32>,
+    pub rail_by_tile:   TileMap<u32>,
 
     pub drone_ids:      lgrn::IdReg<DroneId>,
     pub drone_pos:      Vec<Vec2>,
     pub drone_data:     Vec<Drone>,
-    pub drone_by_x:     Vec<(DroneId, f32)>,
+    pub drone_grid:     SpatialGrid<DroneId>,
 
     pub drone_per_second: f32,
     pub drone_timer:    f32,
@@ -46,22 +73,47 @@ pub struct GameMain {
     pub remove_drones:  Vec<DroneId>,
     pub remove_bullets: Vec<BulletId>,
 
+    /// Things that happened since the last time this was cleared, for drawing and sound to react to
+    pub events:         Vec<GameEvent>,
+    /// Sounds to play since the last time this was cleared, see audio::Audio
+    pub sounds:         Vec<AudioEvent>,
+    /// See SimStats, updated once a frame
+    pub intensity:      Intensity,
+    /// Pause and sim speed
+    pub time:           TimeControl,
+    /// Undo and redo for the Construct tool
+    pub history:        History,
+
     pub itemtype_data:  Vec<ItemType>,
 
     pub feral_ids:      lgrn::IdReg<FeralItemId>,
     pub feral_data:     Vec<FeralItem>,
-    pub feral_by_tile:  BTreeMap<(u8, u8), FeralItemId>,
+    pub feral_by_tile:  TileMap<FeralItemId>,
 
     pub mach_ids:       lgrn::IdReg<MachineId>,
     pub mach_data:      Vec<Machine>,
-    pub mach_by_tile:   BTreeMap<(u8, u8), MachineId>,
+    pub mach_by_tile:   TileMap<MachineId>,
+    /// Machines pasted from a blueprint that haven't been built yet, see build_ghosts
+    pub ghosts:         TileMap<Machine>,
+    /// Last blueprint copied or loaded, for pasting
+    pub blueprint:      Option<Blueprint>,
+    /// Creative mode settings
+    pub sandbox:        Sandbox,
 
     pub tool:           ToolMode
 }
 
+/// Region of one of the sprite atlas pages, see draw::atlas
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct Sprite {
+    pub page:           usize,
+    /// top left, bottom right in texture coordinates
+    pub coord:          (Vec2, Vec2)
+}
+
 #[derive(Default)]
 pub struct ItemType {
-    pub sprite:         (Vec2, Vec2),
+    pub sprite:         Sprite,
     pub stackable:      u32,
     pub name:           &'static str,
     pub desc:           &'static str
@@ -77,6 +129,7 @@ impl Default for ToolMode {
     fn default() -> Self { ToolMode::GunPod }
 }
 
+#[derive(Clone, PartialEq)]
 pub enum Drag {
      None,
      Item(ItemSlot),
@@ -91,13 +144,56 @@ pub struct Controls {
     pub walk: Vec2
 }
 
+/// Positions are in world pixels
 #[derive(Clone, Copy, Debug)]
+pub enum GameEvent {
+    DroneKilled{ pos: Vec2 },
+    /// made it to the end of the rail
+    DroneLeaked{ pos: Vec2 },
+    BulletDeflected{ pos: Vec2, dir: Vec2 },
+    TurretFired{ mach: MachineId, pos: Vec2, dir: Vec2 },
+    PlayerFired{ pos: Vec2, dir: Vec2 },
+    Obfuscated{ center: UVec2 }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SoundKind {
+    Step, ChangeDir, Press,
+    PlayerShoot, TurretShoot, Deflect, Explode,
+    Pickup, Place, Craft, Reload, Obfuscate
+}
+
+impl SoundKind {
+    pub const COUNT: usize = 12;
+}
+
+/// A sound for the audio module to play, if it can be heard
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AudioEvent {
+    pub kind:       SoundKind,
+    /// world pixels, None for sounds that aren't anywhere in particular like picking things up
+    pub pos:        Option<Vec2>,
+    /// 0 to 1, before distance and volume settings
+    pub intensity:  f32
+}
+
+impl AudioEvent {
+    pub fn at(kind: SoundKind, pos: Vec2, intensity: f32) -> AudioEvent {
+        AudioEvent{ kind, pos: Some(pos), intensity }
+    }
+
+    pub fn flat(kind: SoundKind, intensity: f32) -> AudioEvent {
+        AudioEvent{ kind, pos: None, intensity }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Dir { Right, Down, Left, Up }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Bend { Forward, Right, Left }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq)]
 pub struct Rail {
     pub pos:    UVec2,
     pub dir:    Dir,
@@ -120,33 +216,123 @@ pub struct Bullet {
     pub travel_max: f32
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct Machine {
-    pub pos:    Option<UVec2>,
-    pub dir:    Dir,
-    pub spec:   MachineSpec
+    pub pos:        Option<UVec2>,
+    pub dir:        Dir,
+    /// Disabled machines sit there and do nothing
+    pub enabled:    bool,
+    pub spec:       MachineSpec
 }
 impl Default for Machine {
     fn default() -> Self {
-        Machine{ pos: None, dir: Dir::Right, spec: MachineSpec::None }
+        Machine{ pos: None, dir: Dir::Right, enabled: true, spec: MachineSpec::None }
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub enum MachineSpec {
     None,
-    Turret      {ammo: u32, can_fire_time_us: u64},
+    Turret      {ammo: u32, can_fire_time_us: u64, target: TurretTarget},
     Conveyor    {item: ItemSlot, filter: bool, can_move_time_us: u64, can_dump_time_us: u64},
 }
 
-pub fn regen_rail_by_tile(rail: &Vec<Rail>, rail_by_tile: &mut BTreeMap<(u8, u8), u32>) {
+/// What a turret shoots at
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TurretTarget {
+    /// Anything that crosses in front of it
+    #[default]
+    Ahead,
+    /// Closest drone within TURRET_RANGE, in any direction
+    Nearest
+}
+
+/// Tiles
+pub const TURRET_RANGE: f32 = 4.5;
+
+/// A change made from the machine config panel
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MachineSetting {
+    Rotate{ clockwise: bool },
+    Enabled(bool),
+    /// Only for filterveyors. None lets anything through
+    Filter(Option<ItemTypeId>),
+    Target(TurretTarget)
+}
+
+impl Machine {
+    /// Returns false if the setting doesn't work for this machine, or can't be changed right now.
+    /// A filterveyor's filter can only be changed while it's empty.
+    pub fn configure(&mut self, setting: MachineSetting) -> bool {
+        match (setting, &mut self.spec) {
+            (MachineSetting::Rotate{ clockwise }, _) => {
+                self.dir = match (self.dir, clockwise) {
+                    (Dir::Right, false) | (Dir::Left, true)  => Dir::Up,
+                    (Dir::Up, false)    | (Dir::Down, true)  => Dir::Left,
+                    (Dir::Left, false)  | (Dir::Right, true) => Dir::Down,
+                    (Dir::Down, false)  | (Dir::Up, true)    => Dir::Right
+                };
+                true
+            },
+            (MachineSetting::Enabled(enabled), _) => {
+                self.enabled = enabled;
+                true
+            },
+            (MachineSetting::Filter(itemtype), MachineSpec::Conveyor{ item, filter: true, .. }) => {
+                // the conveyor keeps no filter as Default::default()
+                let itemtype = itemtype.unwrap_or_default();
+                if item.count != 0 && item.itemtype != itemtype {
+                    return false;
+                }
+                item.itemtype = itemtype;
+                true
+            },
+            (MachineSetting::Target(t), MachineSpec::Turret{ target, .. }) => {
+                *target = t;
+                true
+            },
+            _ => false
+        }
+    }
+}
+
+pub fn regen_rail_by_tile(rail: &Vec<Rail>, rail_by_tile: &mut TileMap<u32>) {
     rail_by_tile.clear();
     for (i, r) in rail.iter().enumerate()  {
-        rail_by_tile.insert((r.pos.x as u8, r.pos.y as u8), i as u32);
+        rail_by_tile.insert(r.pos.into(), i as u32);
     }
 }
 
-pub fn world_obfuscate(center: UVec2, rail: &mut Vec<Rail>, world_size: UVec2) -> bool {
+/// Box around a drone that bullets hit, also the box around a bullet that drones get found in
+pub fn drone_aabb(pos: Vec2) -> (Vec2, Vec2) {
+    (pos - TILE_SIZE * 0.5, pos + TILE_SIZE * 0.5)
+}
+
+/// Where a turret set to TurretTarget::Ahead looks for drones: 5 tiles out from its middle, a bit
+/// over a tile wide
+pub fn turret_detect_box(pos: UVec2, dir: &Dir) -> (Vec2, Vec2) {
+    let ppos = (pos.as_vec2() + vec2(0.5, 0.5)) * TILE_SIZE;
+    let dirmat = dir_to_mat2(dir);
+    let point_a = ppos + dirmat.mul_vec2(vec2(0.0, 0.6)) * TILE_SIZE;
+    let point_b = ppos + dirmat.mul_vec2(vec2(5.0, -0.6)) * TILE_SIZE;
+    (Vec2::min(point_a, point_b), Vec2::max(point_a, point_b))
+}
+
+/// New drone at the start of the rail. It gets put on the rail the next time drones move.
+pub fn spawn_drone(main: &mut GameMain) -> DroneId {
+    let id = main.drone_ids.create_resize();
+    main.drone_pos.resize(main.drone_ids.capacity(), vec2(-10000.0, -100000.0));
+    main.drone_data.resize(main.drone_ids.capacity(), Drone{rail_idx: 0, rail_pos: 0.0, speed: 0.0});
+
+    main.drone_data[id.0] = Drone{rail_idx: 0, rail_pos: 0.0, speed: 32.0};
+
+    let pos = &mut main.drone_pos[id.0];
+    *pos = vec2(-10000.0, -100000.0);
+    main.drone_grid.insert(id, *pos);
+    id
+}
+
+pub fn world_obfuscate(center: UVec2, rail: &mut Vec<Rail>, world_size: UVec2, feral_by_tile: &TileMap<FeralItemId>, mach_by_tile: &TileMap<MachineId>) -> bool {
 
     if     (2 > center.x) || (center.x > (world_size.x-4))
         || (2 > center.y) || (center.y > (world_size.y-4)) {
@@ -200,6 +386,18 @@ pub fn world_obfuscate(center: UVec2, rail: &mut Vec<Rail>, world_size: UVec2) -
         obstacles[rpob.y as usize][rpob.x as usize] = 0;
     }
 
+    // anything else that rail can't go through. existing rail is already handled above
+    let no_rail: TileMap<u32> = Default::default();
+    for y in 0..5 {
+        for x in 0..5 {
+            if let Some(contents) = tile_contents(world_size, &no_rail, feral_by_tile, mach_by_tile, tl + uvec2(x, y)) {
+                if contents.can_place(TileKind::Rail).not() {
+                    obstacles[y as usize][x as usize] = 1;
+                }
+            }
+        }
+    }
+
     let mut path: Vec<Dir> = Default::default();
     path.reserve(25);
 
@@ -218,7 +416,6 @@ pub fn world_obfuscate(center: UVec2, rail: &mut Vec<Rail>, world_size: UVec2) -
     let obf_start_pos = rail[enter_idx].pos - tl;
     let obf_end_pos   = rail[exit_idx-1].pos - tl;
 
-    println!("{}->{}", obf_start_pos, obf_end_pos);
     for _ in 0..50 {
         path.clear();
         success = obfuscate(obstacles, obf_start_pos, enter_dir, obf_end_pos, &mut path);
@@ -257,7 +454,7 @@ pub fn world_obfuscate(center: UVec2, rail: &mut Vec<Rail>, world_size: UVec2) -
 
     }
 
-    return true;
+    return success;
 }
 
 pub fn obfuscate(obstacles: [[u8; 5]; 5], start: UVec2, start_dir: Dir, end: UVec2, out: &mut Vec<Dir>) -> bool {
@@ -353,12 +550,6 @@ pub fn obfuscate(obstacles: [[u8; 5]; 5], start: UVec2, start_dir: Dir, end: UVe
         steps += 1;
     }
 
-    println!("\n{:?}", visited[0]);
-    println!("{:?}", visited[1]);
-    println!("{:?}", visited[2]);
-    println!("{:?}", visited[3]);
-    println!("{:?}", visited[4]);
-
     return true;
 }
 
@@ -377,12 +568,12 @@ pub fn rot_ccw_90(a: Vec2) -> Vec2 {
     vec2(a.y, -a.x)
 }
 
-pub fn flip_x(a: (Vec2, Vec2)) -> (Vec2, Vec2) {
-    (vec2(a.1.x, a.0.y), vec2(a.0.x, a.1.y))
+pub fn flip_x(a: Sprite) -> Sprite {
+    Sprite{ coord: (vec2(a.coord.1.x, a.coord.0.y), vec2(a.coord.0.x, a.coord.1.y)), ..a }
 }
 
-pub fn flip_y(a: (Vec2, Vec2)) -> (Vec2, Vec2) {
-    (vec2(a.0.x, a.1.y), vec2(a.1.x, a.0.y))
+pub fn flip_y(a: Sprite) -> Sprite {
+    Sprite{ coord: (vec2(a.coord.0.x, a.coord.1.y), vec2(a.coord.1.x, a.coord.0.y)), ..a }
 }
 
 pub fn dir_to_mat2(dir: &Dir) -> Mat2 {
@@ -458,5 +649,38 @@ mod tests {
 
         assert!(line_segment_vs_line_intersect((vec2(5.0, 5.0), vec2(10.0, 10.0)), vec2(4.0, 0.0), vec2(0.0, 1.0)).not());
     }
+
+    #[test]
+    fn test_machine_configure() {
+        let mut filterveyor = Machine{ spec: MachineSpec::Conveyor{ item: Default::default(), filter: true, can_move_time_us: 0, can_dump_time_us: 0 }, ..Default::default() };
+
+        assert!(filterveyor.configure(MachineSetting::Rotate{ clockwise: true }));
+        assert!(matches!(filterveyor.dir, Dir::Down));
+        assert!(filterveyor.configure(MachineSetting::Rotate{ clockwise: false }));
+        assert!(matches!(filterveyor.dir, Dir::Right));
+
+        assert!(filterveyor.configure(MachineSetting::Filter(Some(ITEM_BULLET))));
+        assert!(filterveyor.configure(MachineSetting::Target(TurretTarget::Nearest)).not());
+
+        // can't swap out the filter while carrying something else
+        let MachineSpec::Conveyor{ item, .. } = &mut filterveyor.spec else { panic!() };
+        item.count = 1;
+        assert!(filterveyor.configure(MachineSetting::Filter(Some(ITEM_SCRAP))).not());
+        let MachineSpec::Conveyor{ item, .. } = &filterveyor.spec else { panic!() };
+        assert_eq!(item.itemtype, ITEM_BULLET);
+
+        // the first item is a filter like any other, not the same as no filter
+        let MachineSpec::Conveyor{ item, .. } = &mut filterveyor.spec else { panic!() };
+        item.count = 0;
+        assert!(filterveyor.configure(MachineSetting::Filter(Some(ITEM_DEAD_DRONE))));
+        assert!(matches!(&filterveyor.spec, MachineSpec::Conveyor{ item, .. } if item.itemtype == ITEM_DEAD_DRONE));
+        assert!(filterveyor.configure(MachineSetting::Filter(None)));
+        assert!(matches!(&filterveyor.spec, MachineSpec::Conveyor{ item, .. } if item.itemtype != ITEM_DEAD_DRONE && item.itemtype == Default::default()));
+
+        let mut conveyor = Machine{ spec: MachineSpec::Conveyor{ item: Default::default(), filter: false, can_move_time_us: 0, can_dump_time_us: 0 }, ..Default::default() };
+        assert!(conveyor.configure(MachineSetting::Filter(Some(ITEM_BULLET))).not());
+        assert!(conveyor.configure(MachineSetting::Enabled(false)));
+        assert!(conveyor.enabled.not());
+    }
 }
 
@@ -0,0 +1,93 @@
+use crate::game::*;
+use glam::UVec2;
+
+/// Everything that's on a single tile
+#[derive(Clone, Copy, Default, Debug)]
+pub struct TileContents {
+    pub pos:    TilePos,
+    pub rail:   Option<u32>,
+    pub feral:  Option<FeralItemId>,
+    pub mach:   Option<MachineId>
+}
+
+/// Kinds of things that can be put on a tile
+#[derive(Clone, Copy, Debug)]
+pub enum TileKind {
+    Rail,
+    Machine,
+    Feral,
+    Wall
+}
+
+impl TileContents {
+    pub fn is_empty(&self) -> bool {
+        self.rail.is_none() && self.feral.is_none() && self.mach.is_none()
+    }
+
+    pub fn can_place(&self, kind: TileKind) -> bool {
+        match kind {
+            // drones need to pass through, but items lying around are fine
+            TileKind::Rail      => self.rail.is_none() && self.mach.is_none(),
+            TileKind::Machine   => self.is_empty(),
+            // items pile up anywhere except inside of machines
+            TileKind::Feral     => self.mach.is_none(),
+            TileKind::Wall      => self.is_empty()
+        }
+    }
+
+    /// Feral piles can be crafted with, but not while they're on rails
+    pub fn can_craft(&self) -> bool {
+        self.feral.is_some() && self.rail.is_none()
+    }
+}
+
+/// Returns None if pos is outside of the world
+pub fn tile_contents(world_size: UVec2, rail_by_tile: &TileMap<u32>, feral_by_tile: &TileMap<FeralItemId>, mach_by_tile: &TileMap<MachineId>, pos: UVec2) -> Option<TileContents> {
+    let pos = TilePos::from(pos);
+    if pos.in_world(world_size).not() {
+        return None;
+    }
+    Some(TileContents {
+        pos,
+        rail:   rail_by_tile.get(pos).copied(),
+        feral:  feral_by_tile.get(pos).copied(),
+        mach:   mach_by_tile.get(pos).copied()
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::uvec2;
+
+    #[test]
+    fn test_occupancy() {
+        let mut main = GameMain{ world_size: uvec2(4, 4), ..Default::default() };
+        main.feral_ids.resize(64);
+        main.mach_ids.resize(64);
+        main.rail_by_tile.insert(tile_pos(0, 0), 0);
+        main.feral_by_tile.insert(tile_pos(1, 0), main.feral_ids.create().unwrap());
+        main.mach_by_tile.insert(tile_pos(2, 0), main.mach_ids.create().unwrap());
+        // items left on the rail
+        main.rail_by_tile.insert(tile_pos(3, 0), 1);
+        main.feral_by_tile.insert(tile_pos(3, 0), main.feral_ids.create().unwrap());
+
+        let at = |x, y| tile_contents(main.world_size, &main.rail_by_tile, &main.feral_by_tile, &main.mach_by_tile, uvec2(x, y));
+        let kinds = [TileKind::Rail, TileKind::Machine, TileKind::Feral, TileKind::Wall];
+        let allowed = |c: TileContents| kinds.map(|k| c.can_place(k));
+
+        //                                   Rail   Machine Feral  Wall
+        assert_eq!(allowed(at(0, 1).unwrap()), [true,  true,  true,  true ]);
+        assert_eq!(allowed(at(0, 0).unwrap()), [false, false, true,  false]);
+        assert_eq!(allowed(at(1, 0).unwrap()), [true,  false, true,  false]);
+        assert_eq!(allowed(at(2, 0).unwrap()), [false, false, false, false]);
+        assert_eq!(allowed(at(3, 0).unwrap()), [false, false, true,  false]);
+        assert!(at(4, 0).is_none());
+        assert!(at(0, 4).is_none());
+
+        assert!(at(1, 0).unwrap().can_craft());
+        assert!(at(3, 0).unwrap().can_craft().not());
+        assert!(at(0, 1).unwrap().can_craft().not());
+    }
+}
@@ -0,0 +1,140 @@
+use crate::game::*;
+
+use std::ops::Not;
+use glam::UVec2;
+
+/// Creative mode for trying out layouts. Everything here only matters while enabled.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sandbox {
+    pub enabled:    bool,
+    /// drones keep spawning
+    pub drones:     bool,
+    /// Use on a tile extends or shortens the rail instead of picking things up, see EditRail
+    pub rail_edit:  bool
+}
+
+impl Default for Sandbox {
+    fn default() -> Self {
+        Sandbox{ enabled: false, drones: true, rail_edit: false }
+    }
+}
+
+impl Sandbox {
+    pub fn spawns_drones(&self) -> bool {
+        self.enabled.not() || self.drones
+    }
+
+    pub fn editing_rail(&self) -> bool {
+        self.enabled && self.rail_edit
+    }
+}
+
+/// New machine that isn't anywhere yet, the same as crafting recipe would make
+pub fn new_machine(main: &mut GameMain, recipe: u32) -> MachineId {
+    let mach = main.mach_ids.create_resize();
+    main.mach_data.resize(main.mach_ids.capacity(), Default::default());
+    main.mach_data[mach.0] = Machine{ spec: match recipe {
+        5 => MachineSpec::Turret{ ammo: 0, can_fire_time_us: main.time_us, target: Default::default() },
+        _ => MachineSpec::Conveyor{ item: Default::default(), filter: recipe == 7, can_move_time_us: main.time_us, can_dump_time_us: main.time_us }
+    }, ..Default::default() };
+    mach
+}
+
+/// Clicking next to the end of the rail adds a tile there, clicking on the end takes it away.
+/// Only the end can change, so the rail stays one unbroken path.
+pub fn rail_edit(main: &mut GameMain, tile: UVec2) -> bool {
+    let Some(last) = main.rail.last().copied() else { return false };
+
+    if tile == last.pos {
+        if main.rail.len() == 1 {
+            return false;
+        }
+        main.rail.pop();
+        main.rail.last_mut().unwrap().bend = Bend::Forward;
+    } else {
+        let free = tile_contents(main.world_size, &main.rail_by_tile, &main.feral_by_tile, &main.mach_by_tile, tile)
+            .is_some_and(|c| c.can_place(TileKind::Rail));
+        let step = tile.as_ivec2() - last.pos.as_ivec2();
+        if free.not() || step.x.abs() + step.y.abs() != 1 {
+            return false;
+        }
+        let dir = vec2_to_dir(step.as_vec2());
+        let ahead = dir_to_vec2(&last.dir);
+        let bend = match step.as_vec2() {
+            s if s == ahead             => Bend::Forward,
+            s if s == rot_cw_90(ahead)  => Bend::Right,
+            s if s == rot_ccw_90(ahead) => Bend::Left,
+            // can't turn around
+            _                           => return false
+        };
+        main.rail.last_mut().unwrap().bend = bend;
+        main.rail.push(Rail{ pos: tile, dir, bend: Bend::Forward });
+    }
+    regen_rail_by_tile(&main.rail, &mut main.rail_by_tile);
+    true
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::uvec2;
+
+    #[test]
+    fn test_sandbox() {
+        let mut main = GameMain{ world_size: uvec2(8, 8), tool: ToolMode::Construct(Drag::None), ..Default::default() };
+        main.rail = (0..4).map(|x| Rail{ pos: uvec2(x, 4), dir: Dir::Right, bend: Bend::Forward }).collect();
+        regen_rail_by_tile(&main.rail, &mut main.rail_by_tile);
+        main.itemtype_data = (0..8).map(|_| ItemType{ stackable: 69, ..Default::default() }).collect();
+        main.feral_ids.resize(64);
+        main.mach_ids.resize(64);
+
+        // nothing works until it's turned on
+        assert!(construct(&mut main, Construct::SpawnItem(ITEM_SCRAP)).not());
+        main.sandbox = Sandbox{ enabled: true, drones: false, ..Default::default() };
+        assert!(main.sandbox.spawns_drones().not());
+
+        // a full stack, for free
+        assert!(construct(&mut main, Construct::SpawnItem(ITEM_SCRAP)));
+        assert!(matches!(main.tool, ToolMode::Construct(Drag::Item(ItemSlot{ itemtype: ITEM_SCRAP, count: 69 }))));
+        assert!(construct(&mut main, Construct::SpawnMachine(5)).not());
+        assert!(construct(&mut main, Construct::Drop(uvec2(1, 1))));
+
+        // a machine without crafting
+        assert!(construct(&mut main, Construct::SpawnMachine(7)));
+        let ToolMode::Construct(Drag::Machine(mach)) = main.tool else { panic!() };
+        assert!(matches!(main.mach_data[mach.0].spec, MachineSpec::Conveyor{ filter: true, .. }));
+        assert!(construct(&mut main, Construct::Drop(uvec2(2, 2))));
+
+        // down and then left, but not back into itself or off the end
+        assert!(construct(&mut main, Construct::EditRail(uvec2(3, 5))));
+        assert!(construct(&mut main, Construct::EditRail(uvec2(2, 5))));
+        assert!(construct(&mut main, Construct::EditRail(uvec2(2, 4))).not());
+        assert!(construct(&mut main, Construct::EditRail(uvec2(0, 0))).not());
+        assert_eq!(main.rail.len(), 6);
+        assert!(matches!(main.rail[3].bend, Bend::Right));
+        assert!(matches!(main.rail[4], Rail{ dir: Dir::Down, bend: Bend::Right, .. }));
+        assert!(matches!(main.rail[5], Rail{ dir: Dir::Left, bend: Bend::Forward, .. }));
+
+        // clicking the end takes it back off
+        assert!(construct(&mut main, Construct::EditRail(uvec2(2, 5))));
+        assert_eq!(main.rail.len(), 5);
+        assert!(matches!(main.rail[4].bend, Bend::Forward));
+        assert!(main.rail_by_tile.get(tile_pos(2, 5)).is_none());
+
+        // and it all undoes
+        for _ in 0..7 {
+            assert!(undo(&mut main).is_some());
+        }
+        assert_eq!(main.rail.len(), 4);
+        assert_eq!(main.mach_ids.iter_ids().count(), 0);
+        assert_eq!(main.feral_ids.iter_ids().count(), 0);
+
+        // but free items don't come back once it's off
+        main.sandbox.enabled = false;
+        assert_eq!(redo(&mut main), None);
+        assert!(matches!(main.tool, ToolMode::Construct(Drag::None)));
+        main.sandbox.enabled = true;
+        assert_eq!(redo(&mut main), Some(Construct::SpawnItem(ITEM_SCRAP)));
+    }
+}
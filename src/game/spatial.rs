@@ -0,0 +1,167 @@
+use glam::{vec2, Vec2, UVec2, IVec2, ivec2};
+
+use std::ops::Not;
+
+/// Uniform grid of buckets for finding things near a point. Ids are stored along with their
+/// position; positions outside of the grid are clamped into the edge cells.
+pub struct SpatialGrid<T> {
+    size:       UVec2,
+    cell_size:  Vec2,
+    cells:      Vec<Vec<(T, Vec2)>>,
+    cell_of:    Vec<Option<u32>>
+}
+
+impl<T> Default for SpatialGrid<T> {
+    fn default() -> Self {
+        SpatialGrid{ size: UVec2::ZERO, cell_size: vec2(1.0, 1.0), cells: Vec::new(), cell_of: Vec::new() }
+    }
+}
+
+impl<T: Into<usize> + Copy + PartialEq> SpatialGrid<T> {
+
+    /// Clears everything and sets the number of cells and their size
+    pub fn resize(&mut self, size: UVec2, cell_size: Vec2) {
+        self.size = size;
+        self.cell_size = cell_size;
+        self.cells.clear();
+        self.cells.resize_with((size.x * size.y) as usize, Default::default);
+        self.cell_of.clear();
+    }
+
+    pub fn insert(&mut self, id: T, pos: Vec2) {
+        let idx: usize = id.into();
+        if idx >= self.cell_of.len() {
+            self.cell_of.resize(idx + 1, None);
+        }
+        if self.cell_of[idx].is_some() {
+            self.remove(id);
+        }
+        let cell = self.cell_at(pos);
+        self.cells[cell].push((id, pos));
+        self.cell_of[idx] = Some(cell as u32);
+    }
+
+    pub fn update(&mut self, id: T, pos: Vec2) {
+        let Some(&Some(cell)) = self.cell_of.get(id.into()) else {
+            self.insert(id, pos);
+            return;
+        };
+        let cell = cell as usize;
+        let new_cell = self.cell_at(pos);
+
+        if cell == new_cell {
+            if let Some(entry) = self.cells[cell].iter_mut().find(|(x, _)| *x == id) {
+                entry.1 = pos;
+            }
+        } else {
+            self.remove(id);
+            self.cells[new_cell].push((id, pos));
+            self.cell_of[id.into()] = Some(new_cell as u32);
+        }
+    }
+
+    /// Returns false if id wasn't in the grid
+    pub fn remove(&mut self, id: T) -> bool {
+        let Some(cell) = self.cell_of.get_mut(id.into()).and_then(Option::take) else {
+            return false;
+        };
+        let bucket = &mut self.cells[cell as usize];
+        if let Some(i) = bucket.iter().position(|(x, _)| *x == id) {
+            bucket.swap_remove(i);
+        }
+        true
+    }
+
+    pub fn contains(&self, id: T) -> bool {
+        matches!(self.cell_of.get(id.into()), Some(Some(_)))
+    }
+
+    /// Everything with a position inside of the box, exclusive
+    pub fn iter_aabb(&self, tl: Vec2, br: Vec2) -> impl Iterator<Item = (T, Vec2)> + '_ {
+        self.iter_cells(tl, br)
+            .filter(move |(_, p)| tl.x < p.x && p.x < br.x && tl.y < p.y && p.y < br.y)
+    }
+
+    /// Everything within radius of the line segment from pos to pos + dir*length
+    pub fn iter_ray(&self, pos: Vec2, dir: Vec2, length: f32, radius: f32) -> impl Iterator<Item = (T, Vec2)> + '_ {
+        let end = pos + dir * length;
+        let tl = Vec2::min(pos, end) - radius;
+        let br = Vec2::max(pos, end) + radius;
+        self.iter_cells(tl, br)
+            .filter(move |(_, p)| {
+                let t = (*p - pos).dot(dir).clamp(0.0, length);
+                (pos + dir * t).distance_squared(*p) <= radius * radius
+            })
+    }
+
+    /// Everything within range of pos and less than half_angle (radians) away from dir
+    pub fn iter_cone(&self, pos: Vec2, dir: Vec2, half_angle: f32, range: f32) -> impl Iterator<Item = (T, Vec2)> + '_ {
+        let cos_min = half_angle.cos();
+        self.iter_cells(pos - range, pos + range)
+            .filter(move |(_, p)| {
+                let rel = *p - pos;
+                let dist = rel.length();
+                dist <= range && (dist == 0.0 || rel.dot(dir) >= dist * cos_min)
+            })
+    }
+
+    fn iter_cells(&self, tl: Vec2, br: Vec2) -> impl Iterator<Item = (T, Vec2)> + '_ {
+        let (a, b) = if self.cells.is_empty() {
+            (ivec2(0, 0), ivec2(-1, -1))
+        } else {
+            (self.cell_coord(tl), self.cell_coord(br))
+        };
+        let width = self.size.x as usize;
+        (a.y..=b.y)
+            .flat_map(move |y| (a.x..=b.x).map(move |x| y as usize * width + x as usize))
+            .flat_map(move |cell| self.cells[cell].iter().copied())
+    }
+
+    fn cell_coord(&self, pos: Vec2) -> IVec2 {
+        let max = self.size.as_ivec2() - 1;
+        (pos / self.cell_size).floor().as_ivec2().clamp(IVec2::ZERO, max)
+    }
+
+    fn cell_at(&self, pos: Vec2) -> usize {
+        assert!(self.cells.is_empty().not(), "SpatialGrid used before resize");
+        let c = self.cell_coord(pos);
+        c.y as usize * self.size.x as usize + c.x as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lgrn;
+
+    lgrn::id_type!(FishId);
+
+    #[test]
+    fn test_spatial_grid() {
+        let mut grid: SpatialGrid<FishId> = Default::default();
+        grid.resize(UVec2::new(10, 10), vec2(64.0, 64.0));
+
+        grid.insert(FishId(0), vec2(100.0, 100.0));
+        grid.insert(FishId(1), vec2(300.0, 100.0));
+        grid.insert(FishId(2), vec2(-5000.0, -5000.0)); // clamped into corner
+
+        let found: Vec<FishId> = grid.iter_aabb(vec2(50.0, 50.0), vec2(150.0, 150.0)).map(|(id, _)| id).collect();
+        assert_eq!(found, vec![FishId(0)]);
+
+        assert!(grid.iter_aabb(vec2(0.0, 0.0), vec2(64.0, 64.0)).next().is_none());
+
+        grid.update(FishId(0), vec2(310.0, 100.0));
+        let mut found: Vec<usize> = grid.iter_aabb(vec2(250.0, 50.0), vec2(350.0, 150.0)).map(|(id, _)| id.0).collect();
+        found.sort();
+        assert_eq!(found, vec![0, 1]);
+
+        assert_eq!(grid.iter_ray(vec2(0.0, 100.0), vec2(1.0, 0.0), 295.0, 10.0).count(), 1);
+        assert_eq!(grid.iter_cone(vec2(100.0, 100.0), vec2(1.0, 0.0), 0.1, 250.0).count(), 2);
+        assert_eq!(grid.iter_cone(vec2(100.0, 100.0), vec2(-1.0, 0.0), 0.1, 250.0).count(), 0);
+
+        assert!(grid.remove(FishId(1)));
+        assert!(grid.remove(FishId(1)).not());
+        assert!(grid.contains(FishId(1)).not());
+        assert_eq!(grid.iter_aabb(vec2(250.0, 50.0), vec2(350.0, 150.0)).count(), 1);
+    }
+}
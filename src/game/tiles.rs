@@ -0,0 +1,195 @@
+use glam::{UVec2, IVec2, uvec2};
+use std::collections::BTreeMap;
+
+/// Tiles are stored in square chunks of this width
+pub const CHUNK_SIZE: u32 = 16;
+const CHUNK_AREA: usize = (CHUNK_SIZE * CHUNK_SIZE) as usize;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+pub struct TilePos {
+    pub x: u32,
+    pub y: u32
+}
+
+pub const fn tile_pos(x: u32, y: u32) -> TilePos {
+    TilePos{ x, y }
+}
+
+impl TilePos {
+    /// Returns None if pos is outside of the world
+    pub fn checked(pos: IVec2, world_size: UVec2) -> Option<TilePos> {
+        if    0 <= pos.x && pos.x < world_size.x as i32
+           && 0 <= pos.y && pos.y < world_size.y as i32 {
+            Some(tile_pos(pos.x as u32, pos.y as u32))
+        } else {
+            None
+        }
+    }
+
+    /// Neighbouring tile, or None if it's outside of the world
+    pub fn offset(self, by: IVec2, world_size: UVec2) -> Option<TilePos> {
+        TilePos::checked(self.as_ivec2() + by, world_size)
+    }
+
+    pub fn in_world(self, world_size: UVec2) -> bool {
+        self.x < world_size.x && self.y < world_size.y
+    }
+
+    pub fn as_uvec2(self) -> UVec2 { uvec2(self.x, self.y) }
+
+    pub fn as_ivec2(self) -> IVec2 { self.as_uvec2().as_ivec2() }
+}
+
+impl From<UVec2> for TilePos {
+    fn from(pos: UVec2) -> Self {
+        tile_pos(pos.x, pos.y)
+    }
+}
+
+impl From<TilePos> for UVec2 {
+    fn from(pos: TilePos) -> Self {
+        pos.as_uvec2()
+    }
+}
+
+struct Chunk<T> {
+    tiles: Vec<Option<T>>,
+    count: u32
+}
+
+/// Sparse map of tiles to T. Chunks are only allocated where something is stored, so the world can
+/// be as large as a u32 allows.
+pub struct TileMap<T> {
+    chunks: BTreeMap<(u32, u32), Chunk<T>>,
+    len: usize,
+    version: u64
+}
+
+impl<T> Default for TileMap<T> {
+    fn default() -> Self {
+        TileMap{ chunks: BTreeMap::new(), len: 0, version: 0 }
+    }
+}
+
+fn split(pos: TilePos) -> ((u32, u32), usize) {
+    let chunk = (pos.x / CHUNK_SIZE, pos.y / CHUNK_SIZE);
+    let idx = (pos.y % CHUNK_SIZE) * CHUNK_SIZE + (pos.x % CHUNK_SIZE);
+    (chunk, idx as usize)
+}
+
+impl<T> TileMap<T> {
+
+    pub fn get(&self, pos: TilePos) -> Option<&T> {
+        let (chunk, idx) = split(pos);
+        self.chunks.get(&chunk)?.tiles[idx].as_ref()
+    }
+
+    pub fn get_mut(&mut self, pos: TilePos) -> Option<&mut T> {
+        self.version += 1;
+        let (chunk, idx) = split(pos);
+        self.chunks.get_mut(&chunk)?.tiles[idx].as_mut()
+    }
+
+    pub fn contains_key(&self, pos: TilePos) -> bool {
+        self.get(pos).is_some()
+    }
+
+    /// Returns the previous value
+    pub fn insert(&mut self, pos: TilePos, value: T) -> Option<T> {
+        self.version += 1;
+        let (chunk, idx) = split(pos);
+        let c = self.chunks.entry(chunk).or_insert_with(|| Chunk{
+            tiles: std::iter::repeat_with(|| None).take(CHUNK_AREA).collect(),
+            count: 0
+        });
+        let prev = c.tiles[idx].replace(value);
+        if prev.is_none() {
+            c.count += 1;
+            self.len += 1;
+        }
+        prev
+    }
+
+    pub fn remove(&mut self, pos: TilePos) -> Option<T> {
+        let (chunk, idx) = split(pos);
+        let c = self.chunks.get_mut(&chunk)?;
+        let prev = c.tiles[idx].take()?;
+        self.version += 1;
+        c.count -= 1;
+        self.len -= 1;
+        if c.count == 0 {
+            self.chunks.remove(&chunk);
+        }
+        Some(prev)
+    }
+
+    pub fn clear(&mut self) {
+        self.version += 1;
+        self.chunks.clear();
+        self.len = 0;
+    }
+
+    pub fn len(&self) -> usize { self.len }
+
+    /// Goes up whenever anything might have changed, for caches to know when to rebuild
+    pub fn version(&self) -> u64 { self.version }
+
+    pub fn is_empty(&self) -> bool { self.len == 0 }
+
+    /// Iterate occupied tiles, chunk by chunk
+    pub fn iter(&self) -> impl Iterator<Item = (TilePos, &T)> + '_ {
+        self.chunks.iter().flat_map(|(&(cx, cy), c)| {
+            c.tiles.iter().enumerate().filter_map(move |(i, tile)| {
+                let i = i as u32;
+                let pos = tile_pos(cx * CHUNK_SIZE + i % CHUNK_SIZE, cy * CHUNK_SIZE + i / CHUNK_SIZE);
+                tile.as_ref().map(|v| (pos, v))
+            })
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Not;
+
+    use super::*;
+    use glam::ivec2;
+
+    #[test]
+    fn test_tile_map() {
+        let mut map: TileMap<u32> = Default::default();
+
+        // used to alias when keyed by u8
+        assert!(map.insert(tile_pos(3, 4), 1).is_none());
+        assert!(map.insert(tile_pos(259, 4), 2).is_none());
+        assert_eq!(map.get(tile_pos(3, 4)), Some(&1));
+        assert_eq!(map.get(tile_pos(259, 4)), Some(&2));
+        assert_eq!(map.len(), 2);
+
+        assert_eq!(map.insert(tile_pos(3, 4), 3), Some(1));
+        assert_eq!(map.len(), 2);
+
+        let all: Vec<(TilePos, u32)> = map.iter().map(|(p, v)| (p, *v)).collect();
+        assert_eq!(all, vec![(tile_pos(3, 4), 3), (tile_pos(259, 4), 2)]);
+
+        let version = map.version();
+        assert_eq!(map.remove(tile_pos(259, 4)), Some(2));
+        assert!(map.version() > version);
+        let version = map.version();
+        assert!(map.remove(tile_pos(259, 4)).is_none());
+        assert_eq!(map.version(), version);
+        assert!(map.contains_key(tile_pos(259, 4)).not());
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn test_tile_bounds() {
+        let world_size = uvec2(300, 25);
+        assert_eq!(TilePos::checked(ivec2(299, 24), world_size), Some(tile_pos(299, 24)));
+        assert!(TilePos::checked(ivec2(300, 0), world_size).is_none());
+        assert!(TilePos::checked(ivec2(-1, 0), world_size).is_none());
+        assert!(tile_pos(0, 0).offset(ivec2(0, -1), world_size).is_none());
+        assert!(tile_pos(0, 25).in_world(world_size).not());
+    }
+}
@@ -0,0 +1,104 @@
+use std::ops::Not;
+
+/// Simulation speeds that SlowDown and SpeedUp step through
+pub const SIM_SPEEDS: [f32; 4] = [0.5, 1.0, 2.0, 4.0];
+/// Index of 1x in SIM_SPEEDS
+pub const SPEED_NORMAL: usize = 1;
+/// Longest the simulation steps at once. Faster speeds take more steps a frame instead of longer
+/// ones, so bullets don't skip past drones and turrets don't miss shots.
+pub const SIM_STEP_MAX: f32 = 1.0 / 60.0;
+
+/// How fast the simulation runs compared to real time. The camera and UI don't care.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimeControl {
+    pub paused:     bool,
+    /// index into SIM_SPEEDS
+    pub speed:      usize
+}
+
+impl Default for TimeControl {
+    fn default() -> Self {
+        TimeControl{ paused: false, speed: SPEED_NORMAL }
+    }
+}
+
+impl TimeControl {
+
+    /// Simulated seconds per real second
+    pub fn scale(&self) -> f32 {
+        if self.paused { 0.0 } else { SIM_SPEEDS[self.speed] }
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = self.paused.not();
+    }
+
+    pub fn slower(&mut self) {
+        self.speed = self.speed.saturating_sub(1);
+    }
+
+    pub fn faster(&mut self) {
+        self.speed = (self.speed + 1).min(SIM_SPEEDS.len() - 1);
+    }
+
+    /// How many steps to simulate for a frame that took delta, and how long each is
+    pub fn steps(&self, delta: f32) -> (u32, f32) {
+        let sim_delta = delta * self.scale();
+        if sim_delta <= 0.0 {
+            return (0, 0.0);
+        }
+        let count = (sim_delta / SIM_STEP_MAX).ceil().max(1.0) as u32;
+        (count, sim_delta / count as f32)
+    }
+
+    /// For the HUD, empty at normal speed
+    pub fn label(&self) -> String {
+        if self.paused {
+            "PAUSED".to_owned()
+        } else if self.speed == SPEED_NORMAL {
+            String::new()
+        } else {
+            format!("{}x", SIM_SPEEDS[self.speed])
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time_steps() {
+        let mut time = TimeControl::default();
+        assert_eq!(time.steps(1.0 / 60.0), (1, 1.0 / 60.0));
+        assert_eq!(time.label(), "");
+
+        // faster speeds take more steps of the same size, and add up to the same thing
+        time.faster();
+        time.faster();
+        time.faster();
+        assert_eq!(time.speed, SIM_SPEEDS.len() - 1);
+        let (count, step) = time.steps(1.0 / 60.0);
+        assert_eq!(count, 4);
+        assert!((step * count as f32 - 4.0 / 60.0).abs() < 0.0001);
+        assert!(step <= SIM_STEP_MAX);
+        assert_eq!(time.label(), "4x");
+
+        // a slow frame gets split up too
+        time.slower();
+        time.slower();
+        let (count, step) = time.steps(0.1);
+        assert_eq!(count, 6);
+        assert!(step <= SIM_STEP_MAX + 0.0001);
+
+        time.slower();
+        time.slower();
+        assert_eq!(time.scale(), 0.5);
+        assert_eq!(time.steps(1.0 / 60.0).0, 1);
+
+        time.toggle_pause();
+        assert_eq!(time.steps(0.1), (0, 0.0));
+        assert_eq!(time.label(), "PAUSED");
+    }
+}
@@ -0,0 +1,181 @@
+use crate::input::{Gamepad, GamepadSource, PadButton, PadState};
+
+use std::fs::File;
+use std::io::Read;
+use std::sync::mpsc::{channel, Receiver};
+use std::time::Duration;
+
+/// Joystick devices tried in order, the first one that opens gets used
+const DEVICES: [&str; 4] = ["/dev/input/js0", "/dev/input/js1", "/dev/input/js2", "/dev/input/js3"];
+/// Wait between looking for a gamepad when there isn't one
+const RETRY: Duration = Duration::from_secs(2);
+
+const JS_EVENT_BUTTON:  u8 = 0x01;
+const JS_EVENT_AXIS:    u8 = 0x02;
+/// Set on the events sent right after opening, with where everything starts out
+const JS_EVENT_INIT:    u8 = 0x80;
+
+/// From the reader thread
+enum Message {
+    /// struct js_event: u32 time, i16 value, u8 type, u8 number
+    Event([u8; 8]),
+    Disconnected
+}
+
+/// Button numbers the way xpad and most Xbox-style pads report them
+fn button(number: u8) -> Option<PadButton> {
+    Some(match number {
+        0   => PadButton::South,
+        1   => PadButton::East,
+        2   => PadButton::West,
+        3   => PadButton::North,
+        4   => PadButton::LeftBumper,
+        5   => PadButton::RightBumper,
+        6   => PadButton::Select,
+        7   => PadButton::Start,
+        9   => PadButton::LeftStick,
+        10  => PadButton::RightStick,
+        _   => return None
+    })
+}
+
+fn set(state: &mut PadState, button: PadButton, down: bool) {
+    let bit = 1 << button as u32;
+    if down { state.buttons |= bit; } else { state.buttons &= !bit; }
+}
+
+/// Applies one js_event. Axes are numbered left stick x y, left trigger, right stick x y, right
+/// trigger, then the dpad x y.
+pub fn apply_event(state: &mut PadState, event: [u8; 8]) {
+    let value = (i16::from_ne_bytes([event[4], event[5]]) as f32 / i16::MAX as f32).clamp(-1.0, 1.0);
+    let number = event[7];
+    match event[6] & !JS_EVENT_INIT {
+        JS_EVENT_BUTTON => if let Some(b) = button(number) {
+            set(state, b, value != 0.0);
+        },
+        JS_EVENT_AXIS => match number {
+            0 => state.left_stick.x = value,
+            1 => state.left_stick.y = value,
+            // triggers rest at -1
+            2 => state.left_trigger = (value + 1.0) / 2.0,
+            3 => state.right_stick.x = value,
+            4 => state.right_stick.y = value,
+            5 => state.right_trigger = (value + 1.0) / 2.0,
+            6 => {
+                set(state, PadButton::DpadLeft, value < -0.5);
+                set(state, PadButton::DpadRight, value > 0.5);
+            },
+            7 => {
+                set(state, PadButton::DpadUp, value < -0.5);
+                set(state, PadButton::DpadDown, value > 0.5);
+            },
+            _ => {}
+        },
+        _ => {}
+    }
+}
+
+/// Reads a gamepad through Linux's joystick devices, which only need the kernel's joydev
+/// driver. A thread blocks on the device and hands events over, and picks the gamepad back up
+/// if it gets unplugged and plugged in again.
+pub struct JoydevGamepad {
+    messages:   Receiver<Message>,
+    state:      PadState,
+    connected:  bool
+}
+
+impl JoydevGamepad {
+    pub fn start() -> JoydevGamepad {
+        let (send, messages) = channel();
+        std::thread::spawn(move || loop {
+            let Some(mut file) = DEVICES.iter().find_map(|path| File::open(path).ok()) else {
+                std::thread::sleep(RETRY);
+                continue;
+            };
+            let mut event = [0; 8];
+            while file.read_exact(&mut event).is_ok() {
+                if send.send(Message::Event(event)).is_err() {
+                    return;
+                }
+            }
+            if send.send(Message::Disconnected).is_err() {
+                return;
+            }
+            std::thread::sleep(RETRY);
+        });
+        JoydevGamepad{ messages, state: Default::default(), connected: false }
+    }
+}
+
+impl GamepadSource for JoydevGamepad {
+    fn poll(&mut self, pad: &mut Gamepad) {
+        while let Ok(message) = self.messages.try_recv() {
+            match message {
+                Message::Event(event) => {
+                    self.connected = true;
+                    apply_event(&mut self.state, event);
+                },
+                Message::Disconnected => {
+                    self.connected = false;
+                    self.state = Default::default();
+                }
+            }
+        }
+        pad.apply(&self.state);
+        pad.connected = self.connected;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ops::Not;
+    use glam::vec2;
+
+    fn event(kind: u8, number: u8, value: i16) -> [u8; 8] {
+        let v = value.to_ne_bytes();
+        [0, 0, 0, 0, v[0], v[1], kind, number]
+    }
+
+    #[test]
+    fn test_joydev() {
+        let (send, messages) = channel();
+        let mut source = JoydevGamepad{ messages, state: Default::default(), connected: false };
+        let mut pad = Gamepad::default();
+        source.poll(&mut pad);
+        assert!(pad.connected.not());
+
+        // where things start out, then a press, a tilt, and a pulled trigger
+        for e in [
+            event(JS_EVENT_AXIS | JS_EVENT_INIT, 2, -32767),
+            event(JS_EVENT_AXIS | JS_EVENT_INIT, 5, -32767),
+            event(JS_EVENT_BUTTON, 0, 1),
+            event(JS_EVENT_AXIS, 1, -32768),
+            event(JS_EVENT_AXIS, 3, 16384),
+            event(JS_EVENT_AXIS, 5, 32767),
+            event(JS_EVENT_AXIS, 6, -32767),
+            event(JS_EVENT_BUTTON, 8, 1)
+        ] {
+            send.send(Message::Event(e)).unwrap();
+        }
+        source.poll(&mut pad);
+        assert!(pad.connected);
+        assert!(pad.is_down(PadButton::South));
+        assert!(pad.is_down(PadButton::DpadLeft) && pad.is_down(PadButton::DpadRight).not());
+        assert!(pad.is_down(PadButton::RightTrigger) && pad.is_down(PadButton::LeftTrigger).not());
+        assert_eq!(pad.left_stick, vec2(0.0, -1.0));
+        assert!((pad.right_stick.x - 0.5).abs() < 0.001);
+
+        send.send(Message::Event(event(JS_EVENT_BUTTON, 0, 0))).unwrap();
+        source.poll(&mut pad);
+        assert!(pad.is_down(PadButton::South).not());
+
+        // unplugged lets go of everything
+        send.send(Message::Disconnected).unwrap();
+        source.poll(&mut pad);
+        assert!(pad.connected.not());
+        assert_eq!(pad.down, 0);
+        assert_eq!(pad.left_stick, vec2(0.0, 0.0));
+    }
+}
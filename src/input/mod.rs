@@ -0,0 +1,801 @@
+#[cfg(target_os = "linux")]
+pub mod joydev;
+
+use crate::draw::mq;
+
+use std::fmt;
+use std::collections::VecDeque;
+use glam::{vec2, Vec2};
+use std::ops::Not;
+
+/// Crafting recipes that have their own binding. See craft_item_recipe and craft_machine_recipe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Recipe { Disassemble, Bullet, Clump, Turret, Conveyor, Filterveyor }
+
+impl Recipe {
+    /// Recipe number for craft_item_recipe or craft_machine_recipe
+    pub fn index(self) -> u32 {
+        match self {
+            Recipe::Disassemble => 0,
+            Recipe::Bullet      => 1,
+            Recipe::Clump       => 2,
+            Recipe::Turret      => 5,
+            Recipe::Conveyor    => 6,
+            Recipe::Filterveyor => 7
+        }
+    }
+}
+
+/// Something the player wants to do, instead of which key they pressed
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    WalkUp, WalkDown, WalkLeft, WalkRight,
+    CycleTool,
+    /// Pick up or put down whatever's under the cursor
+    Interact,
+    /// Click on things in the world. Same as Interact, but opens machine settings instead of
+    /// picking them up
+    Use,
+    /// Machine being held
+    Rotate,
+    Craft(Recipe),
+    Obfuscate,
+    /// Take back the last thing done with the Construct tool, see game::construct
+    Undo, Redo,
+    /// Press at two corners to copy the machines between them as a blueprint
+    Copy,
+    /// Start or stop placing the blueprint
+    Paste,
+    BuildGhosts,
+    SaveBlueprint, LoadBlueprint,
+    Shoot,
+    Reload,
+    ZoomIn, ZoomOut,
+    FreePan,
+    PanUp, PanDown, PanLeft, PanRight,
+    /// Hold to drag the camera around
+    GrabCamera,
+    Minimap,
+    ItemBook,
+    CloseMenus,
+    MuteMusic,
+    Settings,
+    /// Stops the simulation, building still works
+    Pause,
+    /// Step through sim speeds, see game::TimeControl
+    SlowDown, SpeedUp,
+    /// Creative mode menu, see game::Sandbox
+    Sandbox,
+    /// Type in commands, see game::run_command
+    Console,
+    /// Hitboxes, rail indices, id counts... see draw::DebugOverlay
+    DebugOverlay,
+    DumpAscii,
+    TuiLive,
+    Snapshot
+}
+
+/// When an action can happen. Actions can share a binding if they never happen at the same time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Context {
+    Always,
+    /// ToolMode::Construct
+    Construct,
+    /// ToolMode::Construct while dragging a machine
+    Holding,
+    /// ToolMode::Construct with nothing dragged
+    EmptyHanded,
+    /// ToolMode::GunPod
+    Gun
+}
+
+impl Context {
+    pub fn overlaps(self, other: Context) -> bool {
+        use Context::*;
+        match (self, other) {
+            (Always, _) | (_, Always)                       => true,
+            (Construct, Holding | EmptyHanded)              => true,
+            (Holding | EmptyHanded, Construct)              => true,
+            (a, b)                                          => a == b
+        }
+    }
+}
+
+impl Action {
+    pub const ALL: [Action; 46] = [
+        Action::WalkUp, Action::WalkDown, Action::WalkLeft, Action::WalkRight,
+        Action::CycleTool, Action::Interact, Action::Use, Action::Rotate,
+        Action::Craft(Recipe::Disassemble), Action::Craft(Recipe::Bullet), Action::Craft(Recipe::Clump),
+        Action::Craft(Recipe::Turret), Action::Craft(Recipe::Conveyor), Action::Craft(Recipe::Filterveyor),
+        Action::Obfuscate, Action::Undo, Action::Redo,
+        Action::Copy, Action::Paste, Action::BuildGhosts, Action::SaveBlueprint, Action::LoadBlueprint,
+        Action::Shoot, Action::Reload,
+        Action::ZoomIn, Action::ZoomOut, Action::FreePan,
+        Action::PanUp, Action::PanDown, Action::PanLeft, Action::PanRight, Action::GrabCamera,
+        Action::Minimap, Action::ItemBook, Action::CloseMenus, Action::MuteMusic, Action::Settings,
+        Action::Pause, Action::SlowDown, Action::SpeedUp, Action::Sandbox, Action::Console, Action::DebugOverlay,
+        Action::DumpAscii, Action::TuiLive, Action::Snapshot
+    ];
+
+    /// Name used in the bindings file
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::WalkUp                      => "walk_up",
+            Action::WalkDown                    => "walk_down",
+            Action::WalkLeft                    => "walk_left",
+            Action::WalkRight                   => "walk_right",
+            Action::CycleTool                   => "cycle_tool",
+            Action::Interact                    => "interact",
+            Action::Use                         => "use",
+            Action::Rotate                      => "rotate",
+            Action::Craft(Recipe::Disassemble)  => "craft_disassemble",
+            Action::Craft(Recipe::Bullet)       => "craft_bullet",
+            Action::Craft(Recipe::Clump)        => "craft_clump",
+            Action::Craft(Recipe::Turret)       => "craft_turret",
+            Action::Craft(Recipe::Conveyor)     => "craft_conveyor",
+            Action::Craft(Recipe::Filterveyor)  => "craft_filterveyor",
+            Action::Obfuscate                   => "obfuscate",
+            Action::Undo                        => "undo",
+            Action::Redo                        => "redo",
+            Action::Copy                        => "copy",
+            Action::Paste                       => "paste",
+            Action::BuildGhosts                 => "build_ghosts",
+            Action::SaveBlueprint               => "save_blueprint",
+            Action::LoadBlueprint               => "load_blueprint",
+            Action::Shoot                       => "shoot",
+            Action::Reload                      => "reload",
+            Action::ZoomIn                      => "zoom_in",
+            Action::ZoomOut                     => "zoom_out",
+            Action::FreePan                     => "free_pan",
+            Action::PanUp                       => "pan_up",
+            Action::PanDown                     => "pan_down",
+            Action::PanLeft                     => "pan_left",
+            Action::PanRight                    => "pan_right",
+            Action::GrabCamera                  => "grab_camera",
+            Action::Minimap                     => "minimap",
+            Action::ItemBook                    => "item_book",
+            Action::CloseMenus                  => "close_menus",
+            Action::MuteMusic                   => "mute_music",
+            Action::Settings                    => "settings",
+            Action::Pause                       => "pause",
+            Action::SlowDown                    => "slow_down",
+            Action::SpeedUp                     => "speed_up",
+            Action::Sandbox                     => "sandbox",
+            Action::Console                     => "console",
+            Action::DebugOverlay                => "debug_overlay",
+            Action::DumpAscii                   => "dump_ascii",
+            Action::TuiLive                     => "tui_live",
+            Action::Snapshot                    => "snapshot"
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.iter().copied().find(|a| a.name() == name)
+    }
+
+    pub fn context(self) -> Context {
+        match self {
+            Action::Interact | Action::Use                      => Context::Construct,
+            Action::Undo | Action::Redo                         => Context::Construct,
+            Action::Rotate                                      => Context::Holding,
+            Action::Craft(_) | Action::Obfuscate                => Context::EmptyHanded,
+            Action::Copy | Action::Paste | Action::BuildGhosts  => Context::EmptyHanded,
+            Action::Shoot | Action::Reload                      => Context::Gun,
+            _                                                   => Context::Always
+        }
+    }
+}
+
+/// Gamepad buttons, named by where they are instead of what's printed on them
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PadButton {
+    South, East, West, North,
+    LeftBumper, RightBumper, LeftTrigger, RightTrigger,
+    Select, Start,
+    DpadUp, DpadDown, DpadLeft, DpadRight,
+    LeftStick, RightStick
+}
+
+const PAD_NAMES: [(&str, PadButton); 16] = [
+    ("south",           PadButton::South),
+    ("east",            PadButton::East),
+    ("west",            PadButton::West),
+    ("north",           PadButton::North),
+    ("left_bumper",     PadButton::LeftBumper),
+    ("right_bumper",    PadButton::RightBumper),
+    ("left_trigger",    PadButton::LeftTrigger),
+    ("right_trigger",   PadButton::RightTrigger),
+    ("select",          PadButton::Select),
+    ("start",           PadButton::Start),
+    ("dpad_up",         PadButton::DpadUp),
+    ("dpad_down",       PadButton::DpadDown),
+    ("dpad_left",       PadButton::DpadLeft),
+    ("dpad_right",      PadButton::DpadRight),
+    ("left_stick",      PadButton::LeftStick),
+    ("right_stick",     PadButton::RightStick)
+];
+
+const MOUSE_NAMES: [(&str, mq::MouseButton); 3] = [
+    ("left",    mq::MouseButton::Left),
+    ("right",   mq::MouseButton::Right),
+    ("middle",  mq::MouseButton::Middle)
+];
+
+const KEY_NAMES: [(&str, mq::KeyCode); 78] = [
+    ("a", mq::KeyCode::A), ("b", mq::KeyCode::B), ("c", mq::KeyCode::C), ("d", mq::KeyCode::D),
+    ("e", mq::KeyCode::E), ("f", mq::KeyCode::F), ("g", mq::KeyCode::G), ("h", mq::KeyCode::H),
+    ("i", mq::KeyCode::I), ("j", mq::KeyCode::J), ("k", mq::KeyCode::K), ("l", mq::KeyCode::L),
+    ("m", mq::KeyCode::M), ("n", mq::KeyCode::N), ("o", mq::KeyCode::O), ("p", mq::KeyCode::P),
+    ("q", mq::KeyCode::Q), ("r", mq::KeyCode::R), ("s", mq::KeyCode::S), ("t", mq::KeyCode::T),
+    ("u", mq::KeyCode::U), ("v", mq::KeyCode::V), ("w", mq::KeyCode::W), ("x", mq::KeyCode::X),
+    ("y", mq::KeyCode::Y), ("z", mq::KeyCode::Z),
+    ("0", mq::KeyCode::Key0), ("1", mq::KeyCode::Key1), ("2", mq::KeyCode::Key2), ("3", mq::KeyCode::Key3),
+    ("4", mq::KeyCode::Key4), ("5", mq::KeyCode::Key5), ("6", mq::KeyCode::Key6), ("7", mq::KeyCode::Key7),
+    ("8", mq::KeyCode::Key8), ("9", mq::KeyCode::Key9),
+    ("f1", mq::KeyCode::F1), ("f2", mq::KeyCode::F2), ("f3", mq::KeyCode::F3), ("f4", mq::KeyCode::F4),
+    ("f5", mq::KeyCode::F5), ("f6", mq::KeyCode::F6), ("f7", mq::KeyCode::F7), ("f8", mq::KeyCode::F8),
+    ("f9", mq::KeyCode::F9), ("f10", mq::KeyCode::F10), ("f11", mq::KeyCode::F11), ("f12", mq::KeyCode::F12),
+    ("up", mq::KeyCode::Up), ("down", mq::KeyCode::Down), ("left", mq::KeyCode::Left), ("right", mq::KeyCode::Right),
+    ("space", mq::KeyCode::Space), ("enter", mq::KeyCode::Enter), ("escape", mq::KeyCode::Escape),
+    ("tab", mq::KeyCode::Tab), ("backspace", mq::KeyCode::Backspace), ("delete", mq::KeyCode::Delete),
+    ("insert", mq::KeyCode::Insert), ("home", mq::KeyCode::Home), ("end", mq::KeyCode::End),
+    ("page_up", mq::KeyCode::PageUp), ("page_down", mq::KeyCode::PageDown),
+    ("minus", mq::KeyCode::Minus), ("equal", mq::KeyCode::Equal), ("comma", mq::KeyCode::Comma),
+    ("period", mq::KeyCode::Period), ("slash", mq::KeyCode::Slash), ("semicolon", mq::KeyCode::Semicolon),
+    ("apostrophe", mq::KeyCode::Apostrophe), ("grave", mq::KeyCode::GraveAccent),
+    ("left_bracket", mq::KeyCode::LeftBracket), ("right_bracket", mq::KeyCode::RightBracket),
+    ("backslash", mq::KeyCode::Backslash),
+    ("left_shift", mq::KeyCode::LeftShift), ("right_shift", mq::KeyCode::RightShift),
+    ("left_ctrl", mq::KeyCode::LeftControl), ("right_ctrl", mq::KeyCode::RightControl)
+];
+
+/// A button that can trigger an action. Written as key:<name>, mouse:<name>, or pad:<name> in
+/// the bindings file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Binding {
+    Key(mq::KeyCode),
+    Mouse(mq::MouseButton),
+    Pad(PadButton)
+}
+
+impl Binding {
+    pub fn parse(text: &str) -> Option<Binding> {
+        let (kind, name) = text.split_once(':')?;
+        let name = name.to_lowercase();
+        match kind {
+            "key"   => KEY_NAMES.iter().find(|(n, _)| *n == name).map(|&(_, k)| Binding::Key(k)),
+            "mouse" => MOUSE_NAMES.iter().find(|(n, _)| *n == name).map(|&(_, m)| Binding::Mouse(m)),
+            "pad"   => PAD_NAMES.iter().find(|(n, _)| *n == name).map(|&(_, p)| Binding::Pad(p)),
+            _       => None
+        }
+    }
+
+    /// Short name to show in hints, like "R" or "Left Click"
+    pub fn label(&self) -> String {
+        match self {
+            Binding::Key(key)   => KEY_NAMES.iter().find(|(_, k)| k == key).map_or("?", |(n, _)| n).to_uppercase(),
+            Binding::Mouse(m)   => format!("{} Click", match m { mq::MouseButton::Left => "Left", mq::MouseButton::Right => "Right", _ => "Middle" }),
+            Binding::Pad(pad)   => format!("Pad {}", PAD_NAMES.iter().find(|(_, p)| p == pad).map_or("?", |(n, _)| n))
+        }
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Binding::Key(key)   => write!(f, "key:{}", KEY_NAMES.iter().find(|(_, k)| k == key).map_or("?", |(n, _)| n)),
+            Binding::Mouse(m)   => write!(f, "mouse:{}", MOUSE_NAMES.iter().find(|(_, b)| b == m).map_or("?", |(n, _)| n)),
+            Binding::Pad(pad)   => write!(f, "pad:{}", PAD_NAMES.iter().find(|(_, p)| p == pad).map_or("?", |(n, _)| n))
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum BindingsError {
+    Io(String, std::io::Error),
+    Parse{ line: usize, msg: String }
+}
+
+impl fmt::Display for BindingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BindingsError::Io(path, err)        => write!(f, "can't read {}: {}", path, err),
+            BindingsError::Parse{ line, msg }   => write!(f, "line {}: {}", line, msg)
+        }
+    }
+}
+
+impl std::error::Error for BindingsError {}
+
+/// Two actions that can happen at the same time, but share a binding
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Conflict {
+    pub binding:    Binding,
+    pub a:          Action,
+    pub b:          Action
+}
+
+/// Which buttons do which actions. An action can have any number of bindings. See
+/// tf/custom/bindings.txt for the file format.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bindings {
+    pub list: Vec<(Action, Binding)>
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        use mq::KeyCode as K;
+        use mq::MouseButton as M;
+        let key = Binding::Key;
+        let pad = Binding::Pad;
+        Bindings{ list: vec![
+            (Action::WalkUp,                        key(K::W)),
+            (Action::WalkDown,                      key(K::S)),
+            (Action::WalkLeft,                      key(K::A)),
+            (Action::WalkRight,                     key(K::D)),
+            (Action::CycleTool,                     key(K::Q)),
+            (Action::CycleTool,                     pad(PadButton::North)),
+            (Action::Interact,                      key(K::E)),
+            (Action::Interact,                      pad(PadButton::South)),
+            (Action::Interact,                      pad(PadButton::LeftTrigger)),
+            (Action::Use,                           Binding::Mouse(M::Left)),
+            (Action::Use,                           pad(PadButton::RightTrigger)),
+            (Action::Rotate,                        key(K::R)),
+            (Action::Rotate,                        pad(PadButton::West)),
+            (Action::Craft(Recipe::Disassemble),    key(K::Key1)),
+            (Action::Craft(Recipe::Bullet),         key(K::Key2)),
+            (Action::Craft(Recipe::Clump),          key(K::Key3)),
+            (Action::Craft(Recipe::Turret),         key(K::Key5)),
+            (Action::Craft(Recipe::Conveyor),       key(K::Key6)),
+            (Action::Craft(Recipe::Filterveyor),    key(K::Key7)),
+            (Action::Obfuscate,                     key(K::R)),
+            (Action::Undo,                          key(K::U)),
+            (Action::Redo,                          key(K::I)),
+            (Action::Copy,                          key(K::C)),
+            (Action::Paste,                         key(K::V)),
+            (Action::BuildGhosts,                   key(K::G)),
+            (Action::SaveBlueprint,                 key(K::F5)),
+            (Action::LoadBlueprint,                 key(K::F9)),
+            (Action::Shoot,                         Binding::Mouse(M::Left)),
+            (Action::Shoot,                         pad(PadButton::RightTrigger)),
+            (Action::Reload,                        key(K::R)),
+            (Action::Reload,                        pad(PadButton::West)),
+            (Action::ZoomIn,                        key(K::Equal)),
+            (Action::ZoomIn,                        pad(PadButton::DpadUp)),
+            (Action::ZoomOut,                       key(K::Minus)),
+            (Action::ZoomOut,                       pad(PadButton::DpadDown)),
+            (Action::FreePan,                       key(K::F)),
+            (Action::PanUp,                         key(K::Up)),
+            (Action::PanDown,                       key(K::Down)),
+            (Action::PanLeft,                       key(K::Left)),
+            (Action::PanRight,                      key(K::Right)),
+            (Action::GrabCamera,                    Binding::Mouse(M::Middle)),
+            (Action::Minimap,                       key(K::M)),
+            (Action::Minimap,                       pad(PadButton::Select)),
+            (Action::ItemBook,                      key(K::B)),
+            (Action::CloseMenus,                    key(K::Escape)),
+            (Action::CloseMenus,                    pad(PadButton::East)),
+            (Action::MuteMusic,                     key(K::N)),
+            (Action::Settings,                      key(K::O)),
+            (Action::Settings,                      pad(PadButton::Start)),
+            (Action::Pause,                         key(K::P)),
+            (Action::Pause,                         pad(PadButton::DpadRight)),
+            (Action::SlowDown,                      key(K::Comma)),
+            (Action::SlowDown,                      pad(PadButton::LeftBumper)),
+            (Action::SpeedUp,                       key(K::Period)),
+            (Action::SpeedUp,                       pad(PadButton::RightBumper)),
+            (Action::Sandbox,                       key(K::F8)),
+            (Action::Console,                       key(K::GraveAccent)),
+            (Action::DebugOverlay,                  key(K::F3)),
+            (Action::DumpAscii,                     key(K::F10)),
+            (Action::TuiLive,                       key(K::F11)),
+            (Action::Snapshot,                      key(K::F12))
+        ]}
+    }
+}
+
+impl Bindings {
+
+    pub fn get(&self, action: Action) -> impl Iterator<Item = Binding> + '_ {
+        self.list.iter().filter(move |(a, _)| *a == action).map(|&(_, b)| b)
+    }
+
+    /// Replaces all bindings of an action
+    pub fn set(&mut self, action: Action, bindings: &[Binding]) {
+        let at = self.list.iter().position(|(a, _)| *a == action).unwrap_or(self.list.len());
+        self.list.retain(|(a, _)| *a != action);
+        let at = at.min(self.list.len());
+        self.list.splice(at..at, bindings.iter().map(|&b| (action, b)));
+    }
+
+    /// Replaces the key bindings of an action with key, keeping its mouse and pad bindings.
+    /// Returns false for keys that can't be written to a bindings file.
+    pub fn set_key(&mut self, action: Action, key: mq::KeyCode) -> bool {
+        if KEY_NAMES.iter().any(|&(_, k)| k == key).not() {
+            return false;
+        }
+        let list: Vec<Binding> = std::iter::once(Binding::Key(key))
+            .chain(self.get(action).filter(|b| matches!(b, Binding::Key(_)).not()))
+            .collect();
+        self.set(action, &list);
+        true
+    }
+
+    /// Starts with the defaults. Every action in the file replaces all of its default bindings.
+    pub fn parse(text: &str) -> Result<Bindings, BindingsError> {
+        let mut bindings = Bindings::default();
+
+        for (i, line) in text.lines().enumerate() {
+            let line_no = i + 1;
+            let err = |msg: String| BindingsError::Parse{ line: line_no, msg };
+
+            let line = line.split('#').next().unwrap().trim();
+            let mut words = line.split_whitespace();
+            let Some(name) = words.next() else { continue };
+
+            let action = Action::from_name(name).ok_or_else(|| err(format!("no action named '{}'", name)))?;
+            let list = words.map(|w| Binding::parse(w).ok_or_else(|| err(format!("'{}' isn't a binding, expected key:<name>, mouse:<name>, or pad:<name>", w))))
+                .collect::<Result<Vec<Binding>, BindingsError>>()?;
+            bindings.set(action, &list);
+        }
+        Ok(bindings)
+    }
+
+    pub fn load(path: &str) -> Result<Bindings, BindingsError> {
+        let text = std::fs::read_to_string(path).map_err(|e| BindingsError::Io(path.to_owned(), e))?;
+        Bindings::parse(&text)
+    }
+
+    /// Same format that parse reads, one line for every action
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        for action in Action::ALL {
+            out.push_str(action.name());
+            for binding in self.get(action) {
+                out.push(' ');
+                out.push_str(&binding.to_string());
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Bindings shared by actions that can happen at the same time
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        let mut out = Vec::new();
+        for (i, &(a, binding)) in self.list.iter().enumerate() {
+            for &(b, other) in &self.list[i + 1..] {
+                if binding == other && a != b && a.context().overlaps(b.context()) {
+                    out.push(Conflict{ binding, a, b });
+                }
+            }
+        }
+        out
+    }
+
+    /// First binding of an action for hints, like "[R]"
+    pub fn hint(&self, action: Action) -> String {
+        match self.get(action).next() {
+            Some(binding)   => format!("[{}]", binding.label()),
+            None            => "[unbound]".to_owned()
+        }
+    }
+}
+
+/// Sticks further in than this count as centered
+pub const STICK_DEADZONE: f32 = 0.2;
+/// Analog triggers count as pressed past this
+pub const TRIGGER_THRESHOLD: f32 = 0.5;
+/// Screen pixels per second that the virtual cursor moves at full tilt
+pub const CURSOR_SPEED: f32 = 900.0;
+/// Screen pixels from the player to the cursor while aiming with the right stick
+pub const AIM_RADIUS: f32 = 200.0;
+
+/// Everything a gamepad reports for one frame
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PadState {
+    /// bit per PadButton. Triggers get set from their analog value
+    pub buttons:        u32,
+    /// -1 to 1, +y is down
+    pub left_stick:     Vec2,
+    pub right_stick:    Vec2,
+    /// 0 to 1
+    pub left_trigger:   f32,
+    pub right_trigger:  f32
+}
+
+impl PadState {
+    pub fn with(self, button: PadButton) -> PadState {
+        PadState{ buttons: self.buttons | 1 << button as u32, ..self }
+    }
+}
+
+/// Gamepad state for this frame and buttons from last frame. Filled in by a GamepadSource.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Gamepad {
+    pub connected:  bool,
+    /// bit per PadButton, this frame and last frame
+    pub down:       u32,
+    pub prev:       u32,
+    pub left_stick:     Vec2,
+    pub right_stick:    Vec2
+}
+
+impl Gamepad {
+    pub fn set(&mut self, button: PadButton, down: bool) {
+        let bit = 1 << button as u32;
+        if down { self.down |= bit; } else { self.down &= !bit; }
+    }
+
+    pub fn is_down(&self, button: PadButton) -> bool {
+        self.down & (1 << button as u32) != 0
+    }
+
+    pub fn was_down(&self, button: PadButton) -> bool {
+        self.prev & (1 << button as u32) != 0
+    }
+
+    pub fn apply(&mut self, state: &PadState) {
+        self.connected = true;
+        self.down = state.buttons;
+        self.set(PadButton::LeftTrigger, state.left_trigger > TRIGGER_THRESHOLD || state.buttons & 1 << PadButton::LeftTrigger as u32 != 0);
+        self.set(PadButton::RightTrigger, state.right_trigger > TRIGGER_THRESHOLD || state.buttons & 1 << PadButton::RightTrigger as u32 != 0);
+        self.left_stick = state.left_stick;
+        self.right_stick = state.right_stick;
+    }
+
+    pub fn left(&self) -> Vec2 { deadzone(self.left_stick) }
+    pub fn right(&self) -> Vec2 { deadzone(self.right_stick) }
+}
+
+/// Zero inside STICK_DEADZONE, then scaled so the edge of the deadzone is 0 and full tilt is 1
+pub fn deadzone(stick: Vec2) -> Vec2 {
+    let length = stick.length();
+    if length <= STICK_DEADZONE {
+        Vec2::ZERO
+    } else {
+        stick / length * ((length - STICK_DEADZONE) / (1.0 - STICK_DEADZONE)).min(1.0)
+    }
+}
+
+/// Where gamepad state comes from. macroquad can't read gamepads, so something else has to.
+pub trait GamepadSource {
+    /// Called once at the start of every frame
+    fn poll(&mut self, pad: &mut Gamepad);
+}
+
+/// Reads real gamepads where there's a way to, see joydev
+#[cfg(target_os = "linux")]
+pub fn platform_gamepad() -> Option<Box<dyn GamepadSource>> {
+    Some(Box::new(joydev::JoydevGamepad::start()))
+}
+
+/// Nothing reads gamepads here yet
+#[cfg(not(target_os = "linux"))]
+pub fn platform_gamepad() -> Option<Box<dyn GamepadSource>> {
+    None
+}
+
+/// Plays back a list of states, one per frame, then stays on the last one
+#[derive(Clone, Debug, Default)]
+pub struct SimulatedGamepad {
+    pub frames:     VecDeque<PadState>,
+    pub current:    PadState
+}
+
+impl SimulatedGamepad {
+    pub fn new(frames: impl IntoIterator<Item = PadState>) -> SimulatedGamepad {
+        SimulatedGamepad{ frames: frames.into_iter().collect(), current: Default::default() }
+    }
+}
+
+impl GamepadSource for SimulatedGamepad {
+    fn poll(&mut self, pad: &mut Gamepad) {
+        if let Some(next) = self.frames.pop_front() {
+            self.current = next;
+        }
+        pad.apply(&self.current);
+    }
+}
+
+/// Action state for this frame, from the keyboard, mouse, and gamepad
+#[derive(Clone, Debug, Default)]
+pub struct Input {
+    pub bindings:   Bindings,
+    pub gamepad:    Gamepad,
+    /// Screen position that the game points at. Follows the mouse, or gets moved around by the
+    /// right stick
+    pub cursor:     Vec2,
+    /// Gamepad was used more recently than the mouse
+    pub using_pad:  bool,
+    /// Keys are going into a text box, so only Console's key bindings do anything
+    pub typing:     bool,
+    /// The settings menu is waiting for a key to bind, so no key bindings do anything
+    pub rebinding:  bool,
+    last_mouse:     Vec2
+}
+
+impl Input {
+
+    pub fn new(bindings: Bindings) -> Input {
+        Input{ bindings, ..Default::default() }
+    }
+
+    fn binding_down(&self, binding: Binding) -> bool {
+        match binding {
+            Binding::Key(key)   => mq::is_key_down(key),
+            Binding::Mouse(m)   => mq::is_mouse_button_down(m),
+            Binding::Pad(pad)   => self.gamepad.is_down(pad)
+        }
+    }
+
+    fn binding_pressed(&self, binding: Binding) -> bool {
+        match binding {
+            Binding::Key(key)   => mq::is_key_pressed(key),
+            Binding::Mouse(m)   => mq::is_mouse_button_pressed(m),
+            Binding::Pad(pad)   => self.gamepad.is_down(pad) && self.gamepad.was_down(pad).not()
+        }
+    }
+
+    fn binding_released(&self, binding: Binding) -> bool {
+        match binding {
+            Binding::Key(key)   => mq::is_key_released(key),
+            Binding::Mouse(m)   => mq::is_mouse_button_released(m),
+            Binding::Pad(pad)   => self.gamepad.is_down(pad).not() && self.gamepad.was_down(pad)
+        }
+    }
+
+    /// Bindings of action that aren't switched off by typing or rebinding
+    fn live(&self, action: Action) -> impl Iterator<Item = Binding> + '_ {
+        let no_keys = self.rebinding || (self.typing && action != Action::Console);
+        self.bindings.get(action).filter(move |b| (no_keys && matches!(b, Binding::Key(_))).not())
+    }
+
+    pub fn down(&self, action: Action) -> bool {
+        self.live(action).any(|b| self.binding_down(b))
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        self.live(action).any(|b| self.binding_pressed(b))
+    }
+
+    pub fn released(&self, action: Action) -> bool {
+        self.live(action).any(|b| self.binding_released(b))
+    }
+
+    /// -1, 0, or 1
+    pub fn axis(&self, negative: Action, positive: Action) -> f32 {
+        (self.down(positive) as i32 - self.down(negative) as i32) as f32
+    }
+
+    /// Keyboard if anything's held, otherwise the left stick
+    pub fn walk(&self) -> Vec2 {
+        let keys = vec2(self.axis(Action::WalkLeft, Action::WalkRight), self.axis(Action::WalkUp, Action::WalkDown));
+        if keys != Vec2::ZERO { keys } else { self.gamepad.left() }
+    }
+
+    /// Moves the cursor with whichever of the mouse or right stick moved last. With aim_from
+    /// (the player on screen), the right stick points the cursor around it like a twin-stick
+    /// shooter. Otherwise it pushes the cursor around the screen.
+    pub fn update_cursor(&mut self, mouse: Vec2, screen_size: Vec2, aim_from: Option<Vec2>, delta: f32) {
+        if mouse != self.last_mouse {
+            self.last_mouse = mouse;
+            self.using_pad = false;
+        }
+        let stick = self.gamepad.right();
+        if stick != Vec2::ZERO {
+            self.using_pad = true;
+        }
+
+        if self.using_pad.not() {
+            self.cursor = mouse;
+            return;
+        }
+        self.cursor = match aim_from {
+            Some(from) if stick != Vec2::ZERO   => from + stick.normalize() * AIM_RADIUS,
+            _                                   => self.cursor + stick * CURSOR_SPEED * delta
+        }.clamp(Vec2::ZERO, screen_size);
+    }
+
+    /// Call once at the end of every frame
+    pub fn end_frame(&mut self) {
+        self.gamepad.prev = self.gamepad.down;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bindings_parse() {
+        let defaults = Bindings::default();
+        assert!(defaults.conflicts().is_empty());
+
+        // everything survives a round trip
+        assert_eq!(Bindings::parse(&defaults.to_text()).unwrap(), defaults);
+
+        // azerty
+        let bindings = Bindings::parse("walk_up key:z\nwalk_left key:q   # comment\ncycle_tool key:a pad:north\n").unwrap();
+        assert_eq!(bindings.get(Action::WalkUp).collect::<Vec<_>>(), [Binding::Key(mq::KeyCode::Z)]);
+        assert_eq!(bindings.get(Action::CycleTool).collect::<Vec<_>>(), [Binding::Key(mq::KeyCode::A), Binding::Pad(PadButton::North)]);
+        assert_eq!(bindings.hint(Action::WalkLeft), "[Q]");
+        assert!(bindings.conflicts().is_empty());
+
+        // R is shared by things that never happen at the same time, but not with walking
+        let bindings = Bindings::parse("walk_right key:r").unwrap();
+        let conflicts = bindings.conflicts();
+        assert_eq!(conflicts.len(), 3);
+        assert!(conflicts.iter().all(|c| c.binding == Binding::Key(mq::KeyCode::R) && (c.a == Action::WalkRight || c.b == Action::WalkRight)));
+
+        let unbound = Bindings::parse("snapshot").unwrap();
+        assert_eq!(unbound.hint(Action::Snapshot), "[unbound]");
+
+        assert!(matches!(Bindings::parse("\n\njump key:space"), Err(BindingsError::Parse{ line: 3, .. })));
+        assert!(matches!(Bindings::parse("shoot key:nope"), Err(BindingsError::Parse{ line: 1, .. })));
+    }
+
+    #[test]
+    fn test_bindings_custom() {
+        // the shipped file is the same as the defaults, so they don't drift apart
+        assert_eq!(Bindings::load("tf/custom/bindings.txt").unwrap(), Bindings::default());
+    }
+
+    #[test]
+    fn test_gamepad_edges() {
+        let mut pad = Gamepad::default();
+        pad.set(PadButton::North, true);
+        assert!(pad.is_down(PadButton::North) && pad.was_down(PadButton::North).not());
+        pad.prev = pad.down;
+        pad.set(PadButton::North, false);
+        assert!(pad.is_down(PadButton::North).not() && pad.was_down(PadButton::North));
+        assert!(pad.is_down(PadButton::South).not());
+    }
+
+    #[test]
+    fn test_simulated_twin_stick() {
+        let screen = vec2(800.0, 600.0);
+        let mouse = vec2(400.0, 300.0);
+        let mut input = Input::default();
+        let mut source = SimulatedGamepad::new([
+            PadState{ left_stick: vec2(0.1, 0.0), right_stick: vec2(1.0, 0.0), ..Default::default() },
+            PadState{ left_stick: vec2(0.0, 1.0), right_stick: vec2(0.0, -1.0), right_trigger: 0.9, ..Default::default() },
+            PadState::default()
+        ]);
+        input.update_cursor(mouse, screen, None, 0.0);
+        assert_eq!(input.cursor, mouse);
+
+        // building: the right stick pushes the cursor, small stick wobbles don't walk
+        source.poll(&mut input.gamepad);
+        input.update_cursor(mouse, screen, None, 0.1);
+        assert!(input.using_pad);
+        assert_eq!(input.cursor, mouse + vec2(0.1 * CURSOR_SPEED, 0.0));
+        assert_eq!(input.gamepad.left(), Vec2::ZERO);
+        input.end_frame();
+
+        // aiming: the cursor goes around the player, and the trigger pulls
+        source.poll(&mut input.gamepad);
+        input.update_cursor(mouse, screen, Some(vec2(100.0, 400.0)), 0.1);
+        assert_eq!(input.cursor, vec2(100.0, 400.0 - AIM_RADIUS));
+        assert_eq!(input.gamepad.left(), vec2(0.0, 1.0));
+        assert!(input.gamepad.is_down(PadButton::RightTrigger) && input.gamepad.was_down(PadButton::RightTrigger).not());
+        assert!(input.bindings.get(Action::Shoot).any(|b| b == Binding::Pad(PadButton::RightTrigger)));
+        input.end_frame();
+
+        // typing leaves Console's keys alone, rebinding switches off every key but not the pad
+        input.typing = true;
+        assert!(input.live(Action::Console).any(|b| matches!(b, Binding::Key(_))));
+        input.rebinding = true;
+        assert!(input.live(Action::Console).any(|b| matches!(b, Binding::Key(_))).not());
+        assert!(input.live(Action::Interact).any(|b| matches!(b, Binding::Pad(_))));
+        (input.typing, input.rebinding) = (false, false);
+
+        // moving the mouse takes over again
+        source.poll(&mut input.gamepad);
+        input.update_cursor(mouse + 5.0, screen, None, 0.1);
+        assert!(input.using_pad.not());
+        assert_eq!(input.cursor, mouse + 5.0);
+        assert!(input.gamepad.is_down(PadButton::RightTrigger).not() && input.gamepad.was_down(PadButton::RightTrigger));
+    }
+}
@@ -116,6 +116,19 @@ impl<T: Into<usize> + From<usize> + Copy> IdReg<T> {
         self.data.resize(capacity / 64 + (capacity % 64 != 0) as usize, ! 0x0u64 );
     }
 
+    /// Takes a specific id, for putting back one that was removed. False if it's already taken.
+    pub fn create_at(&mut self, id: T) -> bool {
+        let pos: usize = id.into();
+        if pos / 64 >= self.data.len() {
+            self.resize(pos + 1);
+        }
+        if self.exists(id) {
+            return false;
+        }
+        bitvec_clear(&mut self.data, pos);
+        true
+    }
+
     pub fn exists(&self, id: T) -> bool {
         let pos: usize = id.into();
         return ! bitvec_test(&self.data, pos);
@@ -2,3 +2,7 @@ pub mod lgrn;
 pub mod game;
 pub mod draw;
 
+pub mod input;
+pub mod audio;
+pub mod assets;
+pub mod settings;
@@ -1,4 +1,3 @@
-use std::cmp::Ordering;
 use std::ops::Not;
 use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -7,6 +6,10 @@ use obfuscation::draw::TileThing;
 use obfuscation::game::GameMain;
 use obfuscation::game::*;
 use obfuscation::draw;
+use obfuscation::audio::{Audio, STEMS, synth_stem};
+use obfuscation::settings::{Settings, SettingsError};
+use obfuscation::assets::{AssetData, AssetKind, Manifest, ASSET_ROOT};
+use obfuscation::input::{self, Action, Bindings, GamepadSource, Input, Recipe};
 
 use glam::{Vec2, vec2, uvec2, ivec2};
 
@@ -22,51 +25,74 @@ pub mod mq
 
 
 
-fn repitch(data: &mut [u8], sample_rate_original: u32, rate_shift: f32)
-{
-    let sample_rate         = ((sample_rate_original as f32) * rate_shift) as u32;
-    let sample_rate_bytes   = sample_rate    .to_le_bytes();
-    let data_rate_bytes     = (sample_rate*2).to_le_bytes();
-
-    data[24] = sample_rate_bytes[0];
-    data[25] = sample_rate_bytes[1];
-    data[26] = sample_rate_bytes[2];
-    data[27] = sample_rate_bytes[3];
-    data[28] = data_rate_bytes[0];
-    data[29] = data_rate_bytes[1];
-    data[30] = data_rate_bytes[2];
-    data[31] = data_rate_bytes[3];
+/// Keys, mouse buttons, and gamepad buttons for each action. See input::Bindings
+const BINDINGS_PATH: &str = "tf/custom/bindings.txt";
+/// Written by the settings menu, see settings::Settings
+const SETTINGS_PATH: &str = "tf/custom/settings.txt";
+/// Where SaveBlueprint and LoadBlueprint keep the clipboard, see game::Blueprint
+const BLUEPRINT_PATH: &str = "tf/custom/blueprint.txt";
+
+/// Defaults if the file isn't there, or after saying why if it's broken
+fn load_settings(base: &Bindings) -> Settings {
+    match Settings::load(SETTINGS_PATH, base) {
+        Ok(settings) => settings,
+        Err(SettingsError::Io(..)) => Settings::with_bindings(base.clone()),
+        Err(err) => {
+            println!("using default settings, {}: {}", SETTINGS_PATH, err);
+            Settings::with_bindings(base.clone())
+        }
+    }
 }
 
-#[macroquad::main("obfuscated bird thing")]
+fn window_conf() -> mq::Conf {
+    load_settings(&Bindings::default()).window_conf("obfuscated bird thing")
+}
+
+#[macroquad::main(window_conf)]
 async fn main() {
 
     //mq::srand(SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs());
 
-    let mut data = mq::load_file("tf/custom/step.wav").await.unwrap();
+    let asset_root = std::env::var("OBFUSCATION_ASSETS").unwrap_or_else(|_| ASSET_ROOT.to_owned());
+    let manifest = Manifest::load(&asset_root).unwrap_or_else(|err| {
+        println!("can't load the asset manifest, everything will be placeholders: {}", err);
+        Manifest{ root: asset_root, entries: Vec::new() }
+    });
+    let mut assets = AssetData::read(manifest);
+
+    let mut audio = Audio::default();
+
+    let step = assets.sound("step").clone();
 
     let semitone = 2.0_f32.powf(1.0/12.0);
-    let mut step_sounds: Vec<mq::Sound> = Vec::with_capacity(4);
-    step_sounds.push(mq::load_sound_from_bytes(&data).await.unwrap());
-    repitch(&mut data, 44100, 1.0 * semitone.powi(4));
-    step_sounds.push(mq::load_sound_from_bytes(&data).await.unwrap());
-    repitch(&mut data, 44100, 1.1 * semitone.powi(7));
-    step_sounds.push(mq::load_sound_from_bytes(&data).await.unwrap());
-    repitch(&mut data, 44100, 0.9 * semitone.powi(10));
-    step_sounds.push(mq::load_sound_from_bytes(&data).await.unwrap());
-
-    let changedir_sound = mq::load_sound("tf/custom/changedir.wav").await.unwrap();
-
-    let shoot0_sound = mq::load_sound("tf/custom/shoot0.wav").await.unwrap();
-    let shoot1_sound = mq::load_sound("tf/custom/shoot1.wav").await.unwrap();
-    let pickup_sound = mq::load_sound("tf/custom/pickup.wav").await.unwrap();
-    let place_sound = mq::load_sound("tf/custom/place.wav").await.unwrap();
-    let deflect_sound = mq::load_sound("tf/custom/deflect.wav").await.unwrap();
-    let explode_sound = mq::load_sound("tf/custom/explode.wav").await.unwrap();
-    let craft_sound = mq::load_sound("tf/custom/craft.wav").await.unwrap();
-    let reload_sound = mq::load_sound("tf/custom/reload.wav").await.unwrap();
-    let press_sound = mq::load_sound("tf/custom/press.wav").await.unwrap();
-    let obfuscator_sound = mq::load_sound("tf/custom/obfuscator.wav").await.unwrap();
+    for pitch in [1.0, 1.0 * semitone.powi(4), 1.1 * semitone.powi(7), 0.9 * semitone.powi(10)] {
+        audio.add(SoundKind::Step, &step.pitch(pitch)).await;
+    }
+
+    for (kind, key) in [
+        (SoundKind::ChangeDir,      "changedir"),
+        (SoundKind::PlayerShoot,    "shoot0"),
+        (SoundKind::TurretShoot,    "shoot1"),
+        (SoundKind::Pickup,         "pickup"),
+        (SoundKind::Place,          "place"),
+        (SoundKind::Deflect,        "deflect"),
+        (SoundKind::Explode,        "explode"),
+        (SoundKind::Craft,          "craft"),
+        (SoundKind::Reload,         "reload"),
+        (SoundKind::Press,          "press"),
+        (SoundKind::Obfuscate,      "obfuscator")
+    ] {
+        audio.add(kind, assets.sound(key)).await;
+    }
+
+    // music files in the manifest replace the built in stems
+    for (i, stem) in STEMS.iter().enumerate() {
+        let wav = match assets.manifest.get(AssetKind::Sound, stem.key) {
+            Some(_) => assets.sound(stem.key).clone(),
+            None    => synth_stem(i)
+        };
+        audio.add_stem(&wav).await;
+    }
 
     //let step: mq::Sound;
     //step = mq::load_sound_from_bytes(&data).await.unwrap();
@@ -99,65 +125,72 @@ async fn main() {
 
     game_main.drone_per_second = 5.0;
 
+    // start in creative mode, or turn it on from the sandbox menu
+    game_main.sandbox.enabled = std::env::args().any(|arg| arg == "--sandbox");
+
     regen_rail_by_tile(&game_main.rail, &mut game_main.rail_by_tile);
 
     game_main.drone_ids.resize(512);
     game_main.drone_pos.resize(512, vec2(0.0, 0.0));
     game_main.drone_data.resize(512, Drone{rail_idx: 0, rail_pos: 0.0, speed: 0.0});
-    game_main.drone_by_x.reserve(512);
+    game_main.drone_grid.resize(game_main.world_size, TILE_SIZE);
 
     game_main.bullet_ids.resize(128);
     game_main.bullet_pos.resize(512, vec2(0.0, 0.0));
     game_main.bullet_data.resize(512, Bullet{dir: vec2(0.0, 0.0), speed: 0.0, travel: 0.0, travel_max: 0.0});
 
+    let mut game_draw: draw::GameDraw = draw::make_game_draw(&mut assets);
+
     game_main.itemtype_data.push(ItemType {
-        sprite: draw::sprite(0, 3),
+        sprite: assets.sprite("sprites", &game_draw.atlas, "item_dead_drone"),
         stackable: 1,
         name: "Destroyed Logistics Drone",
         desc: "gwah"
     });
     game_main.itemtype_data.push(ItemType {
-        sprite: draw::sprite(1, 3),
+        sprite: assets.sprite("sprites", &game_draw.atlas, "item_scrap"),
         stackable: 69,
         name: "Scrap Metal",
         desc: "Level 1 craft item"
     });
     game_main.itemtype_data.push(ItemType {
-        sprite: draw::sprite(2, 3),
+        sprite: assets.sprite("sprites", &game_draw.atlas, "item_battery"),
         stackable: 69,
         name: "Battery",
         desc: "Energy-dense solid"
     });
     game_main.itemtype_data.push(ItemType {
-        sprite: draw::sprite(3, 3),
+        sprite: assets.sprite("sprites", &game_draw.atlas, "item_alignite"),
         stackable: 69,
         name: "Red Alignite crystal",
         desc: "Extremely cubic, rotation-locked with the planet"
     });
     game_main.itemtype_data.push(ItemType {
-        sprite: draw::sprite(4, 3),
+        sprite: assets.sprite("sprites", &game_draw.atlas, "item_gunpowder"),
         stackable: 69,
         name: "Gunpowder",
         desc: "Energy-dense explosive solid"
     });
     game_main.itemtype_data.push(ItemType {
-        sprite: draw::sprite(5, 3),
+        sprite: assets.sprite("sprites", &game_draw.atlas, "item_bullet"),
         stackable: 69,
         name: "Bullet",
         desc: ""
     });
     game_main.itemtype_data.push(ItemType {
-        sprite: draw::sprite(6, 3),
+        sprite: assets.sprite("sprites", &game_draw.atlas, "item_alignite_clump"),
         stackable: 1,
         name: "Red Alignite clump",
         desc: ""
     });
     game_main.itemtype_data.push(ItemType {
-        sprite: draw::sprite(6, 4),
+        sprite: assets.sprite("sprites", &game_draw.atlas, "item_obfuscator"),
         stackable: 1,
         name: "Obfuscator Charge",
         desc: ""
     });
+    // after every sprite has been looked up, so missing ones get listed too
+    assets.report();
 
 
     game_main.feral_ids.resize(32);
@@ -182,29 +215,207 @@ async fn main() {
     place_item(&game_main.itemtype_data, &mut game_main.feral_ids, &mut game_main.feral_data, &mut game_main.feral_by_tile, uvec2(42, 6), ItemSlot { itemtype: ITEM_OBFUSCATOR, count: 1 }).ok();
     place_item(&game_main.itemtype_data, &mut game_main.feral_ids, &mut game_main.feral_data, &mut game_main.feral_by_tile, uvec2(36, 9), ItemSlot { itemtype: ITEM_OBFUSCATOR, count: 1 }).ok();
 
-    let mut game_draw: draw::GameDraw = draw::make_game_draw().await;
+    game_draw.camera.update(game_main.player_pos, game_main.world_size.as_vec2() * TILE_SIZE, &[], 0.0);
+    game_draw.camera.snap();
 
     //let mut frame_count: u64 = 0;
 
+    // print the world to the terminal every second
+    let mut tui_live = false;
+
+    let bindings = Bindings::load(BINDINGS_PATH).unwrap_or_else(|err| {
+        println!("using default bindings, {}: {}", BINDINGS_PATH, err);
+        Bindings::default()
+    });
+    let mut settings = load_settings(&bindings);
+    for conflict in settings.bindings.conflicts() {
+        println!("warning: {} is bound to both {} and {}", conflict.binding, conflict.a.name(), conflict.b.name());
+    }
+    let mut input = Input::new(settings.bindings.clone());
+    settings.apply(&mut audio, &mut game_draw, &mut input);
+    // what the window was last set to, see Settings::apply_window
+    let mut applied = settings.clone();
+
+    // None where there's no way to read gamepads yet
+    let mut gamepad_source: Option<Box<dyn GamepadSource>> = input::platform_gamepad();
+
     loop {
 
         let delta: f32 = mq::get_frame_time().min(0.1);
 
-        controls.walk.x = (mq::is_key_down(mq::KeyCode::D) as i32 - mq::is_key_down(mq::KeyCode::A) as i32) as f32;
-        controls.walk.y = (mq::is_key_down(mq::KeyCode::S) as i32 - mq::is_key_down(mq::KeyCode::W) as i32) as f32;
+        if let Some(source) = &mut gamepad_source {
+            source.poll(&mut input.gamepad);
+        }
+
+        // the key picked for a binding doesn't also do what it's bound to now
+        input.rebinding = game_draw.settings_menu.rebinding.is_some();
+
+        // Console takes the keyboard while it's open
+        let was_typing = game_draw.console.open;
+        if input.pressed(Action::Console) {
+            game_draw.console.toggle();
+            // the key that opened it doesn't get typed
+            while mq::get_char_pressed().is_some() {}
+        } else if game_draw.console.open {
+            let console = &mut game_draw.console;
+            while let Some(c) = mq::get_char_pressed() {
+                if c.is_control().not() {
+                    console.line.push(c);
+                }
+            }
+            if mq::is_key_pressed(mq::KeyCode::Backspace) { console.line.pop(); }
+            if mq::is_key_pressed(mq::KeyCode::Enter) { console.submit(&mut game_main); }
+            if mq::is_key_pressed(mq::KeyCode::Up) { console.history_back(); }
+            if mq::is_key_pressed(mq::KeyCode::Down) { console.history_forward(); }
+            if mq::is_key_pressed(mq::KeyCode::Tab) { console.complete(&game_main); }
+            if mq::is_key_pressed(mq::KeyCode::Escape) { console.open = false; }
+        }
+        input.typing = was_typing || game_draw.console.open;
+
+        // Right stick aims around the player with the gun out, or moves the cursor for building
+        let aim_from = matches!(game_main.tool, ToolMode::GunPod).then(|| game_draw.view.world_to_screen(game_main.player_pos));
+        let (mouse_x, mouse_y) = mq::mouse_position();
+        input.update_cursor(vec2(mouse_x, mouse_y), vec2(mq::screen_width(), mq::screen_height()), aim_from, delta);
+
+        controls.walk = input.walk();
         let is_walking = controls.walk.length_squared() > 0.01;
 
-        game_draw.cam_size = if mq::is_key_down(mq::KeyCode::Z) {25.0} else {10.0};
+        // Camera: wheel or -/= to zoom, F to toggle free pan, arrows or middle mouse to pan
+        let wheel = mq::mouse_wheel().1;
+        if wheel != 0.0 && game_draw.mouse_on_ui.not() {
+            game_draw.camera.zoom_by(-wheel.signum() as i32);
+        }
+        if input.pressed(Action::ZoomOut) { game_draw.camera.zoom_by(1); }
+        if input.pressed(Action::ZoomIn) { game_draw.camera.zoom_by(-1); }
+        if input.pressed(Action::FreePan) {
+            game_draw.camera.free_pan = game_draw.camera.free_pan.not();
+        }
+        let pan = vec2(input.axis(Action::PanLeft, Action::PanRight), input.axis(Action::PanUp, Action::PanDown));
+        if pan != Vec2::ZERO {
+            game_draw.camera.free_pan = true;
+            game_draw.camera.target += pan * delta * TILE_SIZE * game_draw.camera.size;
+        }
+        if input.pressed(Action::GrabCamera) {
+            game_draw.camera.grab = Some(game_draw.mouse_pos);
+        } else if input.released(Action::GrabCamera) {
+            game_draw.camera.grab = None;
+        }
+        game_draw.camera.drag(game_draw.mouse_pos);
 
-        draw::draw_game(&game_main, &mut game_draw);
-        game_draw.clock_1s = (game_draw.clock_1s + delta).fract();
+        // Minimap: M to toggle, click or drag on it to look somewhere else
+        if input.pressed(Action::Minimap) {
+            game_draw.minimap.visible = game_draw.minimap.visible.not();
+        }
+        if game_draw.mouse_on_ui && input.down(Action::Use) && game_draw.minimap.contains(input.cursor) {
+            game_draw.camera.free_pan = true;
+            game_draw.camera.target = game_draw.minimap.screen_to_world(&game_main, input.cursor);
+        }
+        game_draw.camera.update(game_main.player_pos, game_main.world_size.as_vec2() * TILE_SIZE, &game_main.events, delta);
+
+        draw::draw_game(&game_main, &mut game_draw, &input);
+
+        // item book
+        if input.pressed(Action::ItemBook) {
+            game_draw.item_book.toggle(&mut game_draw.ui, &game_main);
+        }
+        if input.pressed(Action::DebugOverlay) {
+            game_draw.debug.toggle(&mut game_draw.ui);
+        }
+        if input.pressed(Action::Sandbox) {
+            game_draw.sandbox_menu.toggle(&mut game_draw.ui, &game_main);
+        }
+        if input.pressed(Action::Settings) {
+            game_draw.settings_menu.toggle(&mut game_draw.ui, &settings);
+        }
+        if let Some(key) = mq::get_last_key_pressed().filter(|_| input.rebinding) {
+            game_draw.settings_menu.key_pressed(&mut game_draw.ui, &mut settings, key);
+        }
+        if input.pressed(Action::CloseMenus) {
+            game_draw.item_book.close(&mut game_draw.ui);
+            game_draw.mach_config.close(&mut game_draw.ui);
+            game_draw.settings_menu.close(&mut game_draw.ui);
+            game_draw.sandbox_menu.close(&mut game_draw.ui);
+            game_draw.debug.close(&mut game_draw.ui);
+            game_draw.blueprint = Default::default();
+        }
+        for event in std::mem::take(&mut game_draw.ui.events) {
+            if game_draw.mach_config.handle(&mut game_draw.ui, &mut game_main, event).not()
+                && game_draw.item_book.handle(&mut game_draw.ui, &game_main, event).not()
+                && game_draw.sandbox_menu.handle(&mut game_draw.ui, &mut game_main, event).not()
+                && game_draw.debug.handle(&mut game_draw.ui, event).not() {
+                game_draw.settings_menu.handle(&mut game_draw.ui, &mut settings, &bindings, event);
+            }
+        }
+        game_draw.mach_config.update(&mut game_draw.ui, &game_main, &game_draw.world.sprites);
+
+        if input.pressed(Action::MuteMusic) {
+            settings.music_muted = settings.music_muted.not();
+            game_draw.settings_menu.changed = true;
+        }
+        if input.pressed(Action::Pause) {
+            game_main.time.toggle_pause();
+        }
+        if input.pressed(Action::SlowDown) {
+            game_main.time.slower();
+        }
+        if input.pressed(Action::SpeedUp) {
+            game_main.time.faster();
+        }
+        if input.pressed(Action::SaveBlueprint) {
+            match &game_main.blueprint {
+                Some(blueprint) => if let Err(err) = blueprint.save(BLUEPRINT_PATH, &game_main.itemtype_data) {
+                    println!("can't save {}: {}", BLUEPRINT_PATH, err);
+                },
+                None => println!("no blueprint to save, copy some machines first")
+            }
+        }
+        if input.pressed(Action::LoadBlueprint) {
+            match Blueprint::load(BLUEPRINT_PATH, &game_main.itemtype_data) {
+                Ok(blueprint) => {
+                    game_main.blueprint = Some(blueprint);
+                    game_draw.blueprint.pasting = true;
+                },
+                Err(err) => println!("{}: {}", BLUEPRINT_PATH, err)
+            }
+        }
+        if game_draw.settings_menu.changed {
+            game_draw.settings_menu.changed = false;
+            settings.apply(&mut audio, &mut game_draw, &mut input);
+            settings.apply_window(&applied);
+            applied = settings.clone();
+            if let Err(err) = settings.save(SETTINGS_PATH, &bindings) {
+                println!("can't save {}: {}", SETTINGS_PATH, err);
+            }
+        }
+
+        if input.pressed(Action::DumpAscii) {
+            print!("{}", draw::ascii::ascii_world(&game_main, uvec2(0, 0), game_main.world_size));
+        }
+        if input.pressed(Action::TuiLive) {
+            tui_live = tui_live.not();
+        }
+        if tui_live && game_draw.world.clock_1s < delta {
+            print!("{}", draw::ascii::ascii_tui_frame(&game_main));
+        }
+
+        if input.pressed(Action::Snapshot) {
+            if let Err(err) = draw::soft::snapshot_png(&game_main, &mut game_draw.world, &game_draw.view, &game_draw.atlas, "snapshot.png") {
+                println!("snapshot failed: {}", err);
+            }
+        }
+        game_main.intensity.update(&SimStats::of(&game_main), delta * game_main.time.scale());
+        game_draw.world.update(&game_main, delta);
+        game_main.events.clear();
+        for event in game_draw.world.events.drain(..) {
+            match event {
+                draw::AnimEvent::Step => game_main.sounds.push(AudioEvent::at(SoundKind::Step, game_main.player_pos, 1.0))
+            }
+        }
 
         // Walk
-        game_draw.player_hop_time += delta;
-        if is_walking && game_draw.player_hop_time > game_draw.player_hop_rate {
+        if is_walking && game_draw.world.player_anim.finished(&game_draw.world.anims) {
             game_main.hop_count += 1;
-            game_draw.player_hop_time = 0.0;
-            mq::play_sound_once(&step_sounds[mq::gen_range(0, step_sounds.len())]);
+            game_draw.world.player_anim.play(game_draw.world.clips.player_hop);
         }
         if controls.walk.x.abs() > 0.01 {
             game_main.player_facing = controls.walk.x.signum() as i8;
@@ -213,7 +424,7 @@ async fn main() {
         game_main.player_pos = game_main.player_pos.clamp(vec2(0.0, 0.0), game_main.world_size.as_vec2() * TILE_SIZE);
 
         // cycle tool
-        if mq::is_key_pressed(mq::KeyCode::Q) {
+        if input.pressed(Action::CycleTool) {
 
             match &game_main.tool {
                 ToolMode::Construct(drag) => {
@@ -225,180 +436,89 @@ async fn main() {
             }
         }
 
-        match &mut game_main.tool {
+        match &game_main.tool {
             ToolMode::Construct(drag) => {
-                // Pick up and place items and machines
-                if mq::is_mouse_button_pressed(mq::MouseButton::Left)
-                || mq::is_key_pressed(mq::KeyCode::E) {
-                    if let Drag::None = drag {
-                        match game_draw.under_cursor {
-                            TileThing::Feral(feral) => {
-                                let d = &mut game_main.feral_data[feral.0];
-
-                                let slot = &mut d.slots[0]; //&mut d.slots.iter_mut().find(|s| s.is_some()).unwrap();
-
-                                let slot_extracted = slot.take().unwrap();
-                                d.slots.rotate_left(1);
-
-                                feral_remove_if_empty(&mut game_main.feral_ids, &mut game_main.feral_data, &mut game_main.feral_by_tile, feral);
-
-                                *drag = Drag::Item(slot_extracted);
-
-                                mq::play_sound(&pickup_sound, mq::PlaySoundParams { looped: false, volume: 0.5 });
-                            },
-                            TileThing::Machine(mach) => {
-                                let d = &mut game_main.mach_data[mach.0];
-                                d.pos = None;
-                                *drag = Drag::Machine(mach);
-                                game_main.mach_by_tile.remove(&(game_draw.mouse_select.x as u8, game_draw.mouse_select.y as u8));
-                                mq::play_sound(&pickup_sound, mq::PlaySoundParams { looped: false, volume: 0.5 });
-                            },
-                            _ => {}
-                        };
-                    } else if let Drag::Item(slot) = drag {
-
-                        if matches!(game_draw.under_cursor, TileThing::Machine(_)).not() {
-
-                            let Drag::Item(slot) = std::mem::take(drag) else { panic!() };
-
-                            match place_item(&game_main.itemtype_data, &mut game_main.feral_ids, &mut game_main.feral_data, &mut game_main.feral_by_tile, game_draw.mouse_select, slot) {
-                                Ok(_) => {
-                                    mq::play_sound(&place_sound, mq::PlaySoundParams { looped: false, volume: 0.5 });
-                                },
-                                Err((_, slot, transfered)) => {
-                                    *drag = Drag::Item(slot);
-                                    if transfered {
-                                        mq::play_sound(&place_sound, mq::PlaySoundParams { looped: false, volume: 0.5 });
-                                    }
-                                }
-                            }
-                        } else if let TileThing::Machine(mach) = game_draw.under_cursor {
-                            // Reload turrets
-                            if slot.itemtype == ITEM_BULLET {
-                                let d = &mut game_main.mach_data[mach.0];
-                                if let MachineSpec::Turret { ammo, can_fire_time_us: _ } = &mut d.spec {
-                                    let transfer = u32::min(69_u32.saturating_sub(*ammo), slot.count);
-                                    *ammo += transfer;
-                                    slot.count -= transfer;
-
-                                    if slot.count == 0 {
-                                        *drag = Drag::None;
-                                    }
-                                    mq::play_sound(&reload_sound, mq::PlaySoundParams { looped: false, volume: 0.8 });
-                                };
+                let tile = game_draw.mouse_select;
+                let mut cmd = None;
+                let pasting = game_draw.blueprint.pasting && game_main.blueprint.is_some() && matches!(drag, Drag::None);
+
+                // Copy machines between two corners, then paste them as ghosts
+                if matches!(drag, Drag::None) && input.pressed(Action::Copy) {
+                    match game_draw.blueprint.corner.take() {
+                        None => game_draw.blueprint.corner = Some(tile),
+                        Some(corner) => {
+                            let blueprint = Blueprint::capture(&game_main, corner, tile);
+                            if blueprint.is_empty().not() {
+                                game_main.blueprint = Some(blueprint);
+                                game_draw.blueprint.pasting = true;
                             }
                         }
-                    } else if let Drag::Machine(mach) = drag {
-
-                        let valid_placement = matches!(game_draw.under_cursor, TileThing::None) && game_main.rail.iter().all(|x| x.pos != game_draw.mouse_select);
-
-                        if valid_placement {
-
-                            mq::play_sound(&place_sound, mq::PlaySoundParams { looped: false, volume: 0.5 });
-
-                            game_main.mach_by_tile.insert((game_draw.mouse_select.x as u8, game_draw.mouse_select.y as u8), mach.clone());
-
-                            game_main.mach_data[mach.0].pos = Some(game_draw.mouse_select);
-
-                            *drag = Drag::None;
-
-                        }
                     }
                 }
+                if input.pressed(Action::Paste) {
+                    game_draw.blueprint.pasting = game_draw.blueprint.pasting.not();
+                }
+                if input.pressed(Action::BuildGhosts) {
+                    cmd = Some(Construct::BuildGhosts);
+                }
 
-                // Rotate machine
-                if mq::is_key_pressed(mq::KeyCode::R) {
-                    if let Drag::Machine(mach) = drag {
-                        let d = &mut game_main.mach_data[mach.0];
-                        d.dir = match d.dir {
-                            Dir::Right  => Dir::Up,
-                            Dir::Up     => Dir::Left,
-                            Dir::Left   => Dir::Down,
-                            Dir::Down   => Dir::Right,
-                        };
+                if game_main.sandbox.editing_rail() && matches!(drag, Drag::None) {
+                    if game_draw.cursor_tile.is_some() && input.pressed(Action::Use) && game_draw.mouse_on_ui.not() {
+                        cmd = Some(Construct::EditRail(tile));
+                    }
+                }
+                else if pasting {
+                    if game_draw.cursor_tile.is_some() && input.pressed(Action::Use) && game_draw.mouse_on_ui.not() {
+                        cmd = Some(Construct::Paste(tile));
+                    }
+                    if input.pressed(Action::Rotate) {
+                        game_main.blueprint = game_main.blueprint.as_ref().map(|b| b.rotated());
                     }
                 }
 
-                // Craft
-                if let Drag::None = drag {
-                    if game_main.rail_by_tile.contains_key(&(game_draw.mouse_select.x as u8, game_draw.mouse_select.y as u8)).not() {
-                        if mq::is_key_pressed(mq::KeyCode::Key1) { // disassemble
-                            if let TileThing::Feral(feral) = game_draw.under_cursor {
-                                if let Some(gwah) = craft_item_recipe(&game_main.itemtype_data, &game_main.feral_data[feral.0].slots, 0) {
-                                    game_main.feral_data[feral.0].slots = gwah;
-                                    mq::play_sound(&craft_sound, mq::PlaySoundParams { looped: false, volume: 1.0 });
-                                }
-                            }
-                        } else if mq::is_key_pressed(mq::KeyCode::Key2) { // bullet
-                            if let TileThing::Feral(feral) = game_draw.under_cursor {
-                                if let Some(gwah) = craft_item_recipe(&game_main.itemtype_data, &game_main.feral_data[feral.0].slots, 1) {
-                                    game_main.feral_data[feral.0].slots = gwah;
-                                    mq::play_sound(&craft_sound, mq::PlaySoundParams { looped: false, volume: 1.0 });
-                                }
-                            }
-                        } else if mq::is_key_pressed(mq::KeyCode::Key3) { // clump
-                            if let TileThing::Feral(feral) = game_draw.under_cursor {
-                                if let Some(gwah) = craft_item_recipe(&game_main.itemtype_data, &game_main.feral_data[feral.0].slots, 2) {
-                                    game_main.feral_data[feral.0].slots = gwah;
-                                    mq::play_sound(&craft_sound, mq::PlaySoundParams { looped: false, volume: 1.0 });
-                                }
-                            }
-                        } else if mq::is_key_pressed(mq::KeyCode::Key5) { // turret
-                            if let TileThing::Feral(feral) = game_draw.under_cursor {
-                                if let Some(gwah) = craft_machine_recipe(&game_main.itemtype_data, &game_main.feral_data[feral.0].slots, 5) {
-                                    game_main.feral_data[feral.0].slots = gwah;
-                                    mq::play_sound(&craft_sound, mq::PlaySoundParams { looped: false, volume: 1.0 });
-
-                                    feral_remove_if_empty(&mut game_main.feral_ids, &mut game_main.feral_data, &mut game_main.feral_by_tile, feral);
-
-                                    let mach = game_main.mach_ids.create_resize();
-                                    game_main.mach_data.resize(game_main.mach_ids.capacity(), Default::default());
-                                    game_main.mach_data[mach.0].spec = MachineSpec::Turret { ammo: 0, can_fire_time_us: game_main.time_us };
-                                    *drag = Drag::Machine(mach);
-                                }
-                            }
-                        } else if mq::is_key_pressed(mq::KeyCode::Key6) { // conveyor
-                            if let TileThing::Feral(feral) = game_draw.under_cursor {
-                                if let Some(gwah) = craft_machine_recipe(&game_main.itemtype_data, &game_main.feral_data[feral.0].slots, 6) {
-                                    game_main.feral_data[feral.0].slots = gwah;
-                                    mq::play_sound(&craft_sound, mq::PlaySoundParams { looped: false, volume: 1.0 });
-
-                                    feral_remove_if_empty(&mut game_main.feral_ids, &mut game_main.feral_data, &mut game_main.feral_by_tile, feral);
-
-                                    let mach = game_main.mach_ids.create_resize();
-                                    game_main.mach_data.resize(game_main.mach_ids.capacity(), Default::default());
-
-                                    game_main.mach_data[mach.0].spec = MachineSpec::Conveyor{item: Default::default(), filter: false, can_move_time_us: game_main.time_us, can_dump_time_us: game_main.time_us};
-                                    *drag = Drag::Machine(mach);
-                                }
-                            }
-                        } else if mq::is_key_pressed(mq::KeyCode::Key7) { // filterveyor
-                            if let TileThing::Feral(feral) = game_draw.under_cursor {
-                                if let Some(gwah) = craft_machine_recipe(&game_main.itemtype_data, &game_main.feral_data[feral.0].slots, 7) {
-                                    game_main.feral_data[feral.0].slots = gwah;
-                                    mq::play_sound(&craft_sound, mq::PlaySoundParams { looped: false, volume: 1.0 });
-
-                                    feral_remove_if_empty(&mut game_main.feral_ids, &mut game_main.feral_data, &mut game_main.feral_by_tile, feral);
+                // Pick up and place items and machines, only when pointing at the world
+                else if game_draw.cursor_tile.is_some() && ((input.pressed(Action::Use) && game_draw.mouse_on_ui.not()) || input.pressed(Action::Interact)) {
+                    cmd = match (drag, &game_draw.under_cursor) {
+                        (Drag::None, TileThing::Machine(mach)) if input.pressed(Action::Interact).not() => {
+                            // clicked on it
+                            game_draw.mach_config.open(*mach);
+                            None
+                        },
+                        (Drag::None, TileThing::None) if game_main.ghosts.get(tile.into()).is_none() => None,
+                        (Drag::None, _) => Some(Construct::PickUp(tile)),
+                        _ => Some(Construct::Drop(tile))
+                    };
+                }
 
-                                    let mach = game_main.mach_ids.create_resize();
-                                    game_main.mach_data.resize(game_main.mach_ids.capacity(), Default::default());
+                // Rotate machine
+                if input.pressed(Action::Rotate) && matches!(drag, Drag::Machine(_)) {
+                    cmd = Some(Construct::Rotate);
+                }
 
-                                    game_main.mach_data[mach.0].spec = MachineSpec::Conveyor{item: Default::default(), filter: true, can_move_time_us: game_main.time_us, can_dump_time_us: game_main.time_us};
-                                    *drag = Drag::Machine(mach);
-                                }
-                            }
-                        } else if mq::is_key_pressed(mq::KeyCode::R) {
-                            if let TileThing::Feral(feral) = game_draw.under_cursor {
-                                if let Some(gwah) = craft_item_recipe(&game_main.itemtype_data, &game_main.feral_data[feral.0].slots, 69) {
+                // Craft
+                if pasting.not() && game_main.sandbox.editing_rail().not() && matches!(drag, Drag::None) && game_draw.cursor_tile.is_some_and(|t| t.can_craft()) {
+                    for recipe in [Recipe::Disassemble, Recipe::Bullet, Recipe::Clump, Recipe::Turret, Recipe::Conveyor, Recipe::Filterveyor] {
+                        if input.pressed(Action::Craft(recipe)) {
+                            cmd = Some(Construct::Craft{ recipe: recipe.index(), tile });
+                        }
+                    }
+                    if input.pressed(Action::Obfuscate) {
+                        cmd = Some(Construct::Obfuscate(tile));
+                    }
+                }
 
-                                    if world_obfuscate(game_draw.mouse_select, &mut game_main.rail, game_main.world_size) {
-                                        game_main.feral_data[feral.0].slots = gwah;
-                                        feral_remove_if_empty(&mut game_main.feral_ids, &mut game_main.feral_data, &mut game_main.feral_by_tile, feral);
+                // Undo and redo
+                if (input.pressed(Action::Undo) && undo(&mut game_main).is_some())
+                || (input.pressed(Action::Redo) && redo(&mut game_main).is_some()) {
+                    game_main.sounds.push(AudioEvent::flat(SoundKind::Place, 0.3));
+                }
 
-                                        mq::play_sound(&obfuscator_sound, mq::PlaySoundParams { looped: false, volume: 1.0 });
-                                        regen_rail_by_tile(&game_main.rail, &mut game_main.rail_by_tile);
-                                    }
-                                }
+                if let Some(cmd) = cmd {
+                    if construct(&mut game_main, cmd).not() {
+                        if let (Construct::Obfuscate(tile), TileThing::Feral(feral)) = (cmd, &game_draw.under_cursor) {
+                            if slots_contains(&game_main.feral_data[feral.0].slots, ITEM_OBFUSCATOR, 1) {
+                                let tl = tile.max(uvec2(2, 2)) - uvec2(2, 2);
+                                println!("obfuscate failed at {}:\n{}", tile, draw::ascii::ascii_world(&game_main, tl, uvec2(5, 5)));
                             }
                         }
                     }
@@ -407,7 +527,7 @@ async fn main() {
             ToolMode::GunPod => {
 
                 // Reload
-                if mq::is_key_pressed(mq::KeyCode::R) {
+                if input.pressed(Action::Reload) {
                     if let TileThing::Feral(feral) = game_draw.under_cursor {
 
                         let got_bullets = take_items(&mut game_main.feral_data[feral.0].slots, ITEM_BULLET, PLAYER_GUN_AMMO_MAX - game_main.player_gun_ammo);
@@ -415,178 +535,128 @@ async fn main() {
 
                         if got_bullets != 0 {
                             game_main.player_gun_ammo += got_bullets;
-                            mq::play_sound(&reload_sound, mq::PlaySoundParams { looped: false, volume: 1.0 });
+                            game_main.sounds.push(AudioEvent::flat(SoundKind::Reload, 1.0));
                         }
                     }
                 }
             }
         }
 
-        use Dir;
         use Bend;
 
-        // Remove drones
-        for drone in &game_main.remove_drones {
-            game_main.drone_ids.remove(*drone);
-        }
-        game_main.remove_drones.clear();
-
-        // Move drones
-        for drone in game_main.drone_ids.iter_ids() {
-            let d: &mut Drone     = &mut game_main.drone_data[drone.0];
-            let p: &mut Vec2      = &mut game_main.drone_pos[drone.0];
+        // Simulate, in steps so faster speeds stay as accurate as normal speed. Nothing in here
+        // reads input, or it would happen once per step.
+        let (sim_steps, sim_delta) = game_main.time.steps(delta);
+        for _ in 0..sim_steps {
 
-            // hack: index goes out of bounds when rail gets shortened when using obfuscation charge
-            if d.rail_idx >= game_main.rail.len() {
-                d.rail_idx = game_main.rail.len() - 1;
+            // Remove drones
+            for drone in &game_main.remove_drones {
+                game_main.drone_ids.remove(*drone);
+                game_main.drone_grid.remove(*drone);
             }
+            game_main.remove_drones.clear();
+
+            // Move drones
+            for drone in game_main.drone_ids.iter_ids() {
+                let d: &mut Drone     = &mut game_main.drone_data[drone.0];
+                let p: &mut Vec2      = &mut game_main.drone_pos[drone.0];
 
-            let r: &Rail          = &game_main.rail[d.rail_idx];
+                // hack: index goes out of bounds when rail gets shortened when using obfuscation charge
+                if d.rail_idx >= game_main.rail.len() {
+                    d.rail_idx = game_main.rail.len() - 1;
+                }
 
-            let mut dir = dir_to_vec2(&r.dir);
+                let r: &Rail          = &game_main.rail[d.rail_idx];
 
-            if d.rail_pos > 0.5 {
-                dir = match r.bend {
-                    Bend::Forward => dir,
-                    Bend::Right   => rot_cw_90(dir),
-                    Bend::Left    => rot_ccw_90(dir)
-                };
-            }
+                let mut dir = dir_to_vec2(&r.dir);
+
+                if d.rail_pos > 0.5 {
+                    dir = match r.bend {
+                        Bend::Forward => dir,
+                        Bend::Right   => rot_cw_90(dir),
+                        Bend::Left    => rot_ccw_90(dir)
+                    };
+                }
 
-            *p = (r.pos.as_vec2() + vec2(0.5, 0.5-0.125) + dir * (d.rail_pos - 0.5)) * TILE_SIZE;
+                *p = (r.pos.as_vec2() + vec2(0.5, 0.5-0.125) + dir * (d.rail_pos - 0.5)) * TILE_SIZE;
+                game_main.drone_grid.update(drone, *p);
 
-            let rail_pos_next = d.rail_pos+d.speed*delta;
-            let midway = d.rail_pos < 0.5 && 0.5 < rail_pos_next;
+                let rail_pos_next = d.rail_pos+d.speed*sim_delta;
+                let midway = d.rail_pos < 0.5 && 0.5 < rail_pos_next;
 
-            if midway {
-                if let Some(feral) = game_main.feral_by_tile.get(&(r.pos.x as u8, r.pos.y as u8)) {
-                    let mut somethinghappen = false;
-                    for slot_opt in &mut game_main.feral_data[feral.0].slots {
-                        if let Some(slot) = slot_opt {
-                            if slot.itemtype == ITEM_BATTERY {
-                                somethinghappen = true;
-                                slot.itemtype = ITEM_GUNPOWDER;
+                if midway {
+                    if let Some(feral) = game_main.feral_by_tile.get(r.pos.into()) {
+                        let mut somethinghappen = false;
+                        for slot_opt in &mut game_main.feral_data[feral.0].slots {
+                            if let Some(slot) = slot_opt {
+                                if slot.itemtype == ITEM_BATTERY {
+                                    somethinghappen = true;
+                                    slot.itemtype = ITEM_GUNPOWDER;
+                                }
                             }
                         }
-                    }
-                    if somethinghappen {
-                        //d.speed *= 2.0/16.0;
-                        let vol = (1.0 - (game_draw.cam_center - *p).length()/12.0/TILE_SIZE.x).min(1.0);
-                        if vol > 0.0 {
-                            mq::play_sound(&press_sound, mq::PlaySoundParams { looped: false, volume: vol });
+                        if somethinghappen {
+                            //d.speed *= 2.0/16.0;
+                            game_main.sounds.push(AudioEvent::at(SoundKind::Press, *p, 1.0));
                         }
                     }
                 }
-            }
 
-            if matches!(r.bend, Bend::Forward).not() && midway {
-                d.rail_pos = 0.5;
-                d.speed *= 15.0/16.0;
+                if matches!(r.bend, Bend::Forward).not() && midway {
+                    d.rail_pos = 0.5;
+                    d.speed *= 15.0/16.0;
 
-                let vol = (1.0 - (game_draw.cam_center - *p).length()/8.0/TILE_SIZE.x).min(1.0) * 0.2;
-                if vol > 0.0 {
-                    mq::play_sound(&changedir_sound, mq::PlaySoundParams { looped: false, volume: vol });
-                }
+                    game_main.sounds.push(AudioEvent::at(SoundKind::ChangeDir, *p, 0.2));
 
-            } else {
-                d.rail_pos = rail_pos_next;
-                d.speed = f32::min(d.speed + delta*0.2, 32.0);
-            }
+                } else {
+                    d.rail_pos = rail_pos_next;
+                    d.speed = f32::min(d.speed + sim_delta*0.2, 32.0);
+                }
 
-            if d.rail_pos > 1.0 {
-                d.rail_pos -= 1.0;
-                d.rail_idx += 1;
+                if d.rail_pos > 1.0 {
+                    d.rail_pos -= 1.0;
+                    d.rail_idx += 1;
 
-                if d.rail_idx == game_main.rail.len() {
-                    game_main.remove_drones.push(drone);
+                    if d.rail_idx == game_main.rail.len() {
+                        game_main.remove_drones.push(drone);
+                        game_main.events.push(GameEvent::DroneLeaked{ pos: *p });
+                    }
                 }
             }
-        }
-
 
-        if game_main.drone_timer < 0.0 {
-            let id = game_main.drone_ids.create_resize();
-            game_main.drone_pos.resize(game_main.drone_ids.capacity(), vec2(-10000.0, -100000.0));
-            game_main.drone_data.resize(game_main.drone_ids.capacity(), Drone{rail_idx: 0, rail_pos: 0.0, speed: 0.0});
 
-            game_main.drone_data[id.0] = Drone{rail_idx: 0, rail_pos: 0.0, speed: 32.0};
-
-            let pos = &mut game_main.drone_pos[id.0];
-            *pos = vec2(-10000.0, -100000.0);
-            game_main.drone_by_x.push((id, pos.x));
-
-            game_main.drone_timer += 1.0 / game_main.drone_per_second;
-        }
-        game_main.drone_timer -= delta;
-
-        let mut new_len = game_main.drone_by_x.len();
-        let mut slice = game_main.drone_by_x.as_mut_slice();
-        while slice.is_empty().not() {
-            let delete = game_main.drone_ids.exists(slice[0].0).not();
-            if delete {
-                // swap-and-pop (swap_remove) to delete
-                let last_idx = slice.len()-1;
-                new_len -= 1;
-                slice[0] = slice[last_idx];
-                slice = &mut slice[0..last_idx];
-            } else {
-                // update x
-                slice[0].1 = game_main.drone_pos[slice[0].0.0].x;
-
-                // Next element
-                slice = &mut slice[1..];
+            if game_main.drone_timer < 0.0 && game_main.sandbox.spawns_drones() {
+                spawn_drone(&mut game_main);
+                game_main.drone_timer += 1.0 / game_main.drone_per_second;
             }
-        }
-        game_main.drone_by_x.truncate(new_len);
-
-        game_main.drone_by_x.sort_unstable_by(|lhs, rhs| {
-            lhs.1.partial_cmp(&rhs.1).unwrap()
-        });
-
-
-
-        // Delete bullets
-        for id in &game_main.remove_bullets {
-            game_main.bullet_ids.remove(*id);
-        }
-        game_main.remove_bullets.clear();
-
-        // Move bullets
-        for id in game_main.bullet_ids.iter_ids() {
-            let d: &mut Bullet = &mut game_main.bullet_data[id.0];
-            let p: &mut Vec2         = &mut game_main.bullet_pos[id.0];
-
-            let trav = d.speed * delta;
-            d.travel += trav;
-
-            if d.travel > d.travel_max {
-                game_main.remove_bullets.push(id);
+            if game_main.sandbox.spawns_drones() {
+                game_main.drone_timer -= sim_delta;
             }
 
-            // search for drones from this X value and above
-            let first_x: f32 = p.x - 0.5*TILE_SIZE.x;
-
-            // lower_bound: https://stackoverflow.com/questions/75790347/
-            let mut idx = game_main.drone_by_x.binary_search_by(|x| match x.1.total_cmp(&first_x) {
-                Ordering::Equal => Ordering::Greater,
-                ord => ord,
-            }).unwrap_err();
-
-            //println!("wot: {} {}", idx, game_main.drone_by_x.len());
+            // Delete bullets
+            for id in &game_main.remove_bullets {
+                game_main.bullet_ids.remove(*id);
+            }
+            game_main.remove_bullets.clear();
 
+            // Move bullets
+            for id in game_main.bullet_ids.iter_ids() {
+                let d: &mut Bullet = &mut game_main.bullet_data[id.0];
+                let p: &mut Vec2         = &mut game_main.bullet_pos[id.0];
 
-            while idx < game_main.drone_by_x.len() {
-                let drone_id = game_main.drone_by_x[idx].0;
-                let drone_pos = game_main.drone_pos[drone_id.0];
-                let drone_tl = drone_pos - TILE_SIZE * 0.5;
-                let drone_br = drone_pos + TILE_SIZE * 0.5;
+                let trav = d.speed * sim_delta;
+                d.travel += trav;
 
-                if p.x > drone_br.x {
-                    break;
+                if d.travel > d.travel_max {
+                    game_main.remove_bullets.push(id);
                 }
 
-                if    drone_tl.x < p.x && p.x < drone_br.x
-                   && drone_tl.y < p.y && p.y < drone_br.y {
+                // first drone with a hitbox containing the bullet
+                let (near_tl, near_br) = drone_aabb(*p);
+                let hit = game_main.drone_grid.iter_aabb(near_tl, near_br).next();
+
+                if let Some((drone_id, drone_pos)) = hit {
+                    let (drone_tl, drone_br) = drone_aabb(drone_pos);
 
                     let drone_tr = vec2(drone_br.x, drone_tl.y);
                     let drone_bl = vec2(drone_tl.x, drone_br.y);
@@ -621,279 +691,253 @@ async fn main() {
 
                         let dot = norm.dot(-d.dir);
 
-                        let vol = (1.0 - (game_draw.cam_center - *p).length()/12.0/TILE_SIZE.x).min(1.0);
-
                         if dot > f32::cos(26.0_f32.to_radians()) {
                             game_main.remove_drones.push(drone_id);
                             game_main.remove_bullets.push(id);
                             place_item(&game_main.itemtype_data, &mut game_main.feral_ids, &mut game_main.feral_data, &mut game_main.feral_by_tile, (drone_pos / TILE_SIZE).floor().as_uvec2(), ItemSlot { itemtype: ItemTypeId(0), count: 1 }).ok();
+                            game_main.events.push(GameEvent::DroneKilled{ pos: drone_pos });
 
-                            if vol > 0.0 {
-                                mq::play_sound(&explode_sound, mq::PlaySoundParams { looped: false, volume: vol*0.8 });
-                            }
+                            game_main.sounds.push(AudioEvent::at(SoundKind::Explode, *p, 0.8));
 
                         } else {
                             d.dir = d.dir + 2.0*norm*dot;
-                            mq::play_sound(&deflect_sound, mq::PlaySoundParams { looped: false, volume: vol });
+                            game_main.events.push(GameEvent::BulletDeflected{ pos: *p, dir: d.dir });
+                            game_main.sounds.push(AudioEvent::at(SoundKind::Deflect, *p, 1.0));
                         }
                     }
-
-                    break;
                 }
-                idx += 1;
-            }
-
-            *p += d.dir * trav;
-
-        }
 
+                *p += d.dir * trav;
 
-        const TURRET_PERIOD: u64 = 1500000u64; // 1.5 seconds
-        const CONVEY_TAKE_PERIOD: u64 = 250000u64; // 0.25 seconds
-        const CONVEY_DUMP_PERIOD: u64 = 100000u64; // 0.1 seconds
-
-
-        for mach in game_main.mach_ids.iter_ids() {
-
-            let pos_opt = game_main.mach_data[mach.0].pos.clone();
-
+            }
 
-            if let Some(pos) = pos_opt {
 
-                // note: borrow checker not happy when accessing multiple machines at a time
+            const TURRET_PERIOD: u64 = 1500000u64; // 1.5 seconds
+            const CONVEY_TAKE_PERIOD: u64 = 250000u64; // 0.25 seconds
+            const CONVEY_DUMP_PERIOD: u64 = 100000u64; // 0.1 seconds
 
-                // Turrets shoot
-                if matches!(game_main.mach_data[mach.0].spec, MachineSpec::Turret { ammo: _, can_fire_time_us: _ })  {
 
-                    let d = &mut game_main.mach_data[mach.0];
-                    let MachineSpec::Turret { ammo, can_fire_time_us } = &mut d.spec else { panic!(); };
+            for mach in game_main.mach_ids.iter_ids() {
 
-                    if (*ammo != 0) && (*can_fire_time_us < game_main.time_us) {
+                let pos_opt = game_main.mach_data[mach.0].pos.clone();
 
-                        let ppos = (pos.as_vec2() + vec2(0.5, 0.5)) * TILE_SIZE;
-                        let dirmat = dir_to_mat2(&d.dir);
 
-                        let drone_detected: bool = (|| {
+                if let Some(pos) = pos_opt.filter(|_| game_main.mach_data[mach.0].enabled) {
 
-                            let point_a = ppos + dirmat.mul_vec2(vec2(0.0, 0.6)) * TILE_SIZE;
-                            let point_b = ppos + dirmat.mul_vec2(vec2(5.0, -0.6)) * TILE_SIZE;
-                            let tl = Vec2::min(point_a, point_b);
-                            let br = Vec2::max(point_a, point_b);
+                    // note: borrow checker not happy when accessing multiple machines at a time
 
-                            // search for drones from this X value and above
-                            let first_x: f32 = tl.x;
+                    // Turrets shoot
+                    if matches!(game_main.mach_data[mach.0].spec, MachineSpec::Turret { ammo: _, can_fire_time_us: _, .. })  {
 
-                            // lower_bound: https://stackoverflow.com/questions/75790347/
-                            let mut idx = game_main.drone_by_x.binary_search_by(|x| match x.1.total_cmp(&first_x) {
-                                Ordering::Equal => Ordering::Greater,
-                                ord => ord,
-                            }).unwrap_err();
+                        let d = &mut game_main.mach_data[mach.0];
+                        let MachineSpec::Turret { ammo, can_fire_time_us, target } = &mut d.spec else { panic!(); };
 
-                            while idx < game_main.drone_by_x.len() {
-                                let drone_id = game_main.drone_by_x[idx].0;
-                                let drone_pos = game_main.drone_pos[drone_id.0];
+                        if (*ammo != 0) && (*can_fire_time_us < game_main.time_us) {
 
-                                if br.x < drone_pos.x {
-                                    break;
-                                }
+                            let ppos = (pos.as_vec2() + vec2(0.5, 0.5)) * TILE_SIZE;
+                            let mut dirmat = dir_to_mat2(&d.dir);
 
-                                if    (tl.x < drone_pos.x && drone_pos.x < br.x)
-                                   && (tl.y < drone_pos.y && drone_pos.y < br.y) {
-                                    return true;
+                            let drone_detected = match target {
+                                TurretTarget::Ahead => {
+                                    let (tl, br) = turret_detect_box(pos, &d.dir);
+                                    game_main.drone_grid.iter_aabb(tl, br).next().is_some()
+                                },
+                                TurretTarget::Nearest => {
+                                    // aim straight at it
+                                    let range = TURRET_RANGE * TILE_SIZE.x;
+                                    let nearest = game_main.drone_grid.iter_aabb(ppos - range, ppos + range)
+                                        .map(|(_, dpos)| dpos - ppos)
+                                        .filter(|off| off.length() < range && off.length() > 0.001)
+                                        .min_by(|a, b| a.length().partial_cmp(&b.length()).unwrap());
+                                    if let Some(off) = nearest {
+                                        let aim = off.normalize();
+                                        dirmat = Mat2::from_cols(aim, rot_cw_90(aim));
+                                    }
+                                    nearest.is_some()
                                 }
+                            };
 
-                                idx += 1;
-                            }
-
-                            return false;
-                        })();
-
-                        if drone_detected {
-                            *ammo -= 1;
-                            *can_fire_time_us = game_main.time_us + TURRET_PERIOD - (game_main.time_us - *can_fire_time_us)%TURRET_PERIOD;
+                            if drone_detected {
+                                *ammo -= 1;
+                                *can_fire_time_us = game_main.time_us + TURRET_PERIOD - (game_main.time_us - *can_fire_time_us)%TURRET_PERIOD;
 
 
-                            let bullet = game_main.bullet_ids.create().unwrap();
-                            game_main.bullet_pos[bullet.0]  = ppos + dirmat.x_axis * 0.5*TILE_SIZE;
-                            game_main.bullet_data[bullet.0] = Bullet{dir: dirmat.x_axis, speed: 1200.0, travel: 0.0, travel_max: TILE_SIZE.x * 4.5};
+                                let bullet = game_main.bullet_ids.create().unwrap();
+                                game_main.bullet_pos[bullet.0]  = ppos + dirmat.x_axis * 0.5*TILE_SIZE;
+                                game_main.bullet_data[bullet.0] = Bullet{dir: dirmat.x_axis, speed: 1200.0, travel: 0.0, travel_max: TILE_SIZE.x * 4.5};
+                                game_main.events.push(GameEvent::TurretFired{ mach, pos: game_main.bullet_pos[bullet.0], dir: dirmat.x_axis });
 
-                            let vol = (1.0 - (game_draw.cam_center - ppos).length()/12.0/TILE_SIZE.x).min(1.0);
-                            if vol > 0.0 {
-                                mq::play_sound(&shoot1_sound, mq::PlaySoundParams { looped: false, volume: vol });
+                                game_main.sounds.push(AudioEvent::at(SoundKind::TurretShoot, ppos, 1.0));
                             }
                         }
-                    }
-                } else if matches!(game_main.mach_data[mach.0].spec, MachineSpec::Conveyor { item: _, filter: _, can_move_time_us: _, can_dump_time_us: _ }) {
-
-                    let MachineSpec::Conveyor { item, filter, can_move_time_us, can_dump_time_us } = game_main.mach_data[mach.0].spec.clone() else { panic!(); };
+                    } else if matches!(game_main.mach_data[mach.0].spec, MachineSpec::Conveyor { item: _, filter: _, can_move_time_us: _, can_dump_time_us: _ }) {
 
-                    let forward = dir_to_ivec2(&game_main.mach_data[mach.0].dir);
+                        let MachineSpec::Conveyor { item, filter, can_move_time_us, can_dump_time_us } = game_main.mach_data[mach.0].spec.clone() else { panic!(); };
 
-                    if item.count == 0 && can_move_time_us < game_main.time_us {
+                        let forward = dir_to_ivec2(&game_main.mach_data[mach.0].dir);
 
-                        // take item from behind
+                        if item.count == 0 && can_move_time_us < game_main.time_us {
 
-                        let back = pos.as_ivec2() - forward;
+                            // take item from behind
 
-                        //let mut convey: Option<(ItemTypeId, u32)> = None;
+                            //let mut convey: Option<(ItemTypeId, u32)> = None;
 
-                        // tiles valid?
-                        if    0 <= back.x  && back.x  < game_main.world_size.x as i32
-                           && 0 <= back.y  && back.y  < game_main.world_size.y as i32 {
+                            // tiles valid?
+                            if let Some(back) = TilePos::from(pos).offset(-forward, game_main.world_size) {
 
-                            // item in back side?
-                            let backopt = game_main.feral_by_tile.get(&(back.x as u8, back.y as u8));
+                                // item in back side?
+                                let backopt = game_main.feral_by_tile.get(back);
 
-                            if backopt.is_some() {
+                                if backopt.is_some() {
 
-                                let back_feral = backopt.unwrap().clone();
+                                    let back_feral = backopt.unwrap().clone();
 
 
-                                // weh
-                                if filter && item.itemtype != Default::default() {
+                                    // weh
+                                    if filter && item.itemtype != Default::default() {
 
-                                    // extract specific item
+                                        // extract specific item
 
-                                    let MachineSpec::Conveyor { item, filter: _, can_move_time_us, can_dump_time_us } = &mut game_main.mach_data[mach.0].spec else { panic!(); };
+                                        let MachineSpec::Conveyor { item, filter: _, can_move_time_us, can_dump_time_us } = &mut game_main.mach_data[mach.0].spec else { panic!(); };
 
-                                    *can_move_time_us = game_main.time_us + CONVEY_TAKE_PERIOD;
-                                    *can_dump_time_us = game_main.time_us + CONVEY_DUMP_PERIOD;
+                                        *can_move_time_us = game_main.time_us + CONVEY_TAKE_PERIOD;
+                                        *can_dump_time_us = game_main.time_us + CONVEY_DUMP_PERIOD;
 
-                                    let back_feral_d = &mut game_main.feral_data[back_feral.0];
+                                        let back_feral_d = &mut game_main.feral_data[back_feral.0];
 
-                                    let taken = take_items(&mut back_feral_d.slots, item.itemtype, 1);
+                                        let taken = take_items(&mut back_feral_d.slots, item.itemtype, 1);
 
-                                    if taken == 1 {
-                                        item.count += 1;
+                                        if taken == 1 {
+                                            item.count += 1;
 
-                                        if back_feral_d.slots.iter().all(|x| x.is_none()) {
-                                            game_main.feral_by_tile.remove(&(back.x as u8, back.y as u8));
-                                            game_main.feral_ids.remove(back_feral);
+                                            if back_feral_d.slots.iter().all(|x| x.is_none()) {
+                                                game_main.feral_by_tile.remove(back);
+                                                game_main.feral_ids.remove(back_feral);
+                                            }
                                         }
-                                    }
 
-                                } else {
-                                    let MachineSpec::Conveyor { item, filter: _, can_move_time_us, can_dump_time_us } = &mut game_main.mach_data[mach.0].spec else { panic!(); };
-
-                                    *can_move_time_us = game_main.time_us + CONVEY_TAKE_PERIOD;
-                                    *can_dump_time_us = game_main.time_us + CONVEY_DUMP_PERIOD;
+                                    } else {
+                                        let MachineSpec::Conveyor { item, filter: _, can_move_time_us, can_dump_time_us } = &mut game_main.mach_data[mach.0].spec else { panic!(); };
 
-                                    let back_feral_d = &mut game_main.feral_data[back_feral.0];
-                                    let slot_take = back_feral_d.slots[0].as_mut().unwrap();
+                                        *can_move_time_us = game_main.time_us + CONVEY_TAKE_PERIOD;
+                                        *can_dump_time_us = game_main.time_us + CONVEY_DUMP_PERIOD;
 
-                                    item.itemtype = slot_take.itemtype;
-                                    item.count += 1;
-                                    slot_take.count -= 1;
+                                        let back_feral_d = &mut game_main.feral_data[back_feral.0];
+                                        let slot_take = back_feral_d.slots[0].as_mut().unwrap();
 
-                                    if slot_take.count == 0 {
-                                        back_feral_d.slots[0] = None;
-                                        back_feral_d.slots.rotate_left(1);
-                                        if back_feral_d.slots.iter().all(|x| x.is_none()) {
-                                            game_main.feral_by_tile.remove(&(back.x as u8, back.y as u8));
-                                            game_main.feral_ids.remove(back_feral);
+                                        item.itemtype = slot_take.itemtype;
+                                        item.count += 1;
+                                        slot_take.count -= 1;
+
+                                        if slot_take.count == 0 {
+                                            back_feral_d.slots[0] = None;
+                                            back_feral_d.slots.rotate_left(1);
+                                            if back_feral_d.slots.iter().all(|x| x.is_none()) {
+                                                game_main.feral_by_tile.remove(back);
+                                                game_main.feral_ids.remove(back_feral);
+                                            }
                                         }
                                     }
                                 }
                             }
-                        }
-                    } else if item.count != 0 && can_dump_time_us < game_main.time_us {
-
-                        // dump item to front
-
-                        let front = pos.as_ivec2() + forward;
-
-                        if 0 <= front.x && front.x < game_main.world_size.x as i32
-                           && 0 <= front.y && front.y < game_main.world_size.y as i32 {
-
-                            if let Some(front_mach) = game_main.mach_by_tile.get(&(front.x as u8, front.y as u8)) {
-                                match &mut game_main.mach_data[front_mach.0].spec {
-                                    MachineSpec::Turret { ammo, can_fire_time_us: _ } => {
-                                        if item.itemtype == ITEM_BULLET && *ammo < 69 {
-                                            // Refill turret in front
-                                            *ammo += 1;
-
-                                            let MachineSpec::Conveyor { item, filter: _, can_move_time_us: _, can_dump_time_us} = &mut game_main.mach_data[mach.0].spec else { panic!(); };
-                                            item.count -= 1;
-                                            *can_dump_time_us = game_main.time_us + CONVEY_DUMP_PERIOD;
-                                        }
-                                    },
-                                    MachineSpec::Conveyor { item: other_item, filter: _, can_move_time_us: _, can_dump_time_us: other_can_dump_time_us } => {
-                                        if other_item.count == 0 {
-                                            other_item.itemtype = item.itemtype;
-                                            other_item.count += 1;
-
-                                            *other_can_dump_time_us = game_main.time_us + CONVEY_DUMP_PERIOD;
-
-                                            let MachineSpec::Conveyor { item, filter: _, can_move_time_us: _, can_dump_time_us} = &mut game_main.mach_data[mach.0].spec else { panic!(); };
-                                            item.count -= 1;
-                                            *can_dump_time_us = game_main.time_us + CONVEY_DUMP_PERIOD;
-                                       }
-                                    },
-                                    _ => {}
-                                }
-                            } else if filter.not() && item.count == 1 && item.itemtype == ITEM_DEAD_DRONE && mq::gen_range(0, 20) == 1 {
-                                // chance to disassemble drones
+                        } else if item.count != 0 && can_dump_time_us < game_main.time_us {
+
+                            // dump item to front
+
+                            if let Some(front) = TilePos::from(pos).offset(forward, game_main.world_size) {
+
+                                if let Some(front_mach) = game_main.mach_by_tile.get(front) {
+                                    match &mut game_main.mach_data[front_mach.0].spec {
+                                        MachineSpec::Turret { ammo, can_fire_time_us: _, .. } => {
+                                            if item.itemtype == ITEM_BULLET && *ammo < 69 {
+                                                // Refill turret in front
+                                                *ammo += 1;
+
+                                                let MachineSpec::Conveyor { item, filter: _, can_move_time_us: _, can_dump_time_us} = &mut game_main.mach_data[mach.0].spec else { panic!(); };
+                                                item.count -= 1;
+                                                *can_dump_time_us = game_main.time_us + CONVEY_DUMP_PERIOD;
+                                            }
+                                        },
+                                        MachineSpec::Conveyor { item: other_item, filter: _, can_move_time_us: _, can_dump_time_us: other_can_dump_time_us } => {
+                                            if other_item.count == 0 {
+                                                other_item.itemtype = item.itemtype;
+                                                other_item.count += 1;
+
+                                                *other_can_dump_time_us = game_main.time_us + CONVEY_DUMP_PERIOD;
+
+                                                let MachineSpec::Conveyor { item, filter: _, can_move_time_us: _, can_dump_time_us} = &mut game_main.mach_data[mach.0].spec else { panic!(); };
+                                                item.count -= 1;
+                                                *can_dump_time_us = game_main.time_us + CONVEY_DUMP_PERIOD;
+                                           }
+                                        },
+                                        _ => {}
+                                    }
+                                } else if filter.not() && item.count == 1 && item.itemtype == ITEM_DEAD_DRONE && mq::gen_range(0, 20) == 1 {
+                                    // chance to disassemble drones
 
-                                let place_success = place_item(&game_main.itemtype_data, &mut game_main.feral_ids, &mut game_main.feral_data, &mut game_main.feral_by_tile, front.as_uvec2(), ItemSlot { itemtype: ITEM_SCRAP, count: 1 }).is_ok();
+                                    let place_success = place_item(&game_main.itemtype_data, &mut game_main.feral_ids, &mut game_main.feral_data, &mut game_main.feral_by_tile, front.as_uvec2(), ItemSlot { itemtype: ITEM_SCRAP, count: 1 }).is_ok();
 
-                                if mq::gen_range(0, 5) == 1 {
-                                    // chance to make additional alignite
-                                    place_item(&game_main.itemtype_data, &mut game_main.feral_ids, &mut game_main.feral_data, &mut game_main.feral_by_tile, front.as_uvec2(), ItemSlot { itemtype: ITEM_ALIGNITE, count: 1 }).ok();
-                                }
+                                    if mq::gen_range(0, 5) == 1 {
+                                        // chance to make additional alignite
+                                        place_item(&game_main.itemtype_data, &mut game_main.feral_ids, &mut game_main.feral_data, &mut game_main.feral_by_tile, front.as_uvec2(), ItemSlot { itemtype: ITEM_ALIGNITE, count: 1 }).ok();
+                                    }
 
-                                if place_success {
+                                    if place_success {
 
-                                    let MachineSpec::Conveyor { item, filter: _, can_move_time_us: _, can_dump_time_us} = &mut game_main.mach_data[mach.0].spec else { panic!(); };
-                                    item.itemtype = ITEM_BATTERY;
+                                        let MachineSpec::Conveyor { item, filter: _, can_move_time_us: _, can_dump_time_us} = &mut game_main.mach_data[mach.0].spec else { panic!(); };
+                                        item.itemtype = ITEM_BATTERY;
 
-                                    *can_dump_time_us = game_main.time_us + CONVEY_DUMP_PERIOD;
-                                }
+                                        *can_dump_time_us = game_main.time_us + CONVEY_DUMP_PERIOD;
+                                    }
 
-                            } else if filter.not() && item.count == 1 && item.itemtype == ITEM_CLUMP && mq::gen_range(0, 200) == 1 {
-                                // chance to misalign alignite clumps
+                                } else if filter.not() && item.count == 1 && item.itemtype == ITEM_CLUMP && mq::gen_range(0, 200) == 1 {
+                                    // chance to misalign alignite clumps
 
-                                let place_success = place_item(&game_main.itemtype_data, &mut game_main.feral_ids, &mut game_main.feral_data, &mut game_main.feral_by_tile, front.as_uvec2(), ItemSlot { itemtype: ITEM_OBFUSCATOR, count: 1 }).is_ok();
+                                    let place_success = place_item(&game_main.itemtype_data, &mut game_main.feral_ids, &mut game_main.feral_data, &mut game_main.feral_by_tile, front.as_uvec2(), ItemSlot { itemtype: ITEM_OBFUSCATOR, count: 1 }).is_ok();
 
-                                if place_success {
-                                    // all good, all 1 items have been dispensed and nothing exploded
-                                    let MachineSpec::Conveyor { item, filter: _, can_move_time_us: _, can_dump_time_us} = &mut game_main.mach_data[mach.0].spec else { panic!(); };
-                                    item.count -= 1;
+                                    if place_success {
+                                        // all good, all 1 items have been dispensed and nothing exploded
+                                        let MachineSpec::Conveyor { item, filter: _, can_move_time_us: _, can_dump_time_us} = &mut game_main.mach_data[mach.0].spec else { panic!(); };
+                                        item.count -= 1;
 
-                                    *can_dump_time_us = game_main.time_us + CONVEY_DUMP_PERIOD;
-                                }
+                                        *can_dump_time_us = game_main.time_us + CONVEY_DUMP_PERIOD;
+                                    }
 
-                            } else {
-                                let place_success = place_item(&game_main.itemtype_data, &mut game_main.feral_ids, &mut game_main.feral_data, &mut game_main.feral_by_tile, front.as_uvec2(), ItemSlot { itemtype: item.itemtype, count: 1 }).is_ok();
+                                } else {
+                                    let place_success = place_item(&game_main.itemtype_data, &mut game_main.feral_ids, &mut game_main.feral_data, &mut game_main.feral_by_tile, front.as_uvec2(), ItemSlot { itemtype: item.itemtype, count: 1 }).is_ok();
 
-                                if place_success {
-                                    // all good, all 1 items have been dispensed and nothing exploded
-                                    let MachineSpec::Conveyor { item, filter: _, can_move_time_us: _, can_dump_time_us} = &mut game_main.mach_data[mach.0].spec else { panic!(); };
-                                    item.count -= 1;
+                                    if place_success {
+                                        // all good, all 1 items have been dispensed and nothing exploded
+                                        let MachineSpec::Conveyor { item, filter: _, can_move_time_us: _, can_dump_time_us} = &mut game_main.mach_data[mach.0].spec else { panic!(); };
+                                        item.count -= 1;
 
-                                    *can_dump_time_us = game_main.time_us + CONVEY_DUMP_PERIOD;
+                                        *can_dump_time_us = game_main.time_us + CONVEY_DUMP_PERIOD;
+                                    }
                                 }
                             }
                         }
                     }
                 }
             }
+
+            game_main.time_us += (sim_delta * 1000000.0) as u64;
         }
 
 
         // Player Shoot
-        if game_main.player_gun_ammo != 0 && matches!(game_main.tool, ToolMode::GunPod) && mq::is_mouse_button_down(mq::MouseButton::Left) {
+        if game_main.player_gun_ammo != 0 && matches!(game_main.tool, ToolMode::GunPod) && input.down(Action::Shoot) && game_draw.mouse_on_ui.not() && game_main.time.paused.not() {
 
             if game_main.player_gun_cooldown <= 0.0 {
 
                 let mouse_dir = (game_draw.mouse_pos - game_main.player_pos).normalize();
                 let dir = Mat2::from_cols(mouse_dir, rot_cw_90(mouse_dir)).mul_vec2(vec2(100.0, (mq::gen_range(-1.0, 1.0) as f32).powf(3.0)).normalize());
 
-                mq::stop_sound(&shoot0_sound);
-                mq::play_sound(&shoot0_sound, mq::PlaySoundParams { looped: false, volume: 0.5 });
+                game_main.sounds.push(AudioEvent::flat(SoundKind::PlayerShoot, 0.5));
 
                 let bullet = game_main.bullet_ids.create().unwrap();
                 game_main.bullet_pos[bullet.0]  = game_main.player_pos + dir * 0.5*TILE_SIZE;
                 game_main.bullet_data[bullet.0] = Bullet{dir, speed: 1200.0, travel: 0.0, travel_max: 800.0};
+                game_main.events.push(GameEvent::PlayerFired{ pos: game_main.bullet_pos[bullet.0], dir });
 
                 game_main.player_gun_cooldown += 0.4 - 0.25 * f32::min(1.0, (game_main.player_gun_consecutive as f32) / 12.0).powf(0.5);
                 game_main.player_gun_consecutive += 1;
@@ -910,8 +954,11 @@ async fn main() {
 
         //frame_count += 1;
 
-        game_main.time_us += (delta * 1000000.0) as u64;
+        audio.play(&game_main.sounds, game_draw.camera.center, delta);
+        audio.update_music(game_main.intensity.level, delta);
+        game_main.sounds.clear();
 
+        input.end_frame();
         mq::next_frame().await
     }
 }
@@ -0,0 +1,258 @@
+use crate::audio::{Audio, SoundCategory};
+use crate::draw::{mq, GameDraw, SHAKE_MAX};
+use crate::input::{Action, Binding, Bindings, Input};
+
+use std::fmt;
+use std::fmt::Write;
+use std::ops::Not;
+use glam::{uvec2, UVec2};
+
+/// Window sizes the settings menu cycles through
+pub const WINDOW_SIZES: [UVec2; 4] = [uvec2(1024, 576), uvec2(1280, 720), uvec2(1600, 900), uvec2(1920, 1080)];
+pub const UI_SCALE_MIN: f32 = 0.5;
+pub const UI_SCALE_MAX: f32 = 3.0;
+
+/// Options picked in the settings menu and kept in a settings file between runs. Anything left
+/// out of the file keeps its default.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Settings {
+    /// 0 to 1, like the other volumes
+    pub master_volume:  f32,
+    pub sfx_volume:     f32,
+    pub music_volume:   f32,
+    pub music_muted:    bool,
+    pub window_size:    UVec2,
+    pub fullscreen:     bool,
+    /// size of menus and tooltips
+    pub ui_scale:       f32,
+    pub screen_shake:   bool,
+    /// muzzle flashes and explosions
+    pub flashes:        bool,
+    /// the bindings file, plus changes made in the settings menu
+    pub bindings:       Bindings
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings{
+            master_volume:  1.0,
+            sfx_volume:     1.0,
+            music_volume:   0.6,
+            music_muted:    false,
+            window_size:    WINDOW_SIZES[1],
+            fullscreen:     false,
+            ui_scale:       1.0,
+            screen_shake:   true,
+            flashes:        true,
+            bindings:       Bindings::default()
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SettingsError {
+    Io(String, std::io::Error),
+    Parse{ line: usize, msg: String }
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::Io(path, err)        => write!(f, "can't read {}: {}", path, err),
+            SettingsError::Parse{ line, msg }   => write!(f, "line {}: {}", line, msg)
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+impl Settings {
+
+    /// Defaults, with bindings from the bindings file
+    pub fn with_bindings(bindings: Bindings) -> Settings {
+        Settings{ bindings, ..Default::default() }
+    }
+
+    /// Starts from defaults with base for bindings. See to_text for the format.
+    pub fn parse(text: &str, base: &Bindings) -> Result<Settings, SettingsError> {
+        let mut settings = Settings::with_bindings(base.clone());
+
+        for (i, line) in text.lines().enumerate() {
+            let line_no = i + 1;
+            let err = |msg: String| SettingsError::Parse{ line: line_no, msg };
+
+            let line = line.split('#').next().unwrap().trim();
+            let mut words = line.split_whitespace();
+            let Some(name) = words.next() else { continue };
+            let rest: Vec<&str> = words.collect();
+
+            let one = || match rest[..] {
+                [value] => Ok(value),
+                _ => Err(err(format!("expected one value after {}", name)))
+            };
+            let volume = || one()?.parse::<f32>().ok().filter(|v| (0.0..=1.0).contains(v)).ok_or_else(|| err(format!("{} should be a number from 0 to 1", name)));
+            let flag = || match one()? {
+                "true"  => Ok(true),
+                "false" => Ok(false),
+                other   => Err(err(format!("'{}' isn't true or false", other)))
+            };
+
+            match name {
+                "master_volume" => settings.master_volume = volume()?,
+                "sfx_volume"    => settings.sfx_volume = volume()?,
+                "music_volume"  => settings.music_volume = volume()?,
+                "music_muted"   => settings.music_muted = flag()?,
+                "fullscreen"    => settings.fullscreen = flag()?,
+                "screen_shake"  => settings.screen_shake = flag()?,
+                "flashes"       => settings.flashes = flag()?,
+                "ui_scale" => {
+                    settings.ui_scale = one()?.parse::<f32>().ok().filter(|s| (UI_SCALE_MIN..=UI_SCALE_MAX).contains(s))
+                        .ok_or_else(|| err(format!("ui_scale should be a number from {} to {}", UI_SCALE_MIN, UI_SCALE_MAX)))?;
+                },
+                "window_size" => {
+                    let size = match rest[..] {
+                        [w, h] => w.parse::<u32>().ok().zip(h.parse::<u32>().ok()),
+                        _ => None
+                    };
+                    settings.window_size = size.map(|(w, h)| uvec2(w, h)).filter(|s| s.cmpge(uvec2(320, 240)).all())
+                        .ok_or_else(|| err("window_size should be a width and height of at least 320 240".to_owned()))?;
+                },
+                "bind" => {
+                    let Some((action, bindings)) = rest.split_first() else {
+                        return Err(err("expected: bind <action> <binding> ...".to_owned()));
+                    };
+                    let action = Action::from_name(action).ok_or_else(|| err(format!("no action named '{}'", action)))?;
+                    let list = bindings.iter().map(|w| Binding::parse(w).ok_or_else(|| err(format!("'{}' isn't a binding", w))))
+                        .collect::<Result<Vec<Binding>, SettingsError>>()?;
+                    settings.bindings.set(action, &list);
+                },
+                _ => return Err(err(format!("no setting named '{}'", name)))
+            }
+        }
+        Ok(settings)
+    }
+
+    pub fn load(path: &str, base: &Bindings) -> Result<Settings, SettingsError> {
+        let text = std::fs::read_to_string(path).map_err(|e| SettingsError::Io(path.to_owned(), e))?;
+        Settings::parse(&text, base)
+    }
+
+    /// Bindings only get written for actions that are different from base, so changes to the
+    /// bindings file still show up for everything else.
+    pub fn to_text(&self, base: &Bindings) -> String {
+        let mut out = String::from("# Written by the settings menu. Anything left out gets its default.\n\n");
+        writeln!(out, "master_volume   {}", self.master_volume).unwrap();
+        writeln!(out, "sfx_volume      {}", self.sfx_volume).unwrap();
+        writeln!(out, "music_volume    {}", self.music_volume).unwrap();
+        writeln!(out, "music_muted     {}", self.music_muted).unwrap();
+        writeln!(out, "window_size     {} {}", self.window_size.x, self.window_size.y).unwrap();
+        writeln!(out, "fullscreen      {}", self.fullscreen).unwrap();
+        writeln!(out, "ui_scale        {}", self.ui_scale).unwrap();
+        writeln!(out, "screen_shake    {}", self.screen_shake).unwrap();
+        writeln!(out, "flashes         {}", self.flashes).unwrap();
+
+        for action in Action::ALL {
+            let mine: Vec<Binding> = self.bindings.get(action).collect();
+            if mine != base.get(action).collect::<Vec<Binding>>() {
+                out.push_str("bind ");
+                out.push_str(action.name());
+                for binding in mine {
+                    out.push(' ');
+                    out.push_str(&binding.to_string());
+                }
+                out.push('\n');
+            }
+        }
+        out
+    }
+
+    pub fn save(&self, path: &str, base: &Bindings) -> std::io::Result<()> {
+        std::fs::write(path, self.to_text(base))
+    }
+
+    /// Window settings for macroquad to start with
+    pub fn window_conf(&self, title: &str) -> mq::Conf {
+        mq::Conf{
+            window_title:   title.to_owned(),
+            window_width:   self.window_size.x as i32,
+            window_height:  self.window_size.y as i32,
+            fullscreen:     self.fullscreen,
+            ..Default::default()
+        }
+    }
+
+    /// Everything except the window, which only changes with apply_window
+    pub fn apply(&self, audio: &mut Audio, draw: &mut GameDraw, input: &mut Input) {
+        audio.mixer.master = self.master_volume;
+        for category in [SoundCategory::Interface, SoundCategory::Player, SoundCategory::World] {
+            audio.mixer.volumes[category as usize] = self.sfx_volume;
+        }
+        audio.mixer.volumes[SoundCategory::Music as usize] = self.music_volume;
+        audio.music.muted = self.music_muted;
+
+        draw.ui.scale = self.ui_scale;
+        draw.camera.shake_max = if self.screen_shake { SHAKE_MAX } else { 0.0 };
+        draw.world.flashes = self.flashes;
+
+        input.bindings = self.bindings.clone();
+    }
+
+    /// Resizes the window if it's different from before
+    pub fn apply_window(&self, before: &Settings) {
+        if self.fullscreen != before.fullscreen {
+            mq::set_fullscreen(self.fullscreen);
+        }
+        if self.window_size != before.window_size && self.fullscreen.not() {
+            mq::request_new_screen_size(self.window_size.x as f32, self.window_size.y as f32);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_parse() {
+        let base = Bindings::default();
+        let mut settings = Settings::parse("
+            # comment
+            music_volume    0.25
+            window_size     1600 900
+            flashes         false   # trailing comment
+            bind reload key:t pad:north
+        ", &base).unwrap();
+        assert_eq!(settings.music_volume, 0.25);
+        assert_eq!(settings.window_size, uvec2(1600, 900));
+        assert!(settings.flashes.not() && settings.screen_shake);
+        assert_eq!(settings.bindings.hint(Action::Reload), "[T]");
+
+        // round trips, and only changed bindings get written
+        settings.ui_scale = 1.5;
+        let text = settings.to_text(&base);
+        assert_eq!(text.matches("bind ").count(), 1);
+        assert_eq!(Settings::parse(&text, &base).unwrap(), settings);
+
+        let line_of = |text| match Settings::parse(text, &base) {
+            Err(SettingsError::Parse{ line, .. }) => line,
+            _ => 0
+        };
+        assert_eq!(line_of("master_volume 2"), 1);
+        assert_eq!(line_of("\nfullscreen yes"), 2);
+        assert_eq!(line_of("window_size 10 10"), 1);
+        assert_eq!(line_of("ui_scale 0.1"), 1);
+        assert_eq!(line_of("bind nope key:a"), 1);
+        assert_eq!(line_of("volume 1"), 1);
+
+        // rebinding a key keeps the mouse and pad bindings
+        let mut bindings = base.clone();
+        assert!(bindings.set_key(Action::Use, mq::KeyCode::U));
+        assert_eq!(bindings.hint(Action::Use), "[U]");
+        assert_eq!(bindings.get(Action::Use).count(), base.get(Action::Use).count() + 1);
+        assert!(bindings.set_key(Action::Use, mq::KeyCode::World1).not());
+
+        // a missing file is the caller's cue to use defaults
+        assert!(matches!(Settings::load("tf/custom/nope.txt", &base), Err(SettingsError::Io(..))));
+    }
+}